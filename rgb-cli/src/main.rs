@@ -1,10 +1,219 @@
-use rgb_core::{io, mmu::Mmu, system::GameBoy};
+use rgb_core::{
+    archive,
+    cartridge::Cartridge,
+    harness::{self, DEFAULT_TIMEOUT_FRAMES, Outcome, TestRom},
+    io,
+    mmu::Mmu,
+    model::Model,
+    patch::PATCH_EXTENSIONS,
+    system::GameBoy,
+    video::{
+        ColorCorrection, Palette,
+        filters::{Filter, Ghosting},
+    },
+};
+use std::{
+    env, fs,
+    path::{Path, PathBuf},
+    process, thread,
+};
 
-fn main() {
-    // Create GameBoy with default Mmu (uses dummy cartridge)
-    let gameboy: GameBoy<Mmu> = GameBoy::default();
+const USAGE: &str = "Usage: rgb-cli [ROM] [--model dmg|sgb|cgb] [--boot-rom FILE] [--patch FILE.ips|bps|ups] [--no-patch] \
+                     [--frames N] [--screenshot FILE.ppm] [--trace] [--palette classic|grayscale|pocket|light] \
+                     [--color-correction none|curves|lcd] \
+                     [--filter none|scale2x|scale3x|hq2x|lcd] [--ghosting]\n       \
+                     rgb-cli test ROM|DIR [--timeout FRAMES] [--jobs N]";
+
+/// Command line options
+struct Options {
+    rom: Option<String>,
+    /// Hardware model; when None, it follows the boot ROM or the header
+    model: Option<Model>,
+    boot_rom: Option<String>,
+    /// Patch to apply; when None, one named after the ROM is looked up
+    patch: Option<String>,
+    no_patch: bool,
+    frames: u32,
+    screenshot: Option<String>,
+    palette: Palette,
+    color_correction: ColorCorrection,
+    trace: bool,
+    filter: Filter,
+    ghosting: bool,
+}
+
+fn parse_args(args: Vec<String>) -> Result<Options, String> {
+    let mut options = Options {
+        rom: None,
+        model: None,
+        boot_rom: None,
+        patch: None,
+        no_patch: false,
+        frames: 60,
+        screenshot: None,
+        palette: Palette::default(),
+        color_correction: ColorCorrection::default(),
+        trace: false,
+        filter: Filter::default(),
+        ghosting: false,
+    };
+
+    let mut args = args.into_iter();
+    while let Some(arg) = args.next() {
+        let mut value = || args.next().ok_or(format!("Missing value for {}", arg));
+        match arg.as_str() {
+            "--frames" => {
+                options.frames = value()?
+                    .parse()
+                    .map_err(|e| format!("Invalid frame count: {}", e))?;
+            }
+            "--model" => {
+                let name = value()?;
+                options.model =
+                    Some(Model::from_name(&name).ok_or(format!("Unknown model '{}'", name))?);
+            }
+            "--boot-rom" => options.boot_rom = Some(value()?),
+            "--patch" => options.patch = Some(value()?),
+            "--no-patch" => options.no_patch = true,
+            "--screenshot" => options.screenshot = Some(value()?),
+            "--trace" => options.trace = true,
+            "--ghosting" => options.ghosting = true,
+            "--filter" => {
+                let name = value()?;
+                options.filter =
+                    Filter::from_name(&name).ok_or(format!("Unknown filter '{}'", name))?;
+            }
+            "--palette" => {
+                let name = value()?;
+                options.palette =
+                    Palette::builtin(&name).ok_or(format!("Unknown palette '{}'", name))?;
+            }
+            "--color-correction" => {
+                let name = value()?;
+                options.color_correction = ColorCorrection::from_name(&name)
+                    .ok_or(format!("Unknown color correction '{}'", name))?;
+            }
+            _ if arg.starts_with("--") => return Err(format!("Unknown option {}", arg)),
+            _ => options.rom = Some(arg),
+        }
+    }
+
+    Ok(options)
+}
+
+/// Write an RGBA frame as a binary PPM image
+fn write_ppm(path: &str, rgba: &[u8], width: usize, height: usize) -> Result<(), String> {
+    let mut data = format!("P6\n{} {}\n255\n", width, height).into_bytes();
+    for pixel in rgba.chunks_exact(4) {
+        data.extend_from_slice(&pixel[..3]);
+    }
+
+    fs::write(path, data).map_err(|e| format!("Failed to write {}: {}", path, e))
+}
+
+/// Find a patch named after the ROM (`game.gb` -> `game.ips`, `game.bps`
+/// or `game.ups`)
+///
+/// Only the last extension is replaced, so a compressed ROM is also looked
+/// up without its archive extension: `game.gb.gz` finds `game.gb.ips` or
+/// `game.ips`, and `game.zip` finds `game.ips`.
+fn find_patch(rom: &Path) -> Option<PathBuf> {
+    let unpacked = rom
+        .file_stem()
+        .and_then(|stem| stem.to_str())
+        .filter(|stem| archive::is_rom_name(stem))
+        .map(|_| rom.with_extension(""));
+
+    std::iter::once(rom.to_path_buf())
+        .chain(unpacked)
+        .flat_map(|base| {
+            PATCH_EXTENSIONS
+                .iter()
+                .map(move |ext| base.with_extension(ext))
+        })
+        .find(|path| path.is_file())
+}
+
+/// Load a ROM (or archive) with the patch chosen by the options applied
+fn load_rom(path: &str, options: &Options) -> Result<Cartridge, String> {
+    let data = fs::read(path).map_err(|e| format!("Failed to read {}: {}", path, e))?;
+
+    let patch_path = match &options.patch {
+        Some(patch) => Some(PathBuf::from(patch)),
+        None if options.no_patch => None,
+        None => find_patch(Path::new(path)),
+    };
+    let patch = patch_path
+        .as_ref()
+        .map(|patch_path| {
+            fs::read(patch_path)
+                .map_err(|e| format!("Failed to read {}: {}", patch_path.display(), e))
+        })
+        .transpose()?;
+
+    let cartridge =
+        Cartridge::from_archive_patched(data, patch.as_deref()).map_err(|e| match &patch_path {
+            Some(patch_path) => format!("Failed to load ROM with {}: {}", patch_path.display(), e),
+            None => format!("Failed to load ROM: {}", e),
+        })?;
+    if let Some(patch_path) = &patch_path {
+        println!("Applied patch {}", patch_path.display());
+    }
+    Ok(cartridge)
+}
+
+fn run(options: Options) -> Result<(), String> {
+    let mut gameboy: GameBoy<Mmu> = match &options.rom {
+        Some(path) => {
+            let cartridge = load_rom(path, &options)?;
+            let boot_rom = options
+                .boot_rom
+                .as_ref()
+                .map(|path| fs::read(path).map_err(|e| format!("Failed to read {}: {}", path, e)))
+                .transpose()?;
+            match (options.model, boot_rom) {
+                (Some(model), Some(boot_rom)) => {
+                    GameBoy::with_boot_rom_model(cartridge, model, boot_rom)?
+                }
+                (None, Some(boot_rom)) => GameBoy::with_boot_rom(cartridge, boot_rom)?,
+                (Some(model), None) => GameBoy::with_model(cartridge, model),
+                (None, None) => GameBoy::with_cartridge(cartridge),
+            }
+        }
+        // Create GameBoy with default Mmu (uses dummy cartridge)
+        None => GameBoy::default(),
+    };
+    gameboy.ppu.set_palette(options.palette);
+    gameboy.ppu.set_color_correction(options.color_correction);
 
-    // Print all initial register values
+    let (width, height) = gameboy.screen_size();
+    let mut ghosting = options.ghosting.then(Ghosting::default);
+
+    if options.rom.is_some() {
+        for frame in 0..options.frames {
+            // Only the frames that end up in the screenshot are drawn: the
+            // last one, and the one before it when ghosting blends them
+            let remaining = options.frames - frame;
+            gameboy.skip_rendering = remaining > 2 || (remaining == 2 && ghosting.is_none());
+            if options.trace {
+                gameboy.run_frame_with(|gameboy| println!("{}", gameboy.trace_line()));
+            } else {
+                gameboy.run_frame();
+            }
+
+            if remaining == 2
+                && let Some(ghosting) = &mut ghosting
+            {
+                let mut rgba = vec![0u8; width * height * 4];
+                gameboy.framebuffer_rgba(&mut rgba);
+                ghosting.apply(&mut rgba);
+            }
+        }
+    }
+
+    println!("Model: {:?}", gameboy.model);
+
+    // Print all register values
     println!("AF: 0x{:04X}", gameboy.af());
     println!("BC: 0x{:04X}", gameboy.bc());
     println!("DE: 0x{:04X}", gameboy.de());
@@ -13,5 +222,89 @@ fn main() {
     println!("PC: 0x{:04X}", gameboy.pc());
 
     // print the P1 memory address
-    println!("P1: 0x{:04X}", gameboy.read(io::P1))
+    println!("P1: 0x{:04X}", gameboy.read(io::P1));
+
+    if let Some(path) = &options.screenshot {
+        let mut rgba = vec![0u8; width * height * 4];
+        gameboy.framebuffer_rgba(&mut rgba);
+        if let Some(ghosting) = &mut ghosting {
+            ghosting.apply(&mut rgba);
+        }
+
+        let mut filtered = Vec::new();
+        options.filter.apply(&rgba, width, height, &mut filtered);
+        let scale = options.filter.scale();
+        write_ppm(path, &filtered, width * scale, height * scale)?;
+        println!("Screenshot saved to {}", path);
+    }
+
+    Ok(())
+}
+
+/// Run mooneye-style test ROMs (a single ROM or every ROM below a
+/// directory) and print a pass/fail table
+///
+/// Returns whether every ROM passed.
+fn run_tests(args: &[String]) -> Result<bool, String> {
+    let mut path = None;
+    let mut timeout_frames = DEFAULT_TIMEOUT_FRAMES;
+    let mut jobs = thread::available_parallelism().map_or(1, |n| n.get());
+
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        let mut value = || args.next().ok_or(format!("Missing value for {}", arg));
+        match arg.as_str() {
+            "--timeout" => {
+                timeout_frames = value()?
+                    .parse()
+                    .map_err(|e| format!("Invalid timeout: {}", e))?;
+            }
+            "--jobs" => {
+                jobs = value()?
+                    .parse()
+                    .map_err(|e| format!("Invalid job count: {}", e))?;
+            }
+            _ if arg.starts_with("--") => return Err(format!("Unknown option {}", arg)),
+            _ => path = Some(Path::new(arg)),
+        }
+    }
+
+    let path = path.ok_or("Missing test ROM or directory")?;
+    let (root, paths) = if path.is_dir() {
+        (path, harness::find_test_roms(path)?)
+    } else {
+        (path.parent().unwrap_or(path), vec![path.to_path_buf()])
+    };
+    if paths.is_empty() {
+        return Err(format!("No ROMs found in {}", path.display()));
+    }
+
+    let roms: Vec<_> = paths
+        .into_iter()
+        .map(|path| TestRom::new(path, timeout_frames))
+        .collect();
+    let results = harness::run_test_roms(&roms, jobs);
+    print!("{}", harness::format_results(&results, root));
+
+    Ok(results
+        .iter()
+        .all(|result| result.outcome == Outcome::Passed))
+}
+
+fn main() {
+    let args: Vec<String> = env::args().skip(1).collect();
+    let result = match args.first().map(String::as_str) {
+        Some("test") => run_tests(&args[1..]),
+        _ => parse_args(args).and_then(run).map(|()| true),
+    };
+
+    match result {
+        Ok(true) => {}
+        Ok(false) => process::exit(1),
+        Err(e) => {
+            eprintln!("{}", e);
+            eprintln!("{}", USAGE);
+            process::exit(1);
+        }
+    }
 }
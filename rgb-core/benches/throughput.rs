@@ -0,0 +1,299 @@
+/// Emulation throughput benchmarks
+///
+/// Measures emulated frames per second of wall-clock time, headless, for
+/// every ROM in `test-roms` and a synthetic busy-loop ROM. Each workload is
+/// measured separately on three paths:
+/// - cpu: `GameBoy<FlatMemory>::step` with the ROM's first 32 KiB mapped flat;
+///   ROMs that need banking or ROM write protection to run are reported as `-`
+/// - step_with_ppu: the full system with the MMU, PPU rendering and DMA
+/// - render_scanline: only `Ppu::render_scanline`, on the VRAM and OAM a
+///   ROM left behind after running for a while
+///
+/// Run with `cargo bench -p rgb-core --bench throughput`. Extra arguments filter workloads by
+/// name; `--save-baseline` rewrites `benches/baseline.txt`. Results are
+/// compared against that file so regressions show up as negative changes.
+/// The baseline is only meaningful on the machine it was recorded on.
+use rgb_core::cartridge::Cartridge;
+use rgb_core::memory::{FlatMemory, Memory};
+use rgb_core::mmu::Mmu;
+use rgb_core::ppu::DOTS_PER_FRAME;
+use rgb_core::system::GameBoy;
+use std::time::{Duration, Instant};
+use std::{env, fs, panic};
+
+const ROM_DIR: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/../test-roms");
+const BASELINE: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/benches/baseline.txt");
+
+/// Frames run before measuring, so ROMs are past their setup code
+const WARMUP_FRAMES: u64 = 60;
+/// Frames per measurement
+const FRAMES: u64 = 300;
+/// Measurements per path; the fastest is reported
+const RUNS: usize = 3;
+
+/// Frames per second on each path
+#[derive(Clone, Copy)]
+struct Throughput {
+    /// None when the ROM can't run on flat memory
+    cpu: Option<f64>,
+    step_with_ppu: f64,
+    render_scanline: f64,
+}
+
+/// A ROM to benchmark
+struct Workload {
+    name: String,
+    rom: Vec<u8>,
+}
+
+/// 32 KiB ROM-only cartridge that loops over a read-modify-write of WRAM
+fn busy_loop_rom() -> Vec<u8> {
+    let mut rom = vec![0; 0x8000];
+
+    // Entry point: NOP; JP 0x0150
+    rom[0x0100..0x0104].copy_from_slice(&[0x00, 0xC3, 0x50, 0x01]);
+    rom[0x0134..0x013D].copy_from_slice(b"BUSY LOOP");
+
+    let mut checksum: u8 = 0;
+    for &byte in &rom[0x0134..=0x014C] {
+        checksum = checksum.wrapping_sub(byte).wrapping_sub(1);
+    }
+    rom[0x014D] = checksum;
+
+    rom[0x0150..0x015D].copy_from_slice(&[
+        0x21, 0x00, 0xC0, // LD HL,0xC000
+        0x06, 0x00, // LD B,0
+        0x7E, // loop: LD A,(HL)
+        0x80, // ADD A,B
+        0x22, // LD (HL+),A
+        0x04, // INC B
+        0xCB, 0x8C, // RES 1,H (stay within 0xC000-0xC1FF)
+        0x18, 0xF8, // JR loop
+    ]);
+    rom
+}
+
+fn workloads() -> Vec<Workload> {
+    let mut workloads = vec![Workload {
+        name: "busy_loop".to_string(),
+        rom: busy_loop_rom(),
+    }];
+
+    let mut paths: Vec<_> = fs::read_dir(ROM_DIR)
+        .expect("Failed to read test-roms")
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| path.extension().is_some_and(|ext| ext == "gb"))
+        .collect();
+    paths.sort();
+
+    for path in paths {
+        let name = path.file_stem().unwrap().to_string_lossy().into_owned();
+        let rom = fs::read(&path).unwrap_or_else(|e| panic!("Failed to read {}: {}", name, e));
+        // Some files in test-roms aren't ROMs (see the Blargg tests)
+        if let Err(e) = Cartridge::from_bytes(rom.clone()) {
+            eprintln!("Skipping {}: {}", name, e);
+            continue;
+        }
+        workloads.push(Workload { name, rom });
+    }
+    workloads
+}
+
+fn system(rom: &[u8]) -> GameBoy<Mmu> {
+    let cartridge = Cartridge::from_bytes(rom.to_vec()).expect("Failed to load ROM");
+    GameBoy::with_cartridge(cartridge)
+}
+
+/// Best frames per second over `RUNS` measurements of `FRAMES` frames
+fn best_of(mut measure: impl FnMut() -> Duration) -> f64 {
+    (0..RUNS)
+        .map(|_| FRAMES as f64 / measure().as_secs_f64())
+        .fold(0.0, f64::max)
+}
+
+/// Without an MBC, bank switches and writes to ROM change the code being
+/// run, which ends in an illegal opcode for some ROMs
+fn bench_cpu(rom: &[u8]) -> Option<f64> {
+    let hook = panic::take_hook();
+    panic::set_hook(Box::new(|_| {}));
+    let result = panic::catch_unwind(|| bench_flat_memory(rom)).ok();
+    panic::set_hook(hook);
+    result
+}
+
+fn bench_flat_memory(rom: &[u8]) -> f64 {
+    let mut memory = FlatMemory::new();
+    for (addr, &byte) in rom.iter().take(0x8000).enumerate() {
+        memory.write(addr as u16, byte);
+    }
+    let mut gameboy = GameBoy::with_memory(memory);
+
+    let mut run = |frames: u64| {
+        let target = gameboy.dot_cycles + frames * DOTS_PER_FRAME;
+        let start = Instant::now();
+        while gameboy.dot_cycles < target {
+            gameboy.step();
+        }
+        start.elapsed()
+    };
+    run(WARMUP_FRAMES);
+    best_of(|| run(FRAMES))
+}
+
+fn bench_step_with_ppu(rom: &[u8]) -> f64 {
+    let mut gameboy = system(rom);
+
+    let mut run = |frames: u64| {
+        let target = gameboy.dot_cycles + frames * DOTS_PER_FRAME;
+        let start = Instant::now();
+        while gameboy.dot_cycles < target {
+            gameboy.step_with_ppu();
+        }
+        start.elapsed()
+    };
+    run(WARMUP_FRAMES);
+    best_of(|| run(FRAMES))
+}
+
+/// Time only the scanline rendering, with the PPU clocked on its own so
+/// the VRAM and OAM stay as the ROM left them
+fn bench_render_scanline(rom: &[u8]) -> f64 {
+    let mut gameboy = system(rom);
+    let target = WARMUP_FRAMES * DOTS_PER_FRAME;
+    while gameboy.dot_cycles < target {
+        gameboy.step_with_ppu();
+    }
+
+    best_of(|| {
+        let mut elapsed = Duration::ZERO;
+        for _ in 0..FRAMES * DOTS_PER_FRAME / 4 {
+            gameboy.ppu.step(4);
+            if gameboy.ppu.should_scan_oam {
+                gameboy.ppu.should_scan_oam = false;
+                gameboy.ppu.scan_oam(gameboy.mmu.oam());
+            }
+            if gameboy.ppu.should_render_scanline {
+                gameboy.ppu.should_render_scanline = false;
+                let start = Instant::now();
+                gameboy
+                    .ppu
+                    .render_scanline(gameboy.mmu.vram(), gameboy.mmu.oam());
+                elapsed += start.elapsed();
+            }
+        }
+        elapsed
+    })
+}
+
+/// Parse `name cpu step_with_ppu render_scanline` lines, skipping comments
+fn load_baseline() -> Vec<(String, Throughput)> {
+    let Ok(text) = fs::read_to_string(BASELINE) else {
+        return Vec::new();
+    };
+
+    text.lines()
+        .filter(|line| !line.starts_with('#'))
+        .filter_map(|line| {
+            let fields: Vec<&str> = line.split_whitespace().collect();
+            let [name, cpu, step_with_ppu, render_scanline] = fields[..] else {
+                return None;
+            };
+            Some((
+                name.to_string(),
+                Throughput {
+                    cpu: cpu.parse().ok(),
+                    step_with_ppu: step_with_ppu.parse().ok()?,
+                    render_scanline: render_scanline.parse().ok()?,
+                },
+            ))
+        })
+        .collect()
+}
+
+fn save_baseline(results: &[(String, Throughput)]) {
+    let mut text = format!(
+        "# Frames per second, release build, best of {} runs of {} frames\n\
+         # Recorded with `cargo bench -p rgb-core --bench throughput -- --save-baseline`\n\
+         # workload cpu step_with_ppu render_scanline\n",
+        RUNS, FRAMES
+    );
+    for (name, result) in results {
+        let cpu = result
+            .cpu
+            .map_or("-".to_string(), |cpu| format!("{:.0}", cpu));
+        text += &format!(
+            "{} {} {:.0} {:.0}\n",
+            name, cpu, result.step_with_ppu, result.render_scanline
+        );
+    }
+    fs::write(BASELINE, text).expect("Failed to write baseline");
+}
+
+/// Format a result with its change against the baseline
+fn cell(value: Option<f64>, baseline: Option<f64>) -> String {
+    match (value, baseline) {
+        (Some(value), Some(baseline)) => {
+            let change = (value / baseline - 1.0) * 100.0;
+            format!("{:.0} ({:+.1}%)", value, change)
+        }
+        (Some(value), None) => format!("{:.0}", value),
+        (None, _) => "-".to_string(),
+    }
+}
+
+fn main() {
+    let args: Vec<String> = env::args().skip(1).collect();
+    let save = args.iter().any(|arg| arg == "--save-baseline");
+    let filters: Vec<&String> = args.iter().filter(|arg| !arg.starts_with("--")).collect();
+
+    let baseline = load_baseline();
+    let mut results = Vec::new();
+
+    println!(
+        "{:<16} {:>20} {:>20} {:>20}",
+        "workload (fps)", "cpu", "step_with_ppu", "render_scanline"
+    );
+    for workload in workloads() {
+        if !filters.is_empty() && !filters.iter().any(|f| workload.name.contains(f.as_str())) {
+            continue;
+        }
+
+        let result = Throughput {
+            cpu: bench_cpu(&workload.rom),
+            step_with_ppu: bench_step_with_ppu(&workload.rom),
+            render_scanline: bench_render_scanline(&workload.rom),
+        };
+        let previous = baseline
+            .iter()
+            .find(|(name, _)| *name == workload.name)
+            .map(|(_, result)| *result);
+
+        println!(
+            "{:<16} {:>20} {:>20} {:>20}",
+            workload.name,
+            cell(result.cpu, previous.and_then(|p| p.cpu)),
+            cell(
+                Some(result.step_with_ppu),
+                previous.map(|p| p.step_with_ppu)
+            ),
+            cell(
+                Some(result.render_scanline),
+                previous.map(|p| p.render_scanline)
+            ),
+        );
+        results.push((workload.name, result));
+    }
+
+    if save {
+        // A filtered run keeps the other workloads' recorded results
+        let mut merged = baseline;
+        for (name, result) in results {
+            match merged.iter_mut().find(|(entry, _)| *entry == name) {
+                Some((_, entry)) => *entry = result,
+                None => merged.push((name, result)),
+            }
+        }
+        save_baseline(&merged);
+        println!("Baseline written to {}", BASELINE);
+    }
+}
@@ -0,0 +1,380 @@
+/// ROM archive extraction
+///
+/// Unpacks ROMs distributed in zip, gzip or 7z containers so they can be
+/// loaded without unpacking them first. Zip and 7z archives often carry a
+/// readme or a save file alongside the ROM; the first entry with a ROM
+/// extension (.gb, .gbc, .sgb) is used. Zip entries must be stored or
+/// deflated; Zip64, multi-disk and encrypted zips aren't supported.
+///
+/// Built with the `archive` feature; 7z extraction also needs `archive-7z`.
+use crate::crc::crc32;
+use miniz_oxide::inflate::decompress_to_vec_with_limit;
+
+/// Largest ROM accepted from an archive (MBC5 maximum, 512 banks)
+pub const MAX_ROM_SIZE: usize = 8 * 1024 * 1024;
+
+/// File extensions picked from multi-file archives
+pub const ROM_EXTENSIONS: [&str; 3] = ["gb", "gbc", "sgb"];
+
+const ZIP_LOCAL_HEADER: u32 = 0x0403_4B50;
+const ZIP_CENTRAL_HEADER: u32 = 0x0201_4B50;
+const ZIP_END_OF_CENTRAL_DIRECTORY: u32 = 0x0605_4B50;
+const GZIP_MAGIC: [u8; 2] = [0x1F, 0x8B];
+const SEVEN_ZIP_MAGIC: [u8; 6] = [b'7', b'z', 0xBC, 0xAF, 0x27, 0x1C];
+
+/// Container format of a file handed to the loader
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Container {
+    /// Not a recognized archive; assumed to be a plain ROM
+    Raw,
+    Zip,
+    Gzip,
+    SevenZip,
+}
+
+impl Container {
+    /// Detect the container from its leading magic bytes
+    pub fn detect(data: &[u8]) -> Self {
+        if data.len() >= 4 && read_u32(data, 0) == ZIP_LOCAL_HEADER {
+            Container::Zip
+        } else if data.starts_with(&GZIP_MAGIC) {
+            Container::Gzip
+        } else if data.starts_with(&SEVEN_ZIP_MAGIC) {
+            Container::SevenZip
+        } else {
+            Container::Raw
+        }
+    }
+}
+
+/// Return the ROM inside `data`, or `data` itself if it isn't an archive
+pub fn extract_rom(data: Vec<u8>) -> Result<Vec<u8>, String> {
+    match Container::detect(&data) {
+        Container::Raw => Ok(data),
+        Container::Zip => extract_zip(&data),
+        Container::Gzip => extract_gzip(&data),
+        Container::SevenZip => extract_7z(&data),
+    }
+}
+
+/// Whether an archive entry name looks like a ROM
+///
+/// macOS resource forks (`__MACOSX/`, `._name.gb`) share the ROM's
+/// extension but aren't ROMs.
+pub fn is_rom_name(name: &str) -> bool {
+    let file_name = name.rsplit(['/', '\\']).next().unwrap_or(name);
+    if name.starts_with("__MACOSX/") || file_name.starts_with("._") {
+        return false;
+    }
+    file_name.rsplit_once('.').is_some_and(|(_, ext)| {
+        ROM_EXTENSIONS
+            .iter()
+            .any(|rom_ext| ext.eq_ignore_ascii_case(rom_ext))
+    })
+}
+
+fn read_u16(data: &[u8], offset: usize) -> u16 {
+    u16::from_le_bytes([data[offset], data[offset + 1]])
+}
+
+fn read_u32(data: &[u8], offset: usize) -> u32 {
+    u32::from_le_bytes([
+        data[offset],
+        data[offset + 1],
+        data[offset + 2],
+        data[offset + 3],
+    ])
+}
+
+/// Slice `len` bytes at `offset`, or an error if the archive is cut short
+fn field(data: &[u8], offset: usize, len: usize) -> Result<&[u8], String> {
+    offset
+        .checked_add(len)
+        .and_then(|end| data.get(offset..end))
+        .ok_or_else(|| "Archive is truncated".to_string())
+}
+
+/// A file listed in a zip's central directory
+struct ZipEntry {
+    name: String,
+    flags: u16,
+    method: u16,
+    crc: u32,
+    compressed_size: usize,
+    size: usize,
+    local_header_offset: usize,
+}
+
+/// Read the central directory, which (unlike the local headers) always has
+/// the sizes even when the archive was written as a stream
+fn zip_entries(data: &[u8]) -> Result<Vec<ZipEntry>, String> {
+    // The end record is 22 bytes plus a comment of up to 64 KiB
+    let search_start = data.len().saturating_sub(22 + 0xFFFF);
+    let end = (search_start..data.len().saturating_sub(21))
+        .rev()
+        .find(|&offset| read_u32(data, offset) == ZIP_END_OF_CENTRAL_DIRECTORY)
+        .ok_or("Zip end of central directory not found")?;
+
+    let count = read_u16(data, end + 10) as usize;
+    let mut offset = read_u32(data, end + 16) as usize;
+    if count == 0xFFFF || offset == 0xFFFF_FFFF {
+        return Err("Zip64 archives are not supported".to_string());
+    }
+
+    let mut entries = Vec::with_capacity(count);
+    for _ in 0..count {
+        let header = field(data, offset, 46)?;
+        if read_u32(header, 0) != ZIP_CENTRAL_HEADER {
+            return Err("Invalid zip central directory".to_string());
+        }
+        let name_len = read_u16(header, 28) as usize;
+        let extra_len = read_u16(header, 30) as usize;
+        let comment_len = read_u16(header, 32) as usize;
+        let name = field(data, offset + 46, name_len)?;
+
+        entries.push(ZipEntry {
+            name: String::from_utf8_lossy(name).into_owned(),
+            flags: read_u16(header, 8),
+            method: read_u16(header, 10),
+            crc: read_u32(header, 16),
+            compressed_size: read_u32(header, 20) as usize,
+            size: read_u32(header, 24) as usize,
+            local_header_offset: read_u32(header, 42) as usize,
+        });
+        offset += 46 + name_len + extra_len + comment_len;
+    }
+    Ok(entries)
+}
+
+fn extract_zip(data: &[u8]) -> Result<Vec<u8>, String> {
+    let entries = zip_entries(data)?;
+    let entry = entries
+        .iter()
+        .find(|entry| is_rom_name(&entry.name))
+        .ok_or("No .gb or .gbc file found in zip archive")?;
+
+    if entry.flags & 0x0001 != 0 {
+        return Err(format!("{} is encrypted", entry.name));
+    }
+    if entry.size > MAX_ROM_SIZE {
+        return Err(format!("{} is too large for a ROM", entry.name));
+    }
+
+    // The local header's name and extra field can differ from the central
+    // directory's, so its lengths are read again
+    let offset = entry.local_header_offset;
+    let header = field(data, offset, 30)?;
+    if read_u32(header, 0) != ZIP_LOCAL_HEADER {
+        return Err("Invalid zip local header".to_string());
+    }
+    let data_start = offset + 30 + read_u16(header, 26) as usize + read_u16(header, 28) as usize;
+    let compressed = field(data, data_start, entry.compressed_size)?;
+
+    let rom = match entry.method {
+        0 => compressed.to_vec(),
+        8 => decompress_to_vec_with_limit(compressed, MAX_ROM_SIZE)
+            .map_err(|e| format!("Failed to inflate {}: {}", entry.name, e))?,
+        method => {
+            return Err(format!(
+                "{} uses unsupported zip compression method {}",
+                entry.name, method
+            ));
+        }
+    };
+
+    if rom.len() != entry.size || crc32(&rom) != entry.crc {
+        return Err(format!("{} is corrupt (CRC mismatch)", entry.name));
+    }
+    Ok(rom)
+}
+
+/// Unpack a single-member gzip file (RFC 1952)
+fn extract_gzip(data: &[u8]) -> Result<Vec<u8>, String> {
+    const FHCRC: u8 = 0x02;
+    const FEXTRA: u8 = 0x04;
+    const FNAME: u8 = 0x08;
+    const FCOMMENT: u8 = 0x10;
+
+    let header = field(data, 0, 10)?;
+    if header[2] != 8 {
+        return Err(format!("Unsupported gzip compression method {}", header[2]));
+    }
+    let flags = header[3];
+
+    let mut offset = 10;
+    if flags & FEXTRA != 0 {
+        offset += 2 + read_u16(field(data, offset, 2)?, 0) as usize;
+    }
+    for flag in [FNAME, FCOMMENT] {
+        if flags & flag != 0 {
+            let rest = field(data, offset, data.len().saturating_sub(offset))?;
+            let len = rest
+                .iter()
+                .position(|&byte| byte == 0)
+                .ok_or("Archive is truncated")?;
+            offset += len + 1;
+        }
+    }
+    if flags & FHCRC != 0 {
+        offset += 2;
+    }
+
+    let body = field(data, offset, data.len().saturating_sub(offset))?;
+    let rom = decompress_to_vec_with_limit(body, MAX_ROM_SIZE)
+        .map_err(|e| format!("Failed to inflate gzip data: {}", e))?;
+
+    // The trailer (CRC-32 and size) ends the member
+    let trailer = field(data, data.len().saturating_sub(8), 8)?;
+    if crc32(&rom) != read_u32(trailer, 0) || rom.len() as u32 != read_u32(trailer, 4) {
+        return Err("Gzip data is corrupt (CRC mismatch)".to_string());
+    }
+    Ok(rom)
+}
+
+#[cfg(feature = "archive-7z")]
+fn extract_7z(data: &[u8]) -> Result<Vec<u8>, String> {
+    use sevenz_rust::{Password, SevenZReader};
+    use std::io::Cursor;
+
+    let mut reader = SevenZReader::new(Cursor::new(data), data.len() as u64, Password::empty())
+        .map_err(|e| format!("Failed to open 7z archive: {}", e))?;
+
+    let mut rom = None;
+    reader
+        .for_each_entries(|entry, entry_reader| {
+            if entry.is_directory() || !is_rom_name(entry.name()) {
+                return Ok(true);
+            }
+            if entry.size() as usize > MAX_ROM_SIZE {
+                return Err(sevenz_rust::Error::other(format!(
+                    "{} is too large for a ROM",
+                    entry.name()
+                )));
+            }
+            let mut bytes = Vec::with_capacity(entry.size() as usize);
+            entry_reader.read_to_end(&mut bytes)?;
+            rom = Some(bytes);
+            Ok(false)
+        })
+        .map_err(|e| format!("Failed to extract 7z archive: {}", e))?;
+
+    rom.ok_or_else(|| "No .gb or .gbc file found in 7z archive".to_string())
+}
+
+#[cfg(not(feature = "archive-7z"))]
+fn extract_7z(_data: &[u8]) -> Result<Vec<u8>, String> {
+    Err("7z archives need the archive-7z feature".to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Build a zip with stored (uncompressed) entries
+    fn stored_zip(files: &[(&str, &[u8])]) -> Vec<u8> {
+        let mut zip = Vec::new();
+        let mut central = Vec::new();
+        for (name, contents) in files {
+            let offset = zip.len() as u32;
+            let mut common = Vec::new();
+            common.extend_from_slice(&[0, 0, 0, 0]); // flags, method
+            common.extend_from_slice(&[0, 0, 0, 0]); // time, date
+            common.extend_from_slice(&crc32(contents).to_le_bytes());
+            common.extend_from_slice(&(contents.len() as u32).to_le_bytes());
+            common.extend_from_slice(&(contents.len() as u32).to_le_bytes());
+            common.extend_from_slice(&(name.len() as u16).to_le_bytes());
+            common.extend_from_slice(&[0, 0]); // extra length
+
+            zip.extend_from_slice(&ZIP_LOCAL_HEADER.to_le_bytes());
+            zip.extend_from_slice(&[20, 0]); // version needed
+            zip.extend_from_slice(&common);
+            zip.extend_from_slice(name.as_bytes());
+            zip.extend_from_slice(contents);
+
+            central.extend_from_slice(&ZIP_CENTRAL_HEADER.to_le_bytes());
+            central.extend_from_slice(&[20, 0, 20, 0]); // version made by, needed
+            central.extend_from_slice(&common);
+            central.extend_from_slice(&[0; 6]); // comment length, disk, internal attributes
+            central.extend_from_slice(&[0; 4]); // external attributes
+            central.extend_from_slice(&offset.to_le_bytes());
+            central.extend_from_slice(name.as_bytes());
+        }
+
+        let central_offset = zip.len() as u32;
+        zip.extend_from_slice(&central);
+        zip.extend_from_slice(&ZIP_END_OF_CENTRAL_DIRECTORY.to_le_bytes());
+        zip.extend_from_slice(&[0; 4]); // disk numbers
+        zip.extend_from_slice(&(files.len() as u16).to_le_bytes());
+        zip.extend_from_slice(&(files.len() as u16).to_le_bytes());
+        zip.extend_from_slice(&(central.len() as u32).to_le_bytes());
+        zip.extend_from_slice(&central_offset.to_le_bytes());
+        zip.extend_from_slice(&[0, 0]); // comment length
+        zip
+    }
+
+    #[test]
+    fn test_detect() {
+        assert_eq!(
+            Container::detect(&stored_zip(&[("game.gb", b"")])),
+            Container::Zip
+        );
+        assert_eq!(Container::detect(&[0x1F, 0x8B, 8, 0]), Container::Gzip);
+        assert_eq!(Container::detect(&SEVEN_ZIP_MAGIC), Container::SevenZip);
+        assert_eq!(Container::detect(&[0x00, 0xC3, 0x50, 0x01]), Container::Raw);
+        assert_eq!(Container::detect(&[]), Container::Raw);
+    }
+
+    #[test]
+    fn test_raw_passes_through() {
+        let rom = vec![0x00, 0xC3, 0x50, 0x01];
+        assert_eq!(extract_rom(rom.clone()).unwrap(), rom);
+    }
+
+    #[test]
+    fn test_is_rom_name() {
+        assert!(is_rom_name("Tetris.gb"));
+        assert!(is_rom_name("roms/Pokemon Crystal.GBC"));
+        assert!(is_rom_name("Space Invaders.sgb"));
+        assert!(!is_rom_name("readme.txt"));
+        assert!(!is_rom_name("Tetris.sav"));
+        assert!(!is_rom_name("gb"));
+        assert!(!is_rom_name("__MACOSX/Tetris.gb"));
+        assert!(!is_rom_name("roms/._Tetris.gb"));
+    }
+
+    #[test]
+    fn test_zip_picks_rom_entry() {
+        let zip = stored_zip(&[
+            ("readme.txt", b"hello"),
+            ("__MACOSX/._game.gb", b"fork"),
+            ("game.gbc", b"ROM DATA"),
+            ("other.gb", b"SECOND"),
+        ]);
+        assert_eq!(extract_rom(zip).unwrap(), b"ROM DATA");
+    }
+
+    #[test]
+    fn test_zip_without_rom() {
+        let zip = stored_zip(&[("readme.txt", b"hello")]);
+        assert!(extract_rom(zip).unwrap_err().contains("No .gb or .gbc"));
+    }
+
+    #[test]
+    fn test_zip_crc_mismatch() {
+        let mut zip = stored_zip(&[("game.gb", b"ROM DATA")]);
+        let data = 30 + "game.gb".len();
+        zip[data] ^= 0xFF;
+        assert!(extract_rom(zip).unwrap_err().contains("CRC mismatch"));
+    }
+
+    #[test]
+    fn test_truncated_zip() {
+        let zip = stored_zip(&[("game.gb", b"ROM DATA")]);
+        assert!(extract_rom(zip[..20].to_vec()).is_err());
+    }
+
+    #[test]
+    fn test_truncated_gzip() {
+        assert!(extract_rom(vec![0x1F, 0x8B, 8, 0, 0]).is_err());
+    }
+}
@@ -1,11 +1,13 @@
 /// Game Boy Cartridge module
 ///
 /// This module handles loading and parsing Game Boy ROM files (.gb).
-/// Supports original DMG (Game Boy) only - no CGB (Color Game Boy) support.
+/// Supports original DMG (Game Boy) and CGB (Game Boy Color) cartridges.
 /// Focuses on the most common cartridge types: ROM ONLY, MBC1, MBC3, and MBC5.
 ///
-/// Note: This implementation does not verify the Nintendo logo or use a BIOS,
-/// as the system state is initialized directly to post-boot values.
+/// Note: The Nintendo logo is not verified here. By default the system state
+/// is initialized directly to post-boot values; run a boot ROM
+/// (`GameBoy::with_boot_rom`) to have the logo and header checked as on
+/// hardware.
 use std::fmt;
 use std::fs;
 use std::io;
@@ -68,11 +70,50 @@ impl fmt::Display for CartridgeType {
     }
 }
 
+/// Game Boy Color support declared by the cartridge (0x0143)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CgbSupport {
+    /// DMG-only cartridge
+    None,
+    /// Supports CGB enhancements but also runs on DMG (0x80)
+    Compatible,
+    /// Works only on CGB (0xC0)
+    Required,
+}
+
+impl CgbSupport {
+    /// Parse CGB flag from byte value at 0x0143
+    ///
+    /// Like the CGB boot ROM, only bit 7 selects CGB mode, so flags with
+    /// other low bits set (0x84, 0x88) count as well.
+    pub fn from_byte(byte: u8) -> Self {
+        match byte {
+            b if b & 0xC0 == 0xC0 => CgbSupport::Required,
+            b if b & 0x80 != 0 => CgbSupport::Compatible,
+            _ => CgbSupport::None,
+        }
+    }
+}
+
+impl fmt::Display for CgbSupport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CgbSupport::None => write!(f, "DMG"),
+            CgbSupport::Compatible => write!(f, "DMG/CGB"),
+            CgbSupport::Required => write!(f, "CGB only"),
+        }
+    }
+}
+
 /// Game Boy cartridge header information
 #[derive(Debug, Clone)]
 pub struct CartridgeHeader {
-    /// Game title (0x0134-0x0143)
+    /// Game title (0x0134-0x0143, 0x0134-0x0142 on CGB cartridges)
     pub title: String,
+    /// CGB flag (0x0143)
+    pub cgb_support: CgbSupport,
+    /// SGB flag (0x0146 = 0x03, only honored with old licensee code 0x33)
+    pub sgb_support: bool,
     /// Cartridge type (0x0147)
     pub cartridge_type: CartridgeType,
     /// ROM size in bytes (0x0148)
@@ -101,13 +142,24 @@ impl CartridgeHeader {
             ));
         }
 
+        // CGB flag (overlaps the last title byte)
+        let cgb_support = CgbSupport::from_byte(rom[0x0143]);
+
         // Extract title (0x0134-0x0143, null-terminated or space-padded)
-        let title_bytes = &rom[0x0134..=0x0143];
+        let title_end = match cgb_support {
+            CgbSupport::None => 0x0143,
+            _ => 0x0142,
+        };
+        let title_bytes = &rom[0x0134..=title_end];
         let title = String::from_utf8_lossy(title_bytes)
             .trim_end_matches('\0')
             .trim()
             .to_string();
 
+        // SGB functions are only enabled when the old licensee code says
+        // to use the new licensee code
+        let sgb_support = rom[0x0146] == 0x03 && rom[0x014B] == 0x33;
+
         // Cartridge type
         let cartridge_type = CartridgeType::from_byte(rom[0x0147]);
 
@@ -161,6 +213,8 @@ impl CartridgeHeader {
 
         Ok(CartridgeHeader {
             title,
+            cgb_support,
+            sgb_support,
             cartridge_type,
             rom_size,
             ram_size,
@@ -174,6 +228,12 @@ impl fmt::Display for CartridgeHeader {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         writeln!(f, "=== Cartridge Information ===")?;
         writeln!(f, "Title: {}", self.title)?;
+        writeln!(f, "Hardware: {}", self.cgb_support)?;
+        writeln!(
+            f,
+            "SGB Support: {}",
+            if self.sgb_support { "Yes" } else { "No" }
+        )?;
         writeln!(f, "Cartridge Type: {}", self.cartridge_type)?;
         writeln!(f, "ROM Size: {} KiB", self.rom_size / 1024)?;
         writeln!(f, "RAM Size: {} KiB", self.ram_size / 1024)?;
@@ -218,6 +278,41 @@ impl Cartridge {
         Ok(Cartridge { header, rom })
     }
 
+    /// Create a cartridge from a zip, gzip or 7z archive holding a ROM
+    ///
+    /// Bytes that aren't a recognized archive are loaded as a plain ROM, so
+    /// frontends can pass every file through this.
+    ///
+    /// # Arguments
+    /// * `data` - Archive or ROM data bytes
+    ///
+    /// # Returns
+    /// Result containing Cartridge or IO error
+    #[cfg(feature = "archive")]
+    pub fn from_archive(data: Vec<u8>) -> io::Result<Self> {
+        Self::from_archive_patched(data, None)
+    }
+
+    /// Create a cartridge from an archive (or plain ROM), applying an IPS,
+    /// BPS or UPS patch to the extracted ROM
+    ///
+    /// # Arguments
+    /// * `data` - Archive or ROM data bytes
+    /// * `patch` - Patch file bytes, or None to load the ROM unpatched
+    ///
+    /// # Returns
+    /// Result containing Cartridge or IO error
+    #[cfg(feature = "archive")]
+    pub fn from_archive_patched(data: Vec<u8>, patch: Option<&[u8]>) -> io::Result<Self> {
+        let mut rom = crate::archive::extract_rom(data)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+        if let Some(patch) = patch {
+            rom = crate::patch::apply(&rom, patch)
+                .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+        }
+        Self::from_bytes(rom)
+    }
+
     /// Read a byte from ROM at the specified address
     ///
     /// # Arguments
@@ -415,4 +510,50 @@ mod tests {
             assert_eq!(header.ram_size, expected_size);
         }
     }
+
+    #[test]
+    fn test_parse_header_cgb_flag() {
+        for (flag, expected) in [
+            (0x00, CgbSupport::None),
+            (0x80, CgbSupport::Compatible),
+            (0x84, CgbSupport::Compatible),
+            (0x88, CgbSupport::Compatible),
+            (0xC0, CgbSupport::Required),
+            (0xC8, CgbSupport::Required),
+        ] {
+            let mut rom = vec![0; 0x8000];
+            rom[0x0134..0x013C].copy_from_slice(b"COLORFUL");
+            rom[0x0143] = flag;
+
+            let mut checksum: u8 = 0;
+            for &byte in &rom[0x0134..=0x014C] {
+                checksum = checksum.wrapping_sub(byte).wrapping_sub(1);
+            }
+            rom[0x014D] = checksum;
+
+            let header = CartridgeHeader::parse(&rom).unwrap();
+            assert_eq!(header.cgb_support, expected);
+            assert_eq!(header.title, "COLORFUL");
+        }
+    }
+
+    #[test]
+    fn test_parse_header_sgb_flag() {
+        for (flag, licensee, expected) in
+            [(0x00, 0x33, false), (0x03, 0x01, false), (0x03, 0x33, true)]
+        {
+            let mut rom = vec![0; 0x8000];
+            rom[0x0146] = flag;
+            rom[0x014B] = licensee;
+
+            let mut checksum: u8 = 0;
+            for &byte in &rom[0x0134..=0x014C] {
+                checksum = checksum.wrapping_sub(byte).wrapping_sub(1);
+            }
+            rom[0x014D] = checksum;
+
+            let header = CartridgeHeader::parse(&rom).unwrap();
+            assert_eq!(header.sgb_support, expected);
+        }
+    }
 }
@@ -0,0 +1,694 @@
+/// Instruction decoder and disassembler (SM83)
+///
+/// Every opcode is decoded once, at compile time, into an `Instruction`
+/// with its operands (8-bit registers, register pairs, conditions). The
+/// CPU dispatches on these tables, and the disassembler and tracer format
+/// them, so all three agree on what each opcode means.
+///
+/// Opcode layout (x = bits 6-7, y = bits 3-5, z = bits 0-2):
+/// - r8 operands are encoded as B, C, D, E, H, L, (HL), A
+/// - r16 operands (bits 4-5) are BC, DE, HL, SP; AF replaces SP for
+///   PUSH/POP and (HL+)/(HL-) replace HL/SP for indirect A loads
+/// - CB-prefixed opcodes are x = operation, y = bit or shift, z = r8
+use std::fmt;
+
+/// 8-bit operand, in opcode encoding order
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum R8 {
+    B,
+    C,
+    D,
+    E,
+    H,
+    L,
+    /// Memory at the address in HL
+    HlIndirect,
+    A,
+}
+
+/// 16-bit register pair for loads and arithmetic
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum R16 {
+    BC,
+    DE,
+    HL,
+    SP,
+}
+
+/// 16-bit register pair for PUSH and POP
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum R16Stack {
+    BC,
+    DE,
+    HL,
+    AF,
+}
+
+/// Address for LD (r16),A and LD A,(r16)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum R16Mem {
+    BC,
+    DE,
+    /// HL, incremented after the access
+    HlIncrement,
+    /// HL, decremented after the access
+    HlDecrement,
+}
+
+/// Branch condition
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Cond {
+    NZ,
+    Z,
+    NC,
+    C,
+}
+
+/// 8-bit arithmetic/logic operation on A
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AluOp {
+    Add,
+    Adc,
+    Sub,
+    Sbc,
+    And,
+    Xor,
+    Or,
+    Cp,
+}
+
+/// Rotate, shift and swap operation (CB 0x00-0x3F)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ShiftOp {
+    Rlc,
+    Rrc,
+    Rl,
+    Rr,
+    Sla,
+    Sra,
+    Swap,
+    Srl,
+}
+
+/// A decoded instruction
+///
+/// Immediate operands are not part of the instruction; they follow the
+/// opcode in memory (see `Instruction::length`).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Instruction {
+    Nop,
+    Stop,
+    Halt,
+    Di,
+    Ei,
+    /// LD r16,n16
+    LdR16Imm(R16),
+    /// LD (r16),A
+    LdMemA(R16Mem),
+    /// LD A,(r16)
+    LdAMem(R16Mem),
+    /// LD (n16),SP
+    LdImm16Sp,
+    IncR16(R16),
+    DecR16(R16),
+    /// ADD HL,r16
+    AddHl(R16),
+    IncR8(R8),
+    DecR8(R8),
+    /// LD r8,n8
+    LdR8Imm(R8),
+    Rlca,
+    Rrca,
+    Rla,
+    Rra,
+    Daa,
+    Cpl,
+    Scf,
+    Ccf,
+    /// JR e8
+    Jr,
+    /// JR cc,e8
+    JrCond(Cond),
+    /// LD r8,r8 (destination, source)
+    Ld(R8, R8),
+    /// ALU operation on A with an 8-bit operand
+    Alu(AluOp, R8),
+    /// ALU operation on A with an immediate
+    AluImm(AluOp),
+    Ret,
+    RetCond(Cond),
+    Reti,
+    /// JP n16
+    Jp,
+    /// JP cc,n16
+    JpCond(Cond),
+    JpHl,
+    /// CALL n16
+    Call,
+    /// CALL cc,n16
+    CallCond(Cond),
+    /// RST to a fixed vector
+    Rst(u8),
+    Pop(R16Stack),
+    Push(R16Stack),
+    /// LDH (C),A
+    LdhCA,
+    /// LDH (n8),A
+    LdhImmA,
+    /// LD (n16),A
+    LdImm16A,
+    /// LDH A,(C)
+    LdhAC,
+    /// LDH A,(n8)
+    LdhAImm,
+    /// LD A,(n16)
+    LdAImm16,
+    /// ADD SP,e8
+    AddSpImm,
+    /// LD HL,SP+e8
+    LdHlSpImm,
+    LdSpHl,
+    /// CB prefix: the next byte selects a CB instruction
+    Prefix,
+    Shift(ShiftOp, R8),
+    /// BIT b,r8
+    Bit(u8, R8),
+    /// RES b,r8
+    Res(u8, R8),
+    /// SET b,r8
+    Set(u8, R8),
+    /// Opcode that locks up the CPU
+    Illegal(u8),
+}
+
+/// Decoded unprefixed opcodes
+pub const OPCODES: [Instruction; 256] = {
+    let mut table = [Instruction::Nop; 256];
+    let mut opcode = 0;
+    while opcode < 256 {
+        table[opcode] = decode(opcode as u8);
+        opcode += 1;
+    }
+    table
+};
+
+/// Decoded CB-prefixed opcodes
+pub const CB_OPCODES: [Instruction; 256] = {
+    let mut table = [Instruction::Nop; 256];
+    let mut opcode = 0;
+    while opcode < 256 {
+        table[opcode] = decode_cb(opcode as u8);
+        opcode += 1;
+    }
+    table
+};
+
+const R8_OPERANDS: [R8; 8] = [
+    R8::B,
+    R8::C,
+    R8::D,
+    R8::E,
+    R8::H,
+    R8::L,
+    R8::HlIndirect,
+    R8::A,
+];
+const R16_OPERANDS: [R16; 4] = [R16::BC, R16::DE, R16::HL, R16::SP];
+const R16_STACK_OPERANDS: [R16Stack; 4] = [R16Stack::BC, R16Stack::DE, R16Stack::HL, R16Stack::AF];
+const R16_MEM_OPERANDS: [R16Mem; 4] = [
+    R16Mem::BC,
+    R16Mem::DE,
+    R16Mem::HlIncrement,
+    R16Mem::HlDecrement,
+];
+const CONDITIONS: [Cond; 4] = [Cond::NZ, Cond::Z, Cond::NC, Cond::C];
+const ALU_OPS: [AluOp; 8] = [
+    AluOp::Add,
+    AluOp::Adc,
+    AluOp::Sub,
+    AluOp::Sbc,
+    AluOp::And,
+    AluOp::Xor,
+    AluOp::Or,
+    AluOp::Cp,
+];
+const SHIFT_OPS: [ShiftOp; 8] = [
+    ShiftOp::Rlc,
+    ShiftOp::Rrc,
+    ShiftOp::Rl,
+    ShiftOp::Rr,
+    ShiftOp::Sla,
+    ShiftOp::Sra,
+    ShiftOp::Swap,
+    ShiftOp::Srl,
+];
+
+/// Decode an unprefixed opcode
+pub const fn decode(opcode: u8) -> Instruction {
+    use Instruction::*;
+
+    let y = ((opcode >> 3) & 0x07) as usize;
+    let z = (opcode & 0x07) as usize;
+    let p = y >> 1;
+    let r8_y = R8_OPERANDS[y];
+    let r8_z = R8_OPERANDS[z];
+
+    match opcode {
+        0x00 => Nop,
+        0x08 => LdImm16Sp,
+        0x10 => Stop,
+        0x18 => Jr,
+        0x20 | 0x28 | 0x30 | 0x38 => JrCond(CONDITIONS[y - 4]),
+        0x76 => Halt,
+        0x40..=0x7F => Ld(r8_y, r8_z),
+        0x80..=0xBF => Alu(ALU_OPS[y], r8_z),
+        0xC0 | 0xC8 | 0xD0 | 0xD8 => RetCond(CONDITIONS[y]),
+        0xC2 | 0xCA | 0xD2 | 0xDA => JpCond(CONDITIONS[y]),
+        0xC4 | 0xCC | 0xD4 | 0xDC => CallCond(CONDITIONS[y]),
+        0xC3 => Jp,
+        0xC9 => Ret,
+        0xCB => Prefix,
+        0xCD => Call,
+        0xD9 => Reti,
+        0xE0 => LdhImmA,
+        0xE2 => LdhCA,
+        0xE8 => AddSpImm,
+        0xE9 => JpHl,
+        0xEA => LdImm16A,
+        0xF0 => LdhAImm,
+        0xF2 => LdhAC,
+        0xF3 => Di,
+        0xF8 => LdHlSpImm,
+        0xF9 => LdSpHl,
+        0xFA => LdAImm16,
+        0xFB => Ei,
+        0xD3 | 0xDB | 0xDD | 0xE3 | 0xE4 | 0xEB | 0xEC | 0xED | 0xF4 | 0xFC | 0xFD => {
+            Illegal(opcode)
+        }
+        _ => match (opcode >> 6, z) {
+            (0, 1) if y & 1 == 0 => LdR16Imm(R16_OPERANDS[p]),
+            (0, 1) => AddHl(R16_OPERANDS[p]),
+            (0, 2) if y & 1 == 0 => LdMemA(R16_MEM_OPERANDS[p]),
+            (0, 2) => LdAMem(R16_MEM_OPERANDS[p]),
+            (0, 3) if y & 1 == 0 => IncR16(R16_OPERANDS[p]),
+            (0, 3) => DecR16(R16_OPERANDS[p]),
+            (0, 4) => IncR8(r8_y),
+            (0, 5) => DecR8(r8_y),
+            (0, 6) => LdR8Imm(r8_y),
+            (0, _) => [Rlca, Rrca, Rla, Rra, Daa, Cpl, Scf, Ccf][y],
+            (_, 1) => Pop(R16_STACK_OPERANDS[p]),
+            (_, 5) => Push(R16_STACK_OPERANDS[p]),
+            (_, 6) => AluImm(ALU_OPS[y]),
+            _ => Rst(opcode & 0x38),
+        },
+    }
+}
+
+/// Decode the opcode following a CB prefix
+pub const fn decode_cb(opcode: u8) -> Instruction {
+    let y = (opcode >> 3) & 0x07;
+    let r8 = R8_OPERANDS[(opcode & 0x07) as usize];
+
+    match opcode >> 6 {
+        0 => Instruction::Shift(SHIFT_OPS[y as usize], r8),
+        1 => Instruction::Bit(y, r8),
+        2 => Instruction::Res(y, r8),
+        _ => Instruction::Set(y, r8),
+    }
+}
+
+impl Instruction {
+    /// Size in bytes, including the opcode (and CB prefix) and immediates
+    pub const fn length(self) -> u16 {
+        use Instruction::*;
+
+        match self {
+            LdR16Imm(_) | LdImm16Sp | Jp | JpCond(_) | Call | CallCond(_) | LdImm16A | LdAImm16 => {
+                3
+            }
+            LdR8Imm(_) | Jr | JrCond(_) | AluImm(_) | LdhImmA | LdhAImm | AddSpImm | LdHlSpImm
+            | Stop | Prefix | Shift(..) | Bit(..) | Res(..) | Set(..) => 2,
+            _ => 1,
+        }
+    }
+
+    /// CPU cycles taken, or when not taken for conditional branches
+    ///
+    /// The CB prefix itself takes no time; the CB instruction includes it.
+    pub const fn cycles(self) -> u64 {
+        use Instruction::*;
+
+        match self {
+            Prefix => 0,
+            Illegal(_) => 0,
+            Bit(_, R8::HlIndirect) => 12,
+            Shift(_, R8::HlIndirect) | Res(_, R8::HlIndirect) | Set(_, R8::HlIndirect) => 16,
+            Shift(..) | Bit(..) | Res(..) | Set(..) => 8,
+            IncR8(R8::HlIndirect) | DecR8(R8::HlIndirect) | LdR8Imm(R8::HlIndirect) => 12,
+            Ld(R8::HlIndirect, _)
+            | Ld(_, R8::HlIndirect)
+            | Alu(_, R8::HlIndirect)
+            | LdR8Imm(_)
+            | AluImm(_)
+            | LdMemA(_)
+            | LdAMem(_)
+            | IncR16(_)
+            | DecR16(_)
+            | AddHl(_)
+            | JrCond(_)
+            | RetCond(_)
+            | LdhCA
+            | LdhAC
+            | LdSpHl => 8,
+            LdR16Imm(_) | Jr | JpCond(_) | CallCond(_) | Pop(_) | LdhImmA | LdhAImm | LdHlSpImm => {
+                12
+            }
+            Ret | Reti | Jp | Rst(_) | Push(_) | LdImm16A | LdAImm16 | AddSpImm => 16,
+            LdImm16Sp => 20,
+            Call => 24,
+            _ => 4,
+        }
+    }
+
+    /// Extra CPU cycles when a conditional branch is taken
+    pub const fn branch_cycles(self) -> u64 {
+        match self {
+            Instruction::JrCond(_) | Instruction::JpCond(_) => 4,
+            Instruction::RetCond(_) | Instruction::CallCond(_) => 12,
+            _ => 0,
+        }
+    }
+
+    /// Write the instruction in assembly syntax
+    ///
+    /// With `operands` (immediate value and address of the next
+    /// instruction) immediates and jump targets are shown as numbers,
+    /// otherwise as n8, n16 and e8.
+    fn write_asm(self, f: &mut fmt::Formatter<'_>, operands: Option<(u16, u16)>) -> fmt::Result {
+        use Instruction::*;
+
+        let n8 = || match operands {
+            Some((value, _)) => format!("${:02X}", value as u8),
+            None => "n8".to_string(),
+        };
+        let n16 = || match operands {
+            Some((value, _)) => format!("${:04X}", value),
+            None => "n16".to_string(),
+        };
+        let e8 = || match operands {
+            Some((value, _)) => format!("{}", value as u8 as i8),
+            None => "e8".to_string(),
+        };
+        let jr_target = || match operands {
+            Some((value, next)) => format!("${:04X}", next.wrapping_add(value as u8 as i8 as u16)),
+            None => "e8".to_string(),
+        };
+
+        match self {
+            Nop => write!(f, "NOP"),
+            Stop => write!(f, "STOP"),
+            Halt => write!(f, "HALT"),
+            Di => write!(f, "DI"),
+            Ei => write!(f, "EI"),
+            LdR16Imm(r16) => write!(f, "LD {},{}", r16, n16()),
+            LdMemA(mem) => write!(f, "LD {},A", mem),
+            LdAMem(mem) => write!(f, "LD A,{}", mem),
+            LdImm16Sp => write!(f, "LD ({}),SP", n16()),
+            IncR16(r16) => write!(f, "INC {}", r16),
+            DecR16(r16) => write!(f, "DEC {}", r16),
+            AddHl(r16) => write!(f, "ADD HL,{}", r16),
+            IncR8(r8) => write!(f, "INC {}", r8),
+            DecR8(r8) => write!(f, "DEC {}", r8),
+            LdR8Imm(r8) => write!(f, "LD {},{}", r8, n8()),
+            Rlca => write!(f, "RLCA"),
+            Rrca => write!(f, "RRCA"),
+            Rla => write!(f, "RLA"),
+            Rra => write!(f, "RRA"),
+            Daa => write!(f, "DAA"),
+            Cpl => write!(f, "CPL"),
+            Scf => write!(f, "SCF"),
+            Ccf => write!(f, "CCF"),
+            Jr => write!(f, "JR {}", jr_target()),
+            JrCond(cond) => write!(f, "JR {},{}", cond, jr_target()),
+            Ld(dest, src) => write!(f, "LD {},{}", dest, src),
+            Alu(op, r8) => write!(f, "{}{}", op.prefix(), r8),
+            AluImm(op) => write!(f, "{}{}", op.prefix(), n8()),
+            Ret => write!(f, "RET"),
+            RetCond(cond) => write!(f, "RET {}", cond),
+            Reti => write!(f, "RETI"),
+            Jp => write!(f, "JP {}", n16()),
+            JpCond(cond) => write!(f, "JP {},{}", cond, n16()),
+            JpHl => write!(f, "JP HL"),
+            Call => write!(f, "CALL {}", n16()),
+            CallCond(cond) => write!(f, "CALL {},{}", cond, n16()),
+            Rst(vector) => write!(f, "RST ${:02X}", vector),
+            Pop(r16) => write!(f, "POP {}", r16),
+            Push(r16) => write!(f, "PUSH {}", r16),
+            LdhCA => write!(f, "LDH (C),A"),
+            LdhImmA => write!(f, "LDH ({}),A", n8()),
+            LdImm16A => write!(f, "LD ({}),A", n16()),
+            LdhAC => write!(f, "LDH A,(C)"),
+            LdhAImm => write!(f, "LDH A,({})", n8()),
+            LdAImm16 => write!(f, "LD A,({})", n16()),
+            AddSpImm => write!(f, "ADD SP,{}", e8()),
+            LdHlSpImm => write!(f, "LD HL,SP+{}", e8()),
+            LdSpHl => write!(f, "LD SP,HL"),
+            Prefix => write!(f, "PREFIX CB"),
+            Shift(op, r8) => write!(f, "{} {}", op, r8),
+            Bit(bit, r8) => write!(f, "BIT {},{}", bit, r8),
+            Res(bit, r8) => write!(f, "RES {},{}", bit, r8),
+            Set(bit, r8) => write!(f, "SET {},{}", bit, r8),
+            Illegal(opcode) => write!(f, "ILLEGAL ${:02X}", opcode),
+        }
+    }
+}
+
+/// Assembly syntax with placeholders for immediates (n8, n16, e8)
+impl fmt::Display for Instruction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.write_asm(f, None)
+    }
+}
+
+impl fmt::Display for R8 {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            R8::B => "B",
+            R8::C => "C",
+            R8::D => "D",
+            R8::E => "E",
+            R8::H => "H",
+            R8::L => "L",
+            R8::HlIndirect => "(HL)",
+            R8::A => "A",
+        };
+        f.write_str(name)
+    }
+}
+
+impl fmt::Display for R16 {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            R16::BC => "BC",
+            R16::DE => "DE",
+            R16::HL => "HL",
+            R16::SP => "SP",
+        };
+        f.write_str(name)
+    }
+}
+
+impl fmt::Display for R16Stack {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            R16Stack::BC => "BC",
+            R16Stack::DE => "DE",
+            R16Stack::HL => "HL",
+            R16Stack::AF => "AF",
+        };
+        f.write_str(name)
+    }
+}
+
+impl fmt::Display for R16Mem {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            R16Mem::BC => "(BC)",
+            R16Mem::DE => "(DE)",
+            R16Mem::HlIncrement => "(HL+)",
+            R16Mem::HlDecrement => "(HL-)",
+        };
+        f.write_str(name)
+    }
+}
+
+impl fmt::Display for Cond {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Cond::NZ => "NZ",
+            Cond::Z => "Z",
+            Cond::NC => "NC",
+            Cond::C => "C",
+        };
+        f.write_str(name)
+    }
+}
+
+impl fmt::Display for ShiftOp {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            ShiftOp::Rlc => "RLC",
+            ShiftOp::Rrc => "RRC",
+            ShiftOp::Rl => "RL",
+            ShiftOp::Rr => "RR",
+            ShiftOp::Sla => "SLA",
+            ShiftOp::Sra => "SRA",
+            ShiftOp::Swap => "SWAP",
+            ShiftOp::Srl => "SRL",
+        };
+        f.write_str(name)
+    }
+}
+
+impl AluOp {
+    /// Mnemonic and the implicit A operand where the syntax names it
+    fn prefix(self) -> &'static str {
+        match self {
+            AluOp::Add => "ADD A,",
+            AluOp::Adc => "ADC A,",
+            AluOp::Sub => "SUB ",
+            AluOp::Sbc => "SBC A,",
+            AluOp::And => "AND ",
+            AluOp::Xor => "XOR ",
+            AluOp::Or => "OR ",
+            AluOp::Cp => "CP ",
+        }
+    }
+}
+
+/// One disassembled instruction
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Disassembly {
+    pub address: u16,
+    pub instruction: Instruction,
+    /// Opcode, CB prefix and immediate bytes
+    pub bytes: Vec<u8>,
+}
+
+impl Disassembly {
+    /// Address of the following instruction
+    pub fn next_address(&self) -> u16 {
+        self.address.wrapping_add(self.bytes.len() as u16)
+    }
+}
+
+/// Assembly syntax with immediates and jump targets filled in
+impl fmt::Display for Disassembly {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let immediate = match self.bytes[..] {
+            [_, low] => low as u16,
+            [_, low, high] => u16::from_le_bytes([low, high]),
+            _ => 0,
+        };
+        let operands = (self.instruction.length() > 1).then_some((immediate, self.next_address()));
+        self.instruction.write_asm(f, operands)
+    }
+}
+
+/// Disassemble the instruction at `address`, reading memory through `read`
+pub fn disassemble<F: Fn(u16) -> u8>(address: u16, read: F) -> Disassembly {
+    let opcode = read(address);
+    let instruction = match OPCODES[opcode as usize] {
+        Instruction::Prefix => CB_OPCODES[read(address.wrapping_add(1)) as usize],
+        instruction => instruction,
+    };
+    let bytes = (0..instruction.length())
+        .map(|offset| read(address.wrapping_add(offset)))
+        .collect();
+
+    Disassembly {
+        address,
+        instruction,
+        bytes,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn disassemble_bytes(bytes: &[u8]) -> String {
+        disassemble(0x0150, |addr| bytes[(addr - 0x0150) as usize]).to_string()
+    }
+
+    #[test]
+    fn test_decode_operands() {
+        assert_eq!(OPCODES[0x41], Instruction::Ld(R8::B, R8::C));
+        assert_eq!(OPCODES[0x76], Instruction::Halt);
+        assert_eq!(OPCODES[0x36], Instruction::LdR8Imm(R8::HlIndirect));
+        assert_eq!(OPCODES[0x3A], Instruction::LdAMem(R16Mem::HlDecrement));
+        assert_eq!(OPCODES[0x39], Instruction::AddHl(R16::SP));
+        assert_eq!(OPCODES[0xF5], Instruction::Push(R16Stack::AF));
+        assert_eq!(OPCODES[0xD8], Instruction::RetCond(Cond::C));
+        assert_eq!(OPCODES[0xBE], Instruction::Alu(AluOp::Cp, R8::HlIndirect));
+        assert_eq!(OPCODES[0xEF], Instruction::Rst(0x28));
+        assert_eq!(CB_OPCODES[0x37], Instruction::Shift(ShiftOp::Swap, R8::A));
+        assert_eq!(CB_OPCODES[0x7E], Instruction::Bit(7, R8::HlIndirect));
+        assert_eq!(CB_OPCODES[0xC8], Instruction::Set(1, R8::B));
+    }
+
+    #[test]
+    fn test_illegal_opcodes() {
+        let illegal: Vec<_> = (0..=0xFF)
+            .filter(|&opcode| matches!(OPCODES[opcode as usize], Instruction::Illegal(_)))
+            .collect();
+        assert_eq!(
+            illegal,
+            [
+                0xD3, 0xDB, 0xDD, 0xE3, 0xE4, 0xEB, 0xEC, 0xED, 0xF4, 0xFC, 0xFD
+            ]
+        );
+    }
+
+    #[test]
+    fn test_lengths_and_cycles() {
+        assert_eq!(OPCODES[0x01].length(), 3);
+        assert_eq!(OPCODES[0x18].length(), 2);
+        assert_eq!(OPCODES[0x10].length(), 2);
+        assert_eq!(CB_OPCODES[0x00].length(), 2);
+
+        assert_eq!(OPCODES[0x34].cycles(), 12);
+        assert_eq!(OPCODES[0x46].cycles(), 8);
+        assert_eq!(OPCODES[0xE8].cycles(), 16);
+        assert_eq!(OPCODES[0xC4].cycles(), 12);
+        assert_eq!(OPCODES[0xC4].branch_cycles(), 12);
+        assert_eq!(OPCODES[0x20].cycles() + OPCODES[0x20].branch_cycles(), 12);
+        assert_eq!(CB_OPCODES[0x46].cycles(), 12);
+        assert_eq!(CB_OPCODES[0x86].cycles(), 16);
+    }
+
+    #[test]
+    fn test_display_placeholders() {
+        assert_eq!(OPCODES[0x01].to_string(), "LD BC,n16");
+        assert_eq!(OPCODES[0x22].to_string(), "LD (HL+),A");
+        assert_eq!(OPCODES[0x96].to_string(), "SUB (HL)");
+        assert_eq!(OPCODES[0x8F].to_string(), "ADC A,A");
+        assert_eq!(OPCODES[0xF8].to_string(), "LD HL,SP+e8");
+        assert_eq!(CB_OPCODES[0x1E].to_string(), "RR (HL)");
+    }
+
+    #[test]
+    fn test_disassemble() {
+        assert_eq!(disassemble_bytes(&[0x3E, 0x42]), "LD A,$42");
+        assert_eq!(disassemble_bytes(&[0xC3, 0x50, 0x01]), "JP $0150");
+        assert_eq!(disassemble_bytes(&[0x18, 0xFE]), "JR $0150");
+        assert_eq!(disassemble_bytes(&[0x20, 0x05]), "JR NZ,$0157");
+        assert_eq!(disassemble_bytes(&[0xE0, 0x40]), "LDH ($40),A");
+        assert_eq!(disassemble_bytes(&[0xE8, 0xFF]), "ADD SP,-1");
+        assert_eq!(disassemble_bytes(&[0xCB, 0x7C]), "BIT 7,H");
+
+        let disassembly = disassemble(0x0150, |_| 0xCD);
+        assert_eq!(disassembly.bytes, [0xCD, 0xCD, 0xCD]);
+        assert_eq!(disassembly.next_address(), 0x0153);
+    }
+}
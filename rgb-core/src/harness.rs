@@ -0,0 +1,325 @@
+/// Test ROM harness (mooneye-gb / SameSuite protocol)
+///
+/// These ROMs end the test by executing `LD B,B` (opcode 0x40) as a software
+/// breakpoint. On success B, C, D, E, H and L hold the Fibonacci numbers
+/// 3, 5, 8, 13, 21 and 34; on failure they all hold 0x42.
+///
+/// ROMs run until the breakpoint or a timeout in frames, one at a time or a
+/// whole directory spread over several threads. ROMs are never downloaded:
+/// point the harness at a local copy of the suite.
+use crate::cartridge::Cartridge;
+use crate::ppu::DOTS_PER_FRAME;
+use crate::system::GameBoy;
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::thread;
+
+/// LD B,B
+const BREAKPOINT_OPCODE: u8 = 0x40;
+
+/// B, C, D, E, H, L when the test passed
+const PASS_REGISTERS: [u8; 6] = [3, 5, 8, 13, 21, 34];
+
+/// Default timeout: 20 seconds of emulated time
+pub const DEFAULT_TIMEOUT_FRAMES: u64 = 1200;
+
+/// How a test ROM run ended
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Outcome {
+    /// Breakpoint reached with the Fibonacci registers
+    Passed,
+    /// Breakpoint reached with any other registers (0x42 on failure)
+    Failed,
+    /// No breakpoint before the timeout
+    Timeout,
+    /// The ROM could not be loaded
+    Error,
+}
+
+impl fmt::Display for Outcome {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Outcome::Passed => "PASS",
+            Outcome::Failed => "FAIL",
+            Outcome::Timeout => "TIMEOUT",
+            Outcome::Error => "ERROR",
+        };
+        f.pad(name)
+    }
+}
+
+/// A test ROM to run and how long to wait for it
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TestRom {
+    pub path: PathBuf,
+    pub timeout_frames: u64,
+}
+
+impl TestRom {
+    pub fn new<P: Into<PathBuf>>(path: P, timeout_frames: u64) -> Self {
+        TestRom {
+            path: path.into(),
+            timeout_frames,
+        }
+    }
+}
+
+/// Result of running a test ROM
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TestResult {
+    pub path: PathBuf,
+    pub outcome: Outcome,
+    /// Frames emulated until the breakpoint or timeout
+    pub frames: u64,
+    /// Registers at the breakpoint, or the error message
+    pub detail: String,
+}
+
+/// Run a Game Boy until it reaches the breakpoint or `timeout_frames` pass
+///
+/// Returns the outcome and the number of frames emulated.
+pub fn run(gameboy: &mut GameBoy, timeout_frames: u64) -> (Outcome, u64) {
+    let start = gameboy.dot_cycles;
+    let timeout = start + timeout_frames * DOTS_PER_FRAME;
+
+    // A stale opcode from before the run must not count as the breakpoint
+    gameboy.last_opcode = 0x00;
+
+    while gameboy.dot_cycles < timeout {
+        gameboy.step_with_ppu();
+
+        if gameboy.last_opcode == BREAKPOINT_OPCODE {
+            let frames = (gameboy.dot_cycles - start) / DOTS_PER_FRAME;
+            let outcome = if registers(gameboy) == PASS_REGISTERS {
+                Outcome::Passed
+            } else {
+                Outcome::Failed
+            };
+            return (outcome, frames);
+        }
+    }
+
+    (Outcome::Timeout, timeout_frames)
+}
+
+/// Load and run a single test ROM
+pub fn run_test_rom(rom: &TestRom) -> TestResult {
+    let result = |outcome, frames, detail| TestResult {
+        path: rom.path.clone(),
+        outcome,
+        frames,
+        detail,
+    };
+
+    let cartridge = match Cartridge::load(&rom.path) {
+        Ok(cartridge) => cartridge,
+        Err(e) => return result(Outcome::Error, 0, e.to_string()),
+    };
+
+    let mut gameboy = GameBoy::with_cartridge(cartridge);
+    let (outcome, frames) = run(&mut gameboy, rom.timeout_frames);
+    let detail = match outcome {
+        Outcome::Passed | Outcome::Failed => format_registers(registers(&gameboy)),
+        _ => String::new(),
+    };
+    result(outcome, frames, detail)
+}
+
+/// Run test ROMs on up to `jobs` threads
+///
+/// Results are returned in the same order as `roms`.
+pub fn run_test_roms(roms: &[TestRom], jobs: usize) -> Vec<TestResult> {
+    let next = AtomicUsize::new(0);
+    let results = Mutex::new(vec![None; roms.len()]);
+
+    thread::scope(|scope| {
+        for _ in 0..jobs.clamp(1, roms.len().max(1)) {
+            scope.spawn(|| {
+                loop {
+                    let index = next.fetch_add(1, Ordering::Relaxed);
+                    let Some(rom) = roms.get(index) else {
+                        break;
+                    };
+                    let result = run_test_rom(rom);
+                    results.lock().unwrap()[index] = Some(result);
+                }
+            });
+        }
+    });
+
+    results
+        .into_inner()
+        .unwrap()
+        .into_iter()
+        .map(|result| result.expect("every ROM has a result"))
+        .collect()
+}
+
+/// Find all ROMs (.gb and .gbc) below a directory, sorted by path
+pub fn find_test_roms(dir: &Path) -> Result<Vec<PathBuf>, String> {
+    let mut roms = Vec::new();
+    let mut pending = vec![dir.to_path_buf()];
+
+    while let Some(dir) = pending.pop() {
+        let entries =
+            fs::read_dir(&dir).map_err(|e| format!("Failed to read {}: {}", dir.display(), e))?;
+
+        for entry in entries {
+            let path = entry
+                .map_err(|e| format!("Failed to read {}: {}", dir.display(), e))?
+                .path();
+            let is_rom = path.extension().is_some_and(|ext| {
+                ext.eq_ignore_ascii_case("gb") || ext.eq_ignore_ascii_case("gbc")
+            });
+
+            if path.is_dir() {
+                pending.push(path);
+            } else if is_rom {
+                roms.push(path);
+            }
+        }
+    }
+
+    roms.sort();
+    Ok(roms)
+}
+
+/// Format results as a table with a summary line
+///
+/// Paths are shown relative to `root` when they are below it.
+pub fn format_results(results: &[TestResult], root: &Path) -> String {
+    let mut table = format!("{:<8} {:>6}  ROM\n", "RESULT", "FRAMES");
+
+    for result in results {
+        let path = result.path.strip_prefix(root).unwrap_or(&result.path);
+        table += &format!(
+            "{:<8} {:>6}  {}",
+            result.outcome,
+            result.frames,
+            path.display()
+        );
+        if !result.detail.is_empty() && result.outcome != Outcome::Passed {
+            table += &format!("  ({})", result.detail);
+        }
+        table.push('\n');
+    }
+
+    let count = |outcome| results.iter().filter(|r| r.outcome == outcome).count();
+    table += &format!(
+        "\n{} passed, {} failed, {} timed out, {} errors ({} total)\n",
+        count(Outcome::Passed),
+        count(Outcome::Failed),
+        count(Outcome::Timeout),
+        count(Outcome::Error),
+        results.len()
+    );
+
+    table
+}
+
+/// B, C, D, E, H and L
+fn registers(gameboy: &GameBoy) -> [u8; 6] {
+    [
+        gameboy.b, gameboy.c, gameboy.d, gameboy.e, gameboy.h, gameboy.l,
+    ]
+}
+
+fn format_registers(registers: [u8; 6]) -> String {
+    let [b, c, d, e, h, l] = registers;
+    format!(
+        "B={:02X} C={:02X} D={:02X} E={:02X} H={:02X} L={:02X}",
+        b, c, d, e, h, l
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Build a 32 KiB ROM that runs `program` at 0x0100
+    fn test_rom(program: &[u8]) -> Vec<u8> {
+        let mut rom = vec![0; 0x8000];
+        rom[0x0100..0x0100 + program.len()].copy_from_slice(program);
+
+        let mut checksum: u8 = 0;
+        for &byte in &rom[0x0134..=0x014C] {
+            checksum = checksum.wrapping_sub(byte).wrapping_sub(1);
+        }
+        rom[0x014D] = checksum;
+        rom
+    }
+
+    /// Load B, C, D, E, H, L and hit the breakpoint
+    fn breakpoint_program(values: [u8; 6]) -> Vec<u8> {
+        let [b, c, d, e, h, l] = values;
+        vec![
+            0x06, b, // LD B,b
+            0x0E, c, // LD C,c
+            0x16, d, // LD D,d
+            0x1E, e, // LD E,e
+            0x26, h, // LD H,h
+            0x2E, l,    // LD L,l
+            0x40, // LD B,B
+            0x18, 0xFE, // JR -2
+        ]
+    }
+
+    fn run_program(program: &[u8], timeout_frames: u64) -> (Outcome, u64) {
+        let cartridge = Cartridge::from_bytes(test_rom(program)).unwrap();
+        let mut gameboy = GameBoy::with_cartridge(cartridge);
+        run(&mut gameboy, timeout_frames)
+    }
+
+    #[test]
+    fn test_fibonacci_registers_pass() {
+        let (outcome, frames) = run_program(&breakpoint_program(PASS_REGISTERS), 10);
+        assert_eq!(outcome, Outcome::Passed);
+        assert_eq!(frames, 0);
+    }
+
+    #[test]
+    fn test_0x42_registers_fail() {
+        let (outcome, _) = run_program(&breakpoint_program([0x42; 6]), 10);
+        assert_eq!(outcome, Outcome::Failed);
+    }
+
+    #[test]
+    fn test_no_breakpoint_times_out() {
+        let (outcome, frames) = run_program(&[0x18, 0xFE], 3);
+        assert_eq!(outcome, Outcome::Timeout);
+        assert_eq!(frames, 3);
+    }
+
+    #[test]
+    fn test_run_directory_in_parallel() {
+        let dir = std::env::temp_dir().join(format!("rgb-harness-{}", std::process::id()));
+        fs::create_dir_all(dir.join("nested")).unwrap();
+        fs::write(
+            dir.join("pass.gb"),
+            test_rom(&breakpoint_program(PASS_REGISTERS)),
+        )
+        .unwrap();
+        fs::write(
+            dir.join("nested/fail.gb"),
+            test_rom(&breakpoint_program([0x42; 6])),
+        )
+        .unwrap();
+        fs::write(dir.join("broken.gbc"), [0u8; 16]).unwrap();
+        fs::write(dir.join("notes.txt"), "not a ROM").unwrap();
+
+        let paths = find_test_roms(&dir).unwrap();
+        let roms: Vec<_> = paths.iter().map(|path| TestRom::new(path, 5)).collect();
+        let results = run_test_roms(&roms, 4);
+        let table = format_results(&results, &dir);
+        fs::remove_dir_all(&dir).unwrap();
+
+        let outcomes: Vec<_> = results.iter().map(|r| r.outcome).collect();
+        assert_eq!(outcomes, [Outcome::Error, Outcome::Failed, Outcome::Passed]);
+        assert!(table.contains("FAIL"), "{}", table);
+        assert!(table.contains("nested/fail.gb  (B=42 C=42"), "{}", table);
+        assert!(table.contains("1 passed, 1 failed, 0 timed out, 1 errors (3 total)"));
+    }
+}
@@ -0,0 +1,183 @@
+/// CGB VRAM DMA (HDMA1-HDMA5)
+///
+/// Copies data to VRAM in blocks of 16 bytes, in one of two modes:
+/// - General purpose DMA: the whole transfer runs at once, halting the CPU
+/// - HBlank DMA: one block is copied at the start of each HBlank
+///
+/// Registers:
+/// HDMA1/HDMA2 (0xFF51/0xFF52): Source address high/low (low 4 bits ignored)
+/// HDMA3/HDMA4 (0xFF53/0xFF54): Destination address high/low (within VRAM)
+/// HDMA5 (0xFF55): Bit 7 = mode (0 = general purpose, 1 = HBlank),
+///                 Bit 0-6 = length / 16 - 1
+pub struct Hdma {
+    source: u16,
+    dest: u16,
+    /// Remaining 16-byte blocks
+    remaining: u8,
+    /// HBlank DMA in progress
+    hblank_active: bool,
+}
+
+/// Kind of transfer started by a write to HDMA5
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HdmaStart {
+    /// Run the whole transfer now
+    GeneralPurpose,
+    /// Transfer one block per HBlank
+    HBlank,
+    /// An active HBlank transfer was cancelled
+    Cancelled,
+}
+
+/// CPU cycles the CPU is halted for each 16-byte block (8 M-cycles at
+/// normal speed, twice as many CPU cycles in double-speed mode)
+pub const BLOCK_CYCLES: u64 = 32;
+
+impl Hdma {
+    pub fn new() -> Self {
+        Self {
+            source: 0,
+            dest: 0,
+            remaining: 0,
+            hblank_active: false,
+        }
+    }
+
+    pub fn write_hdma1(&mut self, value: u8) {
+        self.source = (self.source & 0x00F0) | ((value as u16) << 8);
+    }
+
+    pub fn write_hdma2(&mut self, value: u8) {
+        self.source = (self.source & 0xFF00) | (value & 0xF0) as u16;
+    }
+
+    pub fn write_hdma3(&mut self, value: u8) {
+        self.dest = (self.dest & 0x00F0) | (((value & 0x1F) as u16) << 8);
+    }
+
+    pub fn write_hdma4(&mut self, value: u8) {
+        self.dest = (self.dest & 0x1F00) | (value & 0xF0) as u16;
+    }
+
+    /// Read HDMA5
+    ///
+    /// While an HBlank transfer is active, bit 7 is clear and bits 0-6 hold
+    /// the remaining length. Once finished this reads 0xFF; after a
+    /// cancellation bit 7 is set and the remaining length is kept.
+    pub fn read_hdma5(&self) -> u8 {
+        let length = self.remaining.wrapping_sub(1) & 0x7F;
+        if self.hblank_active {
+            length
+        } else if self.remaining == 0 {
+            0xFF
+        } else {
+            0x80 | length
+        }
+    }
+
+    /// Write HDMA5, starting or cancelling a transfer
+    pub fn write_hdma5(&mut self, value: u8) -> HdmaStart {
+        if self.hblank_active && value & 0x80 == 0 {
+            self.hblank_active = false;
+            return HdmaStart::Cancelled;
+        }
+
+        self.remaining = (value & 0x7F) + 1;
+
+        if value & 0x80 != 0 {
+            self.hblank_active = true;
+            HdmaStart::HBlank
+        } else {
+            HdmaStart::GeneralPurpose
+        }
+    }
+
+    /// Check if an HBlank transfer is waiting for the next HBlank
+    pub fn is_hblank_active(&self) -> bool {
+        self.hblank_active
+    }
+
+    /// Take the next 16-byte block to copy
+    ///
+    /// Returns the (source, destination) addresses of the block and advances
+    /// the transfer, or None when nothing is left.
+    pub fn next_block(&mut self) -> Option<(u16, u16)> {
+        if self.remaining == 0 {
+            return None;
+        }
+
+        let block = (self.source, 0x8000 | self.dest);
+
+        self.source = self.source.wrapping_add(0x10);
+        self.dest = (self.dest + 0x10) & 0x1FF0;
+        self.remaining -= 1;
+
+        if self.remaining == 0 {
+            self.hblank_active = false;
+        }
+
+        Some(block)
+    }
+}
+
+impl Default for Hdma {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_address_registers() {
+        let mut hdma = Hdma::new();
+        hdma.write_hdma1(0xD1);
+        hdma.write_hdma2(0x2F); // Low 4 bits ignored
+        hdma.write_hdma3(0xFF); // Only bits 0-4 used
+        hdma.write_hdma4(0x4F);
+
+        hdma.write_hdma5(0x00);
+        assert_eq!(hdma.next_block(), Some((0xD120, 0x9F40)));
+    }
+
+    #[test]
+    fn test_general_purpose_blocks() {
+        let mut hdma = Hdma::new();
+        hdma.write_hdma1(0xC0);
+
+        assert_eq!(hdma.write_hdma5(0x01), HdmaStart::GeneralPurpose);
+        assert_eq!(hdma.next_block(), Some((0xC000, 0x8000)));
+        assert_eq!(hdma.next_block(), Some((0xC010, 0x8010)));
+        assert_eq!(hdma.next_block(), None);
+        assert_eq!(hdma.read_hdma5(), 0xFF);
+    }
+
+    #[test]
+    fn test_hblank_remaining_and_cancel() {
+        let mut hdma = Hdma::new();
+
+        assert_eq!(hdma.write_hdma5(0x82), HdmaStart::HBlank);
+        assert!(hdma.is_hblank_active());
+        assert_eq!(hdma.read_hdma5(), 0x02);
+
+        hdma.next_block();
+        assert_eq!(hdma.read_hdma5(), 0x01);
+
+        assert_eq!(hdma.write_hdma5(0x00), HdmaStart::Cancelled);
+        assert!(!hdma.is_hblank_active());
+        assert_eq!(hdma.read_hdma5(), 0x81);
+    }
+
+    #[test]
+    fn test_destination_wraps_within_vram() {
+        let mut hdma = Hdma::new();
+        hdma.write_hdma3(0x1F);
+        hdma.write_hdma4(0xF0);
+
+        hdma.write_hdma5(0x01);
+        assert_eq!(hdma.next_block().unwrap().1, 0x9FF0);
+        assert_eq!(hdma.next_block().unwrap().1, 0x8000);
+    }
+}
@@ -1,6 +1,11 @@
-use crate::io::{IE, IF};
+use crate::decode::{
+    AluOp, CB_OPCODES, Cond, Instruction, OPCODES, R8, R16, R16Mem, R16Stack, ShiftOp,
+};
+use crate::io::{DIV, IE, IF};
 use crate::memory::Memory;
+use crate::oam_bug::OamCorruption;
 use crate::system::GameBoy;
+use std::marker::PhantomData;
 
 #[cfg(test)]
 use crate::memory::FlatMemory;
@@ -57,10 +62,9 @@ fn service_interrupts<M: Memory>(state: &mut GameBoy<M>) -> bool {
     let new_if = if_flags & !(1 << interrupt_bit);
     state.write(IF, new_if);
 
-    // Push PC onto stack
-    state.sp = state.sp.wrapping_sub(2);
-    state.write(state.sp, (state.pc & 0xFF) as u8);
-    state.write(state.sp.wrapping_add(1), (state.pc >> 8) as u8);
+    // Push PC onto stack (after two wait cycles)
+    state.tick();
+    push_word(state.pc, state);
 
     // Jump to interrupt vector
     state.pc = vector;
@@ -71,6 +75,111 @@ fn service_interrupts<M: Memory>(state: &mut GameBoy<M>) -> bool {
     true
 }
 
+/// Check a branch condition against the flags
+fn condition<M: Memory>(cond: Cond, state: &GameBoy<M>) -> bool {
+    match cond {
+        Cond::NZ => !state.flag_z(),
+        Cond::Z => state.flag_z(),
+        Cond::NC => !state.flag_c(),
+        Cond::C => state.flag_c(),
+    }
+}
+
+/// Read an 8-bit operand; (HL) takes one M-cycle
+#[inline(always)]
+fn read_r8<M: Memory>(r8: R8, state: &mut GameBoy<M>) -> u8 {
+    match r8 {
+        R8::B => state.b,
+        R8::C => state.c,
+        R8::D => state.d,
+        R8::E => state.e,
+        R8::H => state.h,
+        R8::L => state.l,
+        R8::HlIndirect => state.cpu_read(state.hl()),
+        R8::A => state.a,
+    }
+}
+
+/// Write an 8-bit operand; (HL) takes one M-cycle
+#[inline(always)]
+fn write_r8<M: Memory>(r8: R8, value: u8, state: &mut GameBoy<M>) {
+    match r8 {
+        R8::B => state.b = value,
+        R8::C => state.c = value,
+        R8::D => state.d = value,
+        R8::E => state.e = value,
+        R8::H => state.h = value,
+        R8::L => state.l = value,
+        R8::HlIndirect => state.cpu_write(state.hl(), value),
+        R8::A => state.a = value,
+    }
+}
+
+#[inline(always)]
+fn read_r16<M: Memory>(r16: R16, state: &GameBoy<M>) -> u16 {
+    match r16 {
+        R16::BC => state.bc(),
+        R16::DE => state.de(),
+        R16::HL => state.hl(),
+        R16::SP => state.sp,
+    }
+}
+
+#[inline(always)]
+fn write_r16<M: Memory>(r16: R16, value: u16, state: &mut GameBoy<M>) {
+    match r16 {
+        R16::BC => state.set_bc(value),
+        R16::DE => state.set_de(value),
+        R16::HL => state.set_hl(value),
+        R16::SP => state.sp = value,
+    }
+}
+
+/// Apply an ALU operation to A
+#[inline(always)]
+fn alu<M: Memory>(op: AluOp, value: u8, state: &mut GameBoy<M>) {
+    match op {
+        AluOp::Add => add_a(value, state),
+        AluOp::Adc => adc_a(value, state),
+        AluOp::Sub => sub_a(value, state),
+        AluOp::Sbc => sbc_a(value, state),
+        AluOp::And => and_a(value, state),
+        AluOp::Xor => xor_a(value, state),
+        AluOp::Or => or_a(value, state),
+        AluOp::Cp => cp_a(value, state),
+    }
+}
+
+/// INC r8 - Increment an 8-bit operand by 1
+fn inc_r8<M: Memory>(r8: R8, state: &mut GameBoy<M>) {
+    let value = read_r8(r8, state);
+    let result = inc_byte(value, state);
+    write_r8(r8, result, state);
+}
+
+/// DEC r8 - Decrement an 8-bit operand by 1
+fn dec_r8<M: Memory>(r8: R8, state: &mut GameBoy<M>) {
+    let value = read_r8(r8, state);
+    let result = dec_byte(value, state);
+    write_r8(r8, result, state);
+}
+
+/// RLC, RRC, RL, RR, SLA, SRA, SWAP or SRL on an 8-bit operand
+fn shift_r8<M: Memory>(op: ShiftOp, r8: R8, state: &mut GameBoy<M>) {
+    let value = read_r8(r8, state);
+    let result = match op {
+        ShiftOp::Rlc => rlc_byte(value, state),
+        ShiftOp::Rrc => rrc_byte(value, state),
+        ShiftOp::Rl => rl_byte(value, state),
+        ShiftOp::Rr => rr_byte(value, state),
+        ShiftOp::Sla => sla_byte(value, state),
+        ShiftOp::Sra => sra_byte(value, state),
+        ShiftOp::Swap => swap_byte(value, state),
+        ShiftOp::Srl => srl_byte(value, state),
+    };
+    write_r8(r8, result, state);
+}
+
 /// Add an 8-bit value to register A and update flags accordingly
 /// Z: Set if result is zero
 /// N: Reset (addition operation)
@@ -208,16 +317,16 @@ fn cp_a<M: Memory>(value: u8, state: &mut GameBoy<M>) {
 
 /// Read immediate byte from PC and advance PC
 fn read_immediate_byte<M: Memory>(state: &mut GameBoy<M>) -> u8 {
-    let value = state.read(state.pc);
+    let value = state.cpu_read(state.pc);
     state.pc += 1;
     value
 }
 
 /// Read immediate 16-bit word from PC and advance PC (little-endian)
 fn read_immediate_word<M: Memory>(state: &mut GameBoy<M>) -> u16 {
-    let low = state.read(state.pc);
+    let low = state.cpu_read(state.pc);
     state.pc += 1;
-    let high = state.read(state.pc);
+    let high = state.cpu_read(state.pc);
     state.pc += 1;
     ((high as u16) << 8) | (low as u16)
 }
@@ -225,11 +334,11 @@ fn read_immediate_word<M: Memory>(state: &mut GameBoy<M>) -> u16 {
 /// Pop word (16-bit) value from stack (little-endian)
 fn pop_word<M: Memory>(state: &mut GameBoy<M>) -> u16 {
     // Pop low byte
-    let low = state.read(state.sp);
+    let low = state.cpu_read_idu(state.sp);
     state.sp = state.sp.wrapping_add(1);
 
     // Pop high byte
-    let high = state.read(state.sp);
+    let high = state.cpu_read(state.sp);
     state.sp = state.sp.wrapping_add(1);
 
     // Return the 16-bit value (little-endian)
@@ -241,32 +350,17 @@ fn ret<M: Memory>(state: &mut GameBoy<M>) {
     state.pc = pop_word(state);
 }
 
-/// Return from subroutine if Z flag is clear (NZ)
-fn ret_nz<M: Memory>(state: &mut GameBoy<M>) {
-    if !state.flag_z() {
-        ret(state);
-    }
-}
-
-/// Return from subroutine if Z flag is set (Z)
-fn ret_z<M: Memory>(state: &mut GameBoy<M>) {
-    if state.flag_z() {
-        ret(state);
-    }
-}
-
-/// Return from subroutine if C flag is clear (NC)
-fn ret_nc<M: Memory>(state: &mut GameBoy<M>) {
-    if !state.flag_c() {
-        ret(state);
-    }
-}
-
-/// Return from subroutine if C flag is set (C)
-fn ret_c<M: Memory>(state: &mut GameBoy<M>) {
-    if state.flag_c() {
+/// RET cc - Return from subroutine if the condition holds
+///
+/// Returns true if the branch was taken.
+fn ret_cond<M: Memory>(cond: Cond, state: &mut GameBoy<M>) -> bool {
+    // Internal cycle to check the condition
+    state.tick();
+    let taken = condition(cond, state);
+    if taken {
         ret(state);
     }
+    taken
 }
 
 /// Return from interrupt - pop PC and enable interrupts
@@ -275,29 +369,27 @@ fn reti<M: Memory>(state: &mut GameBoy<M>) {
     state.ime = true; // Enable interrupts
 }
 
-/// Pop 16-bit value from stack into BC register pair
-fn pop_bc<M: Memory>(state: &mut GameBoy<M>) {
+/// POP r16 - Pop a 16-bit value from the stack into a register pair
+fn pop_r16<M: Memory>(r16: R16Stack, state: &mut GameBoy<M>) {
     let value = pop_word(state);
-    state.c = value as u8; // Low byte
-    state.b = (value >> 8) as u8; // High byte
-}
-
-/// Pop 16-bit value from stack into DE register pair
-fn pop_de<M: Memory>(state: &mut GameBoy<M>) {
-    let value = pop_word(state);
-    state.e = value as u8; // Low byte
-    state.d = (value >> 8) as u8; // High byte
-}
-
-/// Push DE register pair onto stack
-fn push_de<M: Memory>(state: &mut GameBoy<M>) {
-    let value = ((state.d as u16) << 8) | (state.e as u16);
-    push_word(value, state);
-}
-
-/// Push BC register pair onto stack
-fn push_bc<M: Memory>(state: &mut GameBoy<M>) {
-    let value = ((state.b as u16) << 8) | (state.c as u16);
+    let [low, high] = value.to_le_bytes();
+    match r16 {
+        R16Stack::BC => (state.b, state.c) = (high, low),
+        R16Stack::DE => (state.d, state.e) = (high, low),
+        R16Stack::HL => (state.h, state.l) = (high, low),
+        // The low 4 bits of F are always 0
+        R16Stack::AF => (state.a, state.f) = (high, low & 0xF0),
+    }
+}
+
+/// PUSH r16 - Push a register pair onto the stack
+fn push_r16<M: Memory>(r16: R16Stack, state: &mut GameBoy<M>) {
+    let value = match r16 {
+        R16Stack::BC => state.bc(),
+        R16Stack::DE => state.de(),
+        R16Stack::HL => state.hl(),
+        R16Stack::AF => state.af(),
+    };
     push_word(value, state);
 }
 
@@ -307,145 +399,71 @@ fn jp<M: Memory>(state: &mut GameBoy<M>) {
     state.pc = address;
 }
 
-/// Jump to absolute address if Z flag is clear (NZ)
-fn jp_nz<M: Memory>(state: &mut GameBoy<M>) {
-    let address = read_immediate_word(state);
-    if !state.flag_z() {
-        state.pc = address;
-    }
-}
-
-/// Jump to absolute address if Z flag is set (Z)
-fn jp_z<M: Memory>(state: &mut GameBoy<M>) {
-    let address = read_immediate_word(state);
-    if state.flag_z() {
-        state.pc = address;
-    }
-}
-
-/// Jump to absolute address if C flag is clear (NC)
-fn jp_nc<M: Memory>(state: &mut GameBoy<M>) {
-    let address = read_immediate_word(state);
-    if !state.flag_c() {
-        state.pc = address;
-    }
-}
-
-/// Jump to absolute address if C flag is set (C)
-fn jp_c<M: Memory>(state: &mut GameBoy<M>) {
+/// JP cc - Jump to absolute address if the condition holds
+///
+/// Returns true if the branch was taken.
+fn jp_cond<M: Memory>(cond: Cond, state: &mut GameBoy<M>) -> bool {
     let address = read_immediate_word(state);
-    if state.flag_c() {
+    let taken = condition(cond, state);
+    if taken {
         state.pc = address;
     }
+    taken
 }
 
 /// Push word (16-bit) value onto stack (little-endian)
 fn push_word<M: Memory>(value: u16, state: &mut GameBoy<M>) {
+    // SP is decremented during an internal cycle before the writes
+    idu_cycle(state, state.sp);
+
     // Push high byte first
     state.sp = state.sp.wrapping_sub(1);
-    state.write(state.sp, (value >> 8) as u8);
+    state.cpu_write(state.sp, (value >> 8) as u8);
 
     // Push low byte
     state.sp = state.sp.wrapping_sub(1);
-    state.write(state.sp, value as u8);
+    state.cpu_write(state.sp, value as u8);
 }
 
 /// Call subroutine - push return address and jump to address
 fn call<M: Memory>(state: &mut GameBoy<M>) {
-    // Push return address (PC + 2, after reading the 2-byte address)
-    push_word(state.pc + 2, state);
-    // Jump to the target address
-    jp(state);
-}
-
-/// Call subroutine if Z flag is clear (NZ)
-fn call_nz<M: Memory>(state: &mut GameBoy<M>) {
-    if !state.flag_z() {
-        call(state);
-    } else {
-        // Skip the 2-byte address
-        state.pc += 2;
-    }
-}
-
-/// Call subroutine if Z flag is set (Z)
-fn call_z<M: Memory>(state: &mut GameBoy<M>) {
-    if state.flag_z() {
-        call(state);
-    } else {
-        // Skip the 2-byte address
-        state.pc += 2;
-    }
-}
-
-/// Call subroutine if C flag is clear (NC)
-fn call_nc<M: Memory>(state: &mut GameBoy<M>) {
-    if !state.flag_c() {
-        call(state);
-    } else {
-        // Skip the 2-byte address
-        state.pc += 2;
-    }
+    // Read the target address, then push the return address (after it)
+    let address = read_immediate_word(state);
+    push_word(state.pc, state);
+    state.pc = address;
 }
 
-/// Call subroutine if C flag is set (C)
-fn call_c<M: Memory>(state: &mut GameBoy<M>) {
-    if state.flag_c() {
+/// CALL cc - Call subroutine if the condition holds
+///
+/// Returns true if the branch was taken.
+fn call_cond<M: Memory>(cond: Cond, state: &mut GameBoy<M>) -> bool {
+    let taken = condition(cond, state);
+    if taken {
         call(state);
     } else {
         // Skip the 2-byte address
-        state.pc += 2;
+        state.pc = state.pc.wrapping_add(2);
     }
+    taken
 }
 
-/// RST 00h - Push PC and jump to address 0x0000
-fn rst_00<M: Memory>(state: &mut GameBoy<M>) {
-    push_word(state.pc, state);
-    state.pc = 0x0000;
-}
-
-/// RST 08h - Push PC and jump to address 0x0008
-fn rst_08<M: Memory>(state: &mut GameBoy<M>) {
-    push_word(state.pc, state);
-    state.pc = 0x0008;
-}
-
-/// RST 10h - Push PC and jump to address 0x0010
-fn rst_10<M: Memory>(state: &mut GameBoy<M>) {
-    push_word(state.pc, state);
-    state.pc = 0x0010;
-}
-
-/// RST 18h - Push PC and jump to address 0x0018
-fn rst_18<M: Memory>(state: &mut GameBoy<M>) {
+/// RST - Push PC and jump to a fixed vector
+fn rst<M: Memory>(vector: u8, state: &mut GameBoy<M>) {
     push_word(state.pc, state);
-    state.pc = 0x0018;
-}
-
-/// Pop 16-bit value from stack into HL register pair
-fn pop_hl<M: Memory>(state: &mut GameBoy<M>) {
-    let value = pop_word(state);
-    state.l = value as u8; // Low byte
-    state.h = (value >> 8) as u8; // High byte
-}
-
-/// Push HL register pair onto stack
-fn push_hl<M: Memory>(state: &mut GameBoy<M>) {
-    let value = ((state.h as u16) << 8) | (state.l as u16);
-    push_word(value, state);
+    state.pc = vector as u16;
 }
 
 /// LDH (n),A - Load A into high memory (0xFF00 + n)
 fn ldh_n_a<M: Memory>(state: &mut GameBoy<M>) {
     let offset = read_immediate_byte(state);
     let address = 0xFF00 | (offset as u16);
-    state.write(address, state.a);
+    state.cpu_write(address, state.a);
 }
 
 /// LDH (C),A - Load A into high memory (0xFF00 + C)
 fn ldh_c_a<M: Memory>(state: &mut GameBoy<M>) {
     let address = 0xFF00 | (state.c as u16);
-    state.write(address, state.a);
+    state.cpu_write(address, state.a);
 }
 
 /// Illegal/undefined opcode handler
@@ -458,30 +476,6 @@ fn illegal_opcode<M: Memory>(opcode: u8, state: &GameBoy<M>) -> ! {
     );
 }
 
-/// RST 20h - Push PC and jump to address 0x0020
-fn rst_20<M: Memory>(state: &mut GameBoy<M>) {
-    push_word(state.pc, state);
-    state.pc = 0x0020;
-}
-
-/// RST 28h - Push PC and jump to address 0x0028
-fn rst_28<M: Memory>(state: &mut GameBoy<M>) {
-    push_word(state.pc, state);
-    state.pc = 0x0028;
-}
-
-/// RST 30h - Push PC and jump to address 0x0030
-fn rst_30<M: Memory>(state: &mut GameBoy<M>) {
-    push_word(state.pc, state);
-    state.pc = 0x0030;
-}
-
-/// RST 38h - Push PC and jump to address 0x0038
-fn rst_38<M: Memory>(state: &mut GameBoy<M>) {
-    push_word(state.pc, state);
-    state.pc = 0x0038;
-}
-
 /// ADD SP,n - Add signed immediate byte to SP
 /// Z: Reset
 /// N: Reset
@@ -509,20 +503,20 @@ fn jp_hl<M: Memory>(state: &mut GameBoy<M>) {
 /// LD (nn),A - Load A into memory at absolute address
 fn ld_nn_a<M: Memory>(state: &mut GameBoy<M>) {
     let address = read_immediate_word(state);
-    state.write(address, state.a);
+    state.cpu_write(address, state.a);
 }
 
 /// LDH A,(n) - Load from high memory (0xFF00 + n) into A
 fn ldh_a_n<M: Memory>(state: &mut GameBoy<M>) {
     let offset = read_immediate_byte(state);
     let address = 0xFF00 | (offset as u16);
-    state.a = state.read(address);
+    state.a = state.cpu_read(address);
 }
 
 /// LDH A,(C) - Load from high memory (0xFF00 + C) into A
 fn ldh_a_c<M: Memory>(state: &mut GameBoy<M>) {
     let address = 0xFF00 | (state.c as u16);
-    state.a = state.read(address);
+    state.a = state.cpu_read(address);
 }
 
 /// LD HL,SP+n - Load SP plus signed immediate into HL
@@ -547,20 +541,7 @@ fn ld_hl_sp_n<M: Memory>(state: &mut GameBoy<M>) {
 /// LD A,(nn) - Load from absolute address into A
 fn ld_a_nn<M: Memory>(state: &mut GameBoy<M>) {
     let address = read_immediate_word(state);
-    state.a = state.read(address);
-}
-
-/// Pop 16-bit value from stack into AF register pair
-fn pop_af<M: Memory>(state: &mut GameBoy<M>) {
-    let value = pop_word(state);
-    state.f = (value as u8) & 0xF0; // Low byte (flags), lower 4 bits always 0
-    state.a = (value >> 8) as u8; // High byte
-}
-
-/// Push AF register pair onto stack
-fn push_af<M: Memory>(state: &mut GameBoy<M>) {
-    let value = ((state.a as u16) << 8) | (state.f as u16);
-    push_word(value, state);
+    state.a = state.cpu_read(address);
 }
 
 /// HALT instruction - Enter low-power mode or trigger HALT bug
@@ -583,6 +564,37 @@ fn halt<M: Memory>(state: &mut GameBoy<M>) {
     }
 }
 
+/// STOP instruction
+///
+/// STOP is followed by a padding byte which is skipped.
+/// The CPU enters STOP mode until a selected joypad input line goes low.
+/// On CGB, when a speed switch was armed through KEY1, STOP toggles
+/// between normal and double speed instead, and the CPU is paused for
+/// 2050 M-cycles while the clock settles. DIV is reset either way.
+fn stop<M: Memory>(state: &mut GameBoy<M>) {
+    state.pc = state.pc.wrapping_add(1);
+    state.write(DIV, 0);
+
+    if state.model.is_cgb() && state.speed_switch_armed {
+        state.speed_switch_armed = false;
+        state.double_speed = !state.double_speed;
+        state.stall(2050 * 4);
+    } else if !state.joypad.has_input() {
+        state.stopped = true;
+    }
+}
+
+/// Handle STOP mode
+///
+/// Returns true if the CPU should continue executing, false if it is
+/// still stopped waiting for joypad input.
+fn handle_stop<M: Memory>(state: &mut GameBoy<M>) -> bool {
+    if state.stopped && state.joypad.has_input() {
+        state.stopped = false;
+    }
+    !state.stopped
+}
+
 /// Handle delayed interrupt master enable (IME) changes
 ///
 /// EI and DI instructions have a 1-instruction delay before taking effect.
@@ -595,19 +607,15 @@ fn halt<M: Memory>(state: &mut GameBoy<M>) {
 /// Must be called BEFORE halt check so IME changes are processed even when halted
 fn handle_delayed_ime<M: Memory>(state: &mut GameBoy<M>) {
     // Handle delayed interrupt disable (DI instruction)
-    if state.di_delay {
-        if state.last_opcode != OPCODE_DI {
-            state.di_delay = false;
-            state.ime = false;
-        }
+    if state.di_delay && state.last_opcode != OPCODE_DI {
+        state.di_delay = false;
+        state.ime = false;
     }
 
     // Handle delayed interrupt enable (EI instruction)
-    if state.ei_delay {
-        if state.last_opcode != OPCODE_EI {
-            state.ei_delay = false;
-            state.ime = true;
-        }
+    if state.ei_delay && state.last_opcode != OPCODE_EI {
+        state.ei_delay = false;
+        state.ime = true;
     }
 }
 
@@ -660,41 +668,6 @@ fn inc_byte<M: Memory>(value: u8, state: &mut GameBoy<M>) -> u8 {
     result
 }
 
-/// Increment the register A by 1 and update flags accordingly
-fn inc_a<M: Memory>(state: &mut GameBoy<M>) {
-    state.a = inc_byte(state.a, state);
-}
-
-// Increment the register B by 1 and update flags accordingly
-fn inc_b<M: Memory>(state: &mut GameBoy<M>) {
-    state.b = inc_byte(state.b, state);
-}
-
-// Increment the register C by 1 and update flags accordingly
-fn inc_c<M: Memory>(state: &mut GameBoy<M>) {
-    state.c = inc_byte(state.c, state);
-}
-
-/// Increment the register D by 1 and update flags accordingly
-fn inc_d<M: Memory>(state: &mut GameBoy<M>) {
-    state.d = inc_byte(state.d, state);
-}
-
-/// Increment the register E by 1 and update flags accordingly
-fn inc_e<M: Memory>(state: &mut GameBoy<M>) {
-    state.e = inc_byte(state.e, state);
-}
-
-/// Increment the register H by 1 and update flags accordingly
-fn inc_h<M: Memory>(state: &mut GameBoy<M>) {
-    state.h = inc_byte(state.h, state);
-}
-
-/// Increment the register L by 1 and update flags accordingly
-fn inc_l<M: Memory>(state: &mut GameBoy<M>) {
-    state.l = inc_byte(state.l, state);
-}
-
 /// Decrement a byte value by 1 and update flags accordingly
 fn dec_byte<M: Memory>(value: u8, state: &mut GameBoy<M>) -> u8 {
     let result = value.wrapping_sub(1);
@@ -704,41 +677,6 @@ fn dec_byte<M: Memory>(value: u8, state: &mut GameBoy<M>) -> u8 {
     result
 }
 
-/// Decrement the register A by 1 and update flags accordingly
-fn dec_a<M: Memory>(state: &mut GameBoy<M>) {
-    state.a = dec_byte(state.a, state);
-}
-
-/// Decrement the register B by 1 and update flags accordingly
-fn dec_b<M: Memory>(state: &mut GameBoy<M>) {
-    state.b = dec_byte(state.b, state);
-}
-
-/// Decrement the register C by 1 and update flags accordingly
-fn dec_c<M: Memory>(state: &mut GameBoy<M>) {
-    state.c = dec_byte(state.c, state);
-}
-
-/// Decrement the register D by 1 and update flags accordingly
-fn dec_d<M: Memory>(state: &mut GameBoy<M>) {
-    state.d = dec_byte(state.d, state);
-}
-
-/// Decrement the register E by 1 and update flags accordingly
-fn dec_e<M: Memory>(state: &mut GameBoy<M>) {
-    state.e = dec_byte(state.e, state);
-}
-
-/// Decrement the register H by 1 and update flags accordingly
-fn dec_h<M: Memory>(state: &mut GameBoy<M>) {
-    state.h = dec_byte(state.h, state);
-}
-
-/// Decrement the register L by 1 and update flags accordingly
-fn dec_l<M: Memory>(state: &mut GameBoy<M>) {
-    state.l = dec_byte(state.l, state);
-}
-
 /// Rotate left circular (RLC) - rotates value left, bit 7 goes to carry and bit 0
 fn rlc_byte<M: Memory>(value: u8, state: &mut GameBoy<M>) -> u8 {
     let bit7 = (value & 0x80) != 0;
@@ -752,73 +690,6 @@ fn rlc_byte<M: Memory>(value: u8, state: &mut GameBoy<M>) -> u8 {
     result
 }
 
-/// Rotate register A left circular
-fn rlc_a<M: Memory>(state: &mut GameBoy<M>) {
-    state.a = rlc_byte(state.a, state);
-}
-
-/// Rotate register B left circular
-fn rlc_b<M: Memory>(state: &mut GameBoy<M>) {
-    state.b = rlc_byte(state.b, state);
-}
-
-/// Rotate register C left circular
-fn rlc_c<M: Memory>(state: &mut GameBoy<M>) {
-    state.c = rlc_byte(state.c, state);
-}
-
-/// Rotate register D left circular
-fn rlc_d<M: Memory>(state: &mut GameBoy<M>) {
-    state.d = rlc_byte(state.d, state);
-}
-
-/// Rotate register E left circular
-fn rlc_e<M: Memory>(state: &mut GameBoy<M>) {
-    state.e = rlc_byte(state.e, state);
-}
-
-/// Rotate register H left circular
-fn rlc_h<M: Memory>(state: &mut GameBoy<M>) {
-    state.h = rlc_byte(state.h, state);
-}
-
-/// Rotate register L left circular
-fn rlc_l<M: Memory>(state: &mut GameBoy<M>) {
-    state.l = rlc_byte(state.l, state);
-}
-
-/// Rotate value at (HL) left circular
-fn rlc_hl_indirect<M: Memory>(state: &mut GameBoy<M>) {
-    let addr = state.hl();
-    let value = state.read(addr);
-    let result = rlc_byte(value, state);
-    state.write(addr, result);
-}
-
-/// Rotate value at (HL) right circular
-fn rrc_hl_indirect<M: Memory>(state: &mut GameBoy<M>) {
-    let addr = state.hl();
-    let value = state.read(addr);
-    let result = rrc_byte(value, state);
-    state.write(addr, result);
-}
-
-/// Rotate left through carry - value at (HL)
-fn rl_hl_indirect<M: Memory>(state: &mut GameBoy<M>) {
-    let addr = state.hl();
-    let value = state.read(addr);
-    let result = rl_byte(value, state);
-    state.write(addr, result);
-}
-
-/// Rotate right through carry - value at (HL)
-fn rr_hl_indirect<M: Memory>(state: &mut GameBoy<M>) {
-    let addr = state.hl();
-    let value = state.read(addr);
-    let result = rr_byte(value, state);
-    state.write(addr, result);
-}
-
 /// RLCA - Rotate A left circular (always resets Z flag)
 fn rlca<M: Memory>(state: &mut GameBoy<M>) {
     state.a = rlc_byte(state.a, state);
@@ -838,41 +709,6 @@ fn rrc_byte<M: Memory>(value: u8, state: &mut GameBoy<M>) -> u8 {
     result
 }
 
-/// Rotate register A right circular
-fn rrc_a<M: Memory>(state: &mut GameBoy<M>) {
-    state.a = rrc_byte(state.a, state);
-}
-
-/// Rotate register B right circular
-fn rrc_b<M: Memory>(state: &mut GameBoy<M>) {
-    state.b = rrc_byte(state.b, state);
-}
-
-/// Rotate register C right circular
-fn rrc_c<M: Memory>(state: &mut GameBoy<M>) {
-    state.c = rrc_byte(state.c, state);
-}
-
-/// Rotate register D right circular
-fn rrc_d<M: Memory>(state: &mut GameBoy<M>) {
-    state.d = rrc_byte(state.d, state);
-}
-
-/// Rotate register E right circular
-fn rrc_e<M: Memory>(state: &mut GameBoy<M>) {
-    state.e = rrc_byte(state.e, state);
-}
-
-/// Rotate register H right circular
-fn rrc_h<M: Memory>(state: &mut GameBoy<M>) {
-    state.h = rrc_byte(state.h, state);
-}
-
-/// Rotate register L right circular
-fn rrc_l<M: Memory>(state: &mut GameBoy<M>) {
-    state.l = rrc_byte(state.l, state);
-}
-
 /// RRCA - Rotate A right circular (always resets Z flag)
 fn rrca<M: Memory>(state: &mut GameBoy<M>) {
     state.a = rrc_byte(state.a, state);
@@ -894,41 +730,6 @@ fn rl_byte<M: Memory>(value: u8, state: &mut GameBoy<M>) -> u8 {
     result
 }
 
-/// Rotate register A left through carry
-fn rl_a<M: Memory>(state: &mut GameBoy<M>) {
-    state.a = rl_byte(state.a, state);
-}
-
-/// Rotate register B left through carry
-fn rl_b<M: Memory>(state: &mut GameBoy<M>) {
-    state.b = rl_byte(state.b, state);
-}
-
-/// Rotate register C left through carry
-fn rl_c<M: Memory>(state: &mut GameBoy<M>) {
-    state.c = rl_byte(state.c, state);
-}
-
-/// Rotate register D left through carry
-fn rl_d<M: Memory>(state: &mut GameBoy<M>) {
-    state.d = rl_byte(state.d, state);
-}
-
-/// Rotate register E left through carry
-fn rl_e<M: Memory>(state: &mut GameBoy<M>) {
-    state.e = rl_byte(state.e, state);
-}
-
-/// Rotate register H left through carry
-fn rl_h<M: Memory>(state: &mut GameBoy<M>) {
-    state.h = rl_byte(state.h, state);
-}
-
-/// Rotate register L left through carry
-fn rl_l<M: Memory>(state: &mut GameBoy<M>) {
-    state.l = rl_byte(state.l, state);
-}
-
 /// RLA - Rotate A left through carry (always resets Z flag)
 fn rla<M: Memory>(state: &mut GameBoy<M>) {
     state.a = rl_byte(state.a, state);
@@ -950,41 +751,6 @@ fn rr_byte<M: Memory>(value: u8, state: &mut GameBoy<M>) -> u8 {
     result
 }
 
-/// Rotate register A right through carry
-fn rr_a<M: Memory>(state: &mut GameBoy<M>) {
-    state.a = rr_byte(state.a, state);
-}
-
-/// Rotate register B right through carry
-fn rr_b<M: Memory>(state: &mut GameBoy<M>) {
-    state.b = rr_byte(state.b, state);
-}
-
-/// Rotate register C right through carry
-fn rr_c<M: Memory>(state: &mut GameBoy<M>) {
-    state.c = rr_byte(state.c, state);
-}
-
-/// Rotate register D right through carry
-fn rr_d<M: Memory>(state: &mut GameBoy<M>) {
-    state.d = rr_byte(state.d, state);
-}
-
-/// Rotate register E right through carry
-fn rr_e<M: Memory>(state: &mut GameBoy<M>) {
-    state.e = rr_byte(state.e, state);
-}
-
-/// Rotate register H right through carry
-fn rr_h<M: Memory>(state: &mut GameBoy<M>) {
-    state.h = rr_byte(state.h, state);
-}
-
-/// Rotate register L right through carry
-fn rr_l<M: Memory>(state: &mut GameBoy<M>) {
-    state.l = rr_byte(state.l, state);
-}
-
 /// RRA - Rotate A right through carry (always resets Z flag)
 fn rra<M: Memory>(state: &mut GameBoy<M>) {
     state.a = rr_byte(state.a, state);
@@ -1005,49 +771,6 @@ fn sla_byte<M: Memory>(value: u8, state: &mut GameBoy<M>) -> u8 {
     result
 }
 
-/// Shift register A left arithmetic
-fn sla_a<M: Memory>(state: &mut GameBoy<M>) {
-    state.a = sla_byte(state.a, state);
-}
-
-/// Shift register B left arithmetic
-fn sla_b<M: Memory>(state: &mut GameBoy<M>) {
-    state.b = sla_byte(state.b, state);
-}
-
-/// Shift register C left arithmetic
-fn sla_c<M: Memory>(state: &mut GameBoy<M>) {
-    state.c = sla_byte(state.c, state);
-}
-
-/// Shift register D left arithmetic
-fn sla_d<M: Memory>(state: &mut GameBoy<M>) {
-    state.d = sla_byte(state.d, state);
-}
-
-/// Shift register E left arithmetic
-fn sla_e<M: Memory>(state: &mut GameBoy<M>) {
-    state.e = sla_byte(state.e, state);
-}
-
-/// Shift register H left arithmetic
-fn sla_h<M: Memory>(state: &mut GameBoy<M>) {
-    state.h = sla_byte(state.h, state);
-}
-
-/// Shift register L left arithmetic
-fn sla_l<M: Memory>(state: &mut GameBoy<M>) {
-    state.l = sla_byte(state.l, state);
-}
-
-/// Shift value at (HL) left arithmetic
-fn sla_hl_indirect<M: Memory>(state: &mut GameBoy<M>) {
-    let addr = state.hl();
-    let value = state.read(addr);
-    let result = sla_byte(value, state);
-    state.write(addr, result);
-}
-
 /// SRA - Shift Right Arithmetic
 /// Shifts value right, bit 0 goes to carry, bit 7 stays the same (preserves sign)
 fn sra_byte<M: Memory>(value: u8, state: &mut GameBoy<M>) -> u8 {
@@ -1063,53 +786,10 @@ fn sra_byte<M: Memory>(value: u8, state: &mut GameBoy<M>) -> u8 {
     result
 }
 
-/// Shift register A right arithmetic
-fn sra_a<M: Memory>(state: &mut GameBoy<M>) {
-    state.a = sra_byte(state.a, state);
-}
-
-/// Shift register B right arithmetic
-fn sra_b<M: Memory>(state: &mut GameBoy<M>) {
-    state.b = sra_byte(state.b, state);
-}
-
-/// Shift register C right arithmetic
-fn sra_c<M: Memory>(state: &mut GameBoy<M>) {
-    state.c = sra_byte(state.c, state);
-}
-
-/// Shift register D right arithmetic
-fn sra_d<M: Memory>(state: &mut GameBoy<M>) {
-    state.d = sra_byte(state.d, state);
-}
-
-/// Shift register E right arithmetic
-fn sra_e<M: Memory>(state: &mut GameBoy<M>) {
-    state.e = sra_byte(state.e, state);
-}
-
-/// Shift register H right arithmetic
-fn sra_h<M: Memory>(state: &mut GameBoy<M>) {
-    state.h = sra_byte(state.h, state);
-}
-
-/// Shift register L right arithmetic
-fn sra_l<M: Memory>(state: &mut GameBoy<M>) {
-    state.l = sra_byte(state.l, state);
-}
-
-/// Shift value at (HL) right arithmetic
-fn sra_hl_indirect<M: Memory>(state: &mut GameBoy<M>) {
-    let addr = state.hl();
-    let value = state.read(addr);
-    let result = sra_byte(value, state);
-    state.write(addr, result);
-}
-
-/// SWAP - Swap upper and lower nibbles
-/// Exchanges the upper 4 bits with the lower 4 bits
-fn swap_byte<M: Memory>(value: u8, state: &mut GameBoy<M>) -> u8 {
-    let result = ((value & 0x0F) << 4) | ((value & 0xF0) >> 4);
+/// SWAP - Swap upper and lower nibbles
+/// Exchanges the upper 4 bits with the lower 4 bits
+fn swap_byte<M: Memory>(value: u8, state: &mut GameBoy<M>) -> u8 {
+    let result = ((value & 0x0F) << 4) | ((value & 0xF0) >> 4);
 
     state.set_flag_z(result == 0);
     state.set_flag_n(false);
@@ -1119,49 +799,6 @@ fn swap_byte<M: Memory>(value: u8, state: &mut GameBoy<M>) -> u8 {
     result
 }
 
-/// Swap register A nibbles
-fn swap_a<M: Memory>(state: &mut GameBoy<M>) {
-    state.a = swap_byte(state.a, state);
-}
-
-/// Swap register B nibbles
-fn swap_b<M: Memory>(state: &mut GameBoy<M>) {
-    state.b = swap_byte(state.b, state);
-}
-
-/// Swap register C nibbles
-fn swap_c<M: Memory>(state: &mut GameBoy<M>) {
-    state.c = swap_byte(state.c, state);
-}
-
-/// Swap register D nibbles
-fn swap_d<M: Memory>(state: &mut GameBoy<M>) {
-    state.d = swap_byte(state.d, state);
-}
-
-/// Swap register E nibbles
-fn swap_e<M: Memory>(state: &mut GameBoy<M>) {
-    state.e = swap_byte(state.e, state);
-}
-
-/// Swap register H nibbles
-fn swap_h<M: Memory>(state: &mut GameBoy<M>) {
-    state.h = swap_byte(state.h, state);
-}
-
-/// Swap register L nibbles
-fn swap_l<M: Memory>(state: &mut GameBoy<M>) {
-    state.l = swap_byte(state.l, state);
-}
-
-/// Swap value at (HL) nibbles
-fn swap_hl_indirect<M: Memory>(state: &mut GameBoy<M>) {
-    let addr = state.hl();
-    let value = state.read(addr);
-    let result = swap_byte(value, state);
-    state.write(addr, result);
-}
-
 /// SRL - Shift Right Logical
 /// Shifts value right, bit 0 goes to carry, bit 7 becomes 0
 fn srl_byte<M: Memory>(value: u8, state: &mut GameBoy<M>) -> u8 {
@@ -1176,49 +813,6 @@ fn srl_byte<M: Memory>(value: u8, state: &mut GameBoy<M>) -> u8 {
     result
 }
 
-/// Shift register A right logical
-fn srl_a<M: Memory>(state: &mut GameBoy<M>) {
-    state.a = srl_byte(state.a, state);
-}
-
-/// Shift register B right logical
-fn srl_b<M: Memory>(state: &mut GameBoy<M>) {
-    state.b = srl_byte(state.b, state);
-}
-
-/// Shift register C right logical
-fn srl_c<M: Memory>(state: &mut GameBoy<M>) {
-    state.c = srl_byte(state.c, state);
-}
-
-/// Shift register D right logical
-fn srl_d<M: Memory>(state: &mut GameBoy<M>) {
-    state.d = srl_byte(state.d, state);
-}
-
-/// Shift register E right logical
-fn srl_e<M: Memory>(state: &mut GameBoy<M>) {
-    state.e = srl_byte(state.e, state);
-}
-
-/// Shift register H right logical
-fn srl_h<M: Memory>(state: &mut GameBoy<M>) {
-    state.h = srl_byte(state.h, state);
-}
-
-/// Shift register L right logical
-fn srl_l<M: Memory>(state: &mut GameBoy<M>) {
-    state.l = srl_byte(state.l, state);
-}
-
-/// Shift value at (HL) right logical
-fn srl_hl_indirect<M: Memory>(state: &mut GameBoy<M>) {
-    let addr = state.hl();
-    let value = state.read(addr);
-    let result = srl_byte(value, state);
-    state.write(addr, result);
-}
-
 /// BIT - Test bit in value
 /// Tests if a specific bit is set, sets Z flag if bit is 0
 fn bit_test<M: Memory>(value: u8, bit: u8, state: &mut GameBoy<M>) {
@@ -1249,16 +843,16 @@ fn jr<M: Memory>(state: &mut GameBoy<M>) {
     state.pc = state.pc.wrapping_add(offset as u16);
 }
 
-/// JR NZ - Jump relative if not zero (Z flag is not set)
-fn jr_nz<M: Memory>(state: &mut GameBoy<M>) -> bool {
+/// JR cc - Jump relative if the condition holds
+///
+/// Returns true if the branch was taken.
+fn jr_cond<M: Memory>(cond: Cond, state: &mut GameBoy<M>) -> bool {
     let offset = read_immediate_byte(state) as i8;
-
-    if !state.flag_z() {
+    let taken = condition(cond, state);
+    if taken {
         state.pc = state.pc.wrapping_add(offset as u16);
-        true
-    } else {
-        false
     }
+    taken
 }
 
 /// DAA - Decimal Adjust Accumulator
@@ -1289,42 +883,6 @@ fn daa<M: Memory>(state: &mut GameBoy<M>) {
     state.set_flag_h(false);
 }
 
-/// JR Z - Jump relative if zero (Z flag is set)
-fn jr_z<M: Memory>(state: &mut GameBoy<M>) -> bool {
-    let offset = read_immediate_byte(state) as i8;
-
-    if state.flag_z() {
-        state.pc = state.pc.wrapping_add(offset as u16);
-        true
-    } else {
-        false
-    }
-}
-
-/// JR NC - Jump relative if not carry (C flag is not set)
-fn jr_nc<M: Memory>(state: &mut GameBoy<M>) -> bool {
-    let offset = read_immediate_byte(state) as i8;
-
-    if !state.flag_c() {
-        state.pc = state.pc.wrapping_add(offset as u16);
-        true
-    } else {
-        false
-    }
-}
-
-/// JR C - Jump relative if carry (C flag is set)
-fn jr_c<M: Memory>(state: &mut GameBoy<M>) -> bool {
-    let offset = read_immediate_byte(state) as i8;
-
-    if state.flag_c() {
-        state.pc = state.pc.wrapping_add(offset as u16);
-        true
-    } else {
-        false
-    }
-}
-
 /// CPL - Complement accumulator (flip all bits)
 fn cpl<M: Memory>(state: &mut GameBoy<M>) {
     state.a = !state.a;
@@ -1346,22 +904,6 @@ fn ccf<M: Memory>(state: &mut GameBoy<M>) {
     state.set_flag_h(false);
 }
 
-/// INC (HL) - Increment value at memory location pointed to by HL
-fn inc_hl_indirect<M: Memory>(state: &mut GameBoy<M>) {
-    let addr = state.hl();
-    let value = state.read(addr);
-    let result = inc_byte(value, state);
-    state.write(addr, result);
-}
-
-/// DEC (HL) - Decrement value at memory location pointed to by HL
-fn dec_hl_indirect<M: Memory>(state: &mut GameBoy<M>) {
-    let addr = state.hl();
-    let value = state.read(addr);
-    let result = dec_byte(value, state);
-    state.write(addr, result);
-}
-
 /// Add 16-bit value to HL and update flags
 /// N flag is reset, H flag is set on carry from bit 11, C flag is set on carry from bit 15
 /// Z flag is not affected
@@ -1378,80 +920,236 @@ fn add_hl<M: Memory>(value: u16, state: &mut GameBoy<M>) {
     state.set_hl(result);
 }
 
-/// ADD HL,BC - Add BC to HL
-fn add_hl_bc<M: Memory>(state: &mut GameBoy<M>) {
-    let bc = state.bc();
-    add_hl(bc, state);
+/// ADD HL,r16 - Add a register pair to HL
+fn add_hl_r16<M: Memory>(r16: R16, state: &mut GameBoy<M>) {
+    let value = read_r16(r16, state);
+    add_hl(value, state);
 }
 
-/// ADD HL,DE - Add DE to HL
-fn add_hl_de<M: Memory>(state: &mut GameBoy<M>) {
-    let de = state.de();
-    add_hl(de, state);
+/// Internal M-cycle of a 16-bit increment/decrement
+///
+/// The old value is put on the address bus, which corrupts OAM when it
+/// points into it during OAM search.
+fn idu_cycle<M: Memory>(state: &mut GameBoy<M>, value: u16) {
+    state.tick();
+    state.trigger_oam_bug(value, OamCorruption::Write);
 }
 
-/// ADD HL,HL - Add HL to HL (double HL)
-fn add_hl_hl<M: Memory>(state: &mut GameBoy<M>) {
-    let hl = state.hl();
-    add_hl(hl, state);
+/// INC r16 - Increment a register pair by 1
+fn inc_r16<M: Memory>(r16: R16, state: &mut GameBoy<M>) {
+    let value = read_r16(r16, state);
+    idu_cycle(state, value);
+    write_r16(r16, value.wrapping_add(1), state);
+}
+
+/// DEC r16 - Decrement a register pair by 1
+fn dec_r16<M: Memory>(r16: R16, state: &mut GameBoy<M>) {
+    let value = read_r16(r16, state);
+    idu_cycle(state, value);
+    write_r16(r16, value.wrapping_sub(1), state);
 }
 
-/// ADD HL,SP - Add SP to HL
-fn add_hl_sp<M: Memory>(state: &mut GameBoy<M>) {
-    let sp = state.sp();
-    add_hl(sp, state);
+/// LD (r16),A - Store A at the address in BC, DE or HL (HL+ and HL-)
+fn ld_mem_a<M: Memory>(mem: R16Mem, state: &mut GameBoy<M>) {
+    let address = match mem {
+        R16Mem::BC => state.bc(),
+        R16Mem::DE => state.de(),
+        R16Mem::HlIncrement | R16Mem::HlDecrement => state.hl(),
+    };
+    state.cpu_write(address, state.a);
+    step_hl(mem, state);
 }
 
-/// Increment the BC register pair by 1
-fn inc_bc<M: Memory>(state: &mut GameBoy<M>) {
-    let value = state.bc().wrapping_add(1);
-    state.set_bc(value);
+/// LD A,(r16) - Load A from the address in BC, DE or HL (HL+ and HL-)
+fn ld_a_mem<M: Memory>(mem: R16Mem, state: &mut GameBoy<M>) {
+    state.a = match mem {
+        R16Mem::BC => state.cpu_read(state.bc()),
+        R16Mem::DE => state.cpu_read(state.de()),
+        // HL is incremented/decremented during the read
+        R16Mem::HlIncrement | R16Mem::HlDecrement => state.cpu_read_idu(state.hl()),
+    };
+    step_hl(mem, state);
 }
 
-/// Increment the DE register pair by 1
-fn inc_de<M: Memory>(state: &mut GameBoy<M>) {
-    let value = state.de().wrapping_add(1);
-    state.set_de(value);
+/// Increment or decrement HL after an (HL+) or (HL-) access
+fn step_hl<M: Memory>(mem: R16Mem, state: &mut GameBoy<M>) {
+    match mem {
+        R16Mem::HlIncrement => state.set_hl(state.hl().wrapping_add(1)),
+        R16Mem::HlDecrement => state.set_hl(state.hl().wrapping_sub(1)),
+        R16Mem::BC | R16Mem::DE => {}
+    }
 }
 
-/// Increment the HL register pair by 1
-fn inc_hl<M: Memory>(state: &mut GameBoy<M>) {
-    let value = state.hl().wrapping_add(1);
-    state.set_hl(value);
+/// Execute a decoded instruction and add the cycles it took
+///
+/// The opcode (and CB prefix) were already read.
+#[inline(always)]
+fn run<M: Memory>(instruction: Instruction, state: &mut GameBoy<M>) {
+    use Instruction::*;
+
+    let mut taken = false;
+    match instruction {
+        Nop => {}
+        Stop => stop(state),
+        Halt => halt(state),
+        Di => state.di_delay = true,
+        Ei => state.ei_delay = true,
+        LdR16Imm(r16) => {
+            let value = read_immediate_word(state);
+            write_r16(r16, value, state);
+        }
+        LdMemA(mem) => ld_mem_a(mem, state),
+        LdAMem(mem) => ld_a_mem(mem, state),
+        LdImm16Sp => {
+            let address = read_immediate_word(state);
+            state.cpu_write_word(address, state.sp);
+        }
+        IncR16(r16) => inc_r16(r16, state),
+        DecR16(r16) => dec_r16(r16, state),
+        AddHl(r16) => add_hl_r16(r16, state),
+        IncR8(r8) => inc_r8(r8, state),
+        DecR8(r8) => dec_r8(r8, state),
+        LdR8Imm(r8) => {
+            let value = read_immediate_byte(state);
+            write_r8(r8, value, state);
+        }
+        Rlca => rlca(state),
+        Rrca => rrca(state),
+        Rla => rla(state),
+        Rra => rra(state),
+        Daa => daa(state),
+        Cpl => cpl(state),
+        Scf => scf(state),
+        Ccf => ccf(state),
+        Jr => jr(state),
+        JrCond(cond) => taken = jr_cond(cond, state),
+        Ld(dest, src) => {
+            let value = read_r8(src, state);
+            write_r8(dest, value, state);
+        }
+        Alu(op, r8) => {
+            let value = read_r8(r8, state);
+            alu(op, value, state);
+        }
+        AluImm(op) => {
+            let value = read_immediate_byte(state);
+            alu(op, value, state);
+        }
+        Ret => ret(state),
+        RetCond(cond) => taken = ret_cond(cond, state),
+        Reti => reti(state),
+        Jp => jp(state),
+        JpCond(cond) => taken = jp_cond(cond, state),
+        JpHl => jp_hl(state),
+        Call => call(state),
+        CallCond(cond) => taken = call_cond(cond, state),
+        Rst(vector) => rst(vector, state),
+        Pop(r16) => pop_r16(r16, state),
+        Push(r16) => push_r16(r16, state),
+        LdhCA => ldh_c_a(state),
+        LdhImmA => ldh_n_a(state),
+        LdImm16A => ld_nn_a(state),
+        LdhAC => ldh_a_c(state),
+        LdhAImm => ldh_a_n(state),
+        LdAImm16 => ld_a_nn(state),
+        AddSpImm => add_sp_n(state),
+        LdHlSpImm => ld_hl_sp_n(state),
+        LdSpHl => state.sp = state.hl(),
+        Prefix => {
+            let cb_op = read_immediate_byte(state);
+            let handler = &Dispatch::<M>::CB_OPCODES[cb_op as usize];
+            handler(state);
+        }
+        Shift(op, r8) => shift_r8(op, r8, state),
+        Bit(bit, r8) => {
+            let value = read_r8(r8, state);
+            bit_test(value, bit, state);
+        }
+        Res(bit, r8) => {
+            let value = read_r8(r8, state);
+            write_r8(r8, res_bit(value, bit), state);
+        }
+        Set(bit, r8) => {
+            let value = read_r8(r8, state);
+            write_r8(r8, set_bit(value, bit), state);
+        }
+        Illegal(opcode) => illegal_opcode(opcode, state),
+    }
+
+    state.cycles += instruction.cycles();
+    if taken {
+        state.cycles += instruction.branch_cycles();
+    }
 }
 
-/// Increment the SP register by 1
-fn inc_sp<M: Memory>(state: &mut GameBoy<M>) {
-    let value = state.sp().wrapping_add(1);
-    state.set_sp(value);
+/// Execute one unprefixed opcode, decoded at compile time
+fn opcode_handler<M: Memory, const OPCODE: u8>(state: &mut GameBoy<M>) {
+    run(const { OPCODES[OPCODE as usize] }, state);
 }
 
-/// Decrement the BC register pair by 1
-fn dec_bc<M: Memory>(state: &mut GameBoy<M>) {
-    let value = state.bc().wrapping_sub(1);
-    state.set_bc(value);
+/// Execute one CB-prefixed opcode, decoded at compile time
+fn cb_opcode_handler<M: Memory, const OPCODE: u8>(state: &mut GameBoy<M>) {
+    run(const { CB_OPCODES[OPCODE as usize] }, state);
 }
 
-/// Decrement the DE register pair by 1
-fn dec_de<M: Memory>(state: &mut GameBoy<M>) {
-    let value = state.de().wrapping_sub(1);
-    state.set_de(value);
+type Handler<M> = fn(&mut GameBoy<M>);
+
+/// Table of 16 rows of 16 handlers, one per opcode
+macro_rules! handler_rows {
+    ($handler:ident) => {
+        handler_rows!(@rows $handler 0 1 2 3 4 5 6 7 8 9 10 11 12 13 14 15)
+    };
+    (@rows $handler:ident $($high:literal)*) => {
+        [$(handler_rows!(@row $handler $high)),*]
+    };
+    (@row $handler:ident $high:literal) => {
+        [
+            $handler::<M, { $high * 16 }>,
+            $handler::<M, { $high * 16 + 1 }>,
+            $handler::<M, { $high * 16 + 2 }>,
+            $handler::<M, { $high * 16 + 3 }>,
+            $handler::<M, { $high * 16 + 4 }>,
+            $handler::<M, { $high * 16 + 5 }>,
+            $handler::<M, { $high * 16 + 6 }>,
+            $handler::<M, { $high * 16 + 7 }>,
+            $handler::<M, { $high * 16 + 8 }>,
+            $handler::<M, { $high * 16 + 9 }>,
+            $handler::<M, { $high * 16 + 10 }>,
+            $handler::<M, { $high * 16 + 11 }>,
+            $handler::<M, { $high * 16 + 12 }>,
+            $handler::<M, { $high * 16 + 13 }>,
+            $handler::<M, { $high * 16 + 14 }>,
+            $handler::<M, { $high * 16 + 15 }>,
+        ]
+    };
 }
 
-/// Decrement the HL register pair by 1
-fn dec_hl<M: Memory>(state: &mut GameBoy<M>) {
-    let value = state.hl().wrapping_sub(1);
-    state.set_hl(value);
+/// Opcode dispatch tables, built at compile time for each memory type
+struct Dispatch<M>(PhantomData<M>);
+
+impl<M: Memory> Dispatch<M> {
+    const OPCODES: [Handler<M>; 256] = flatten(handler_rows!(opcode_handler));
+    const CB_OPCODES: [Handler<M>; 256] = flatten(handler_rows!(cb_opcode_handler));
 }
 
-/// Decrement the SP register by 1
-fn dec_sp<M: Memory>(state: &mut GameBoy<M>) {
-    let value = state.sp().wrapping_sub(1);
-    state.set_sp(value);
+const fn flatten<M: Memory>(rows: [[Handler<M>; 16]; 16]) -> [Handler<M>; 256] {
+    let mut table = [rows[0][0]; 256];
+    let mut opcode = 0;
+    while opcode < 256 {
+        table[opcode] = rows[opcode / 16][opcode % 16];
+        opcode += 1;
+    }
+    table
 }
 
 /// Execute a single CPU instruction.
 pub fn execute<M: Memory>(state: &mut GameBoy<M>) {
+    // Handle STOP mode: nothing runs until a button is pressed
+    if !handle_stop(state) {
+        state.tick_stopped();
+        return;
+    }
+
     // Service any pending interrupts
     if service_interrupts(state) {
         // Interrupt was serviced, return early (PC now points to interrupt handler)
@@ -1473,7 +1171,7 @@ pub fn execute<M: Memory>(state: &mut GameBoy<M>) {
     let op = if state.halt_bug {
         // HALT bug: Read the byte without incrementing PC
         // This causes the byte after HALT to be read twice
-        let value = state.read(state.pc);
+        let value = state.cpu_read(state.pc);
         state.halt_bug = false; // Clear the flag after first read
         value
     } else {
@@ -1482,1730 +1180,8 @@ pub fn execute<M: Memory>(state: &mut GameBoy<M>) {
     };
     state.last_opcode = op; // Store for delayed interrupt handling
 
-    match op {
-        0x00 => {
-            /* NOP */
-            state.cycles += 4;
-        }
-        0x01 => {
-            /* LD BC,n */
-            state.set_bc(state.read_word(state.pc));
-            state.pc += 2;
-            state.cycles += 12;
-        }
-        0x02 => {
-            /* LD (BC),A */
-            state.write(state.bc(), state.a);
-            state.cycles += 8;
-        }
-        0x03 => {
-            /* INC BC */
-            inc_bc(state);
-            state.cycles += 8;
-        }
-        0x04 => {
-            /* INC B */
-            inc_b(state);
-            state.cycles += 4;
-        }
-        0x05 => {
-            /* DEC B */
-            dec_b(state);
-            state.cycles += 4;
-        }
-        0x06 => {
-            /* LD B,n */
-            state.b = read_immediate_byte(state);
-            state.cycles += 8;
-        }
-        0x07 => {
-            /* RLCA */
-            rlca(state);
-            state.cycles += 4;
-        }
-        0x08 => {
-            /* LD (nn),SP */
-            let address = read_immediate_word(state);
-            state.write_word(address, state.sp);
-            state.cycles += 20;
-        }
-        0x09 => {
-            /* ADD HL,BC */
-            add_hl_bc(state);
-            state.cycles += 8;
-        }
-        0x0A => {
-            /* LD A,(BC) */
-            state.a = state.read(state.bc());
-            state.cycles += 8;
-        }
-        0x0B => {
-            /* DEC BC */
-            dec_bc(state);
-            state.cycles += 8;
-        }
-        0x0C => {
-            /* INC C */
-            inc_c(state);
-            state.cycles += 4;
-        }
-        0x0D => {
-            /* DEC C */
-            dec_c(state);
-            state.cycles += 4;
-        }
-        0x0E => {
-            /* LD C,n */
-            state.c = read_immediate_byte(state);
-            state.cycles += 8;
-        }
-        0x0F => {
-            /* RRCA */
-            rrca(state);
-            state.cycles += 4;
-        }
-        0x10 => {
-            /* STOP */
-            state.pc += 1;
-            state.cycles += 4;
-        }
-        0x11 => {
-            /* LD DE,n */
-            state.set_de(state.read_word(state.pc));
-            state.pc += 2;
-            state.cycles += 12;
-        }
-        0x12 => {
-            /* LD (DE),A */
-            state.write(state.de(), state.a);
-            state.cycles += 8;
-        }
-        0x13 => {
-            /* INC DE */
-            inc_de(state);
-            state.cycles += 8;
-        }
-        0x14 => {
-            /* INC D */
-            inc_d(state);
-            state.cycles += 4;
-        }
-        0x15 => {
-            /* DEC D */
-            dec_d(state);
-            state.cycles += 4;
-        }
-        0x16 => {
-            /* LD D,n */
-            state.d = read_immediate_byte(state);
-            state.cycles += 8;
-        }
-        0x17 => {
-            /* RLA */
-            rla(state);
-            state.cycles += 4;
-        }
-        0x18 => {
-            /* JR */
-            jr(state);
-            state.cycles += 12;
-        }
-        0x19 => {
-            /* ADD HL,DE */
-            add_hl_de(state);
-            state.cycles += 8;
-        }
-        0x1A => {
-            /* LD A,(DE) */
-            state.a = state.read(state.de());
-            state.cycles += 8;
-        }
-        0x1B => {
-            /* DEC DE */
-            dec_de(state);
-            state.cycles += 8;
-        }
-        0x1C => {
-            /* INC E */
-            inc_e(state);
-            state.cycles += 4;
-        }
-        0x1D => {
-            /* DEC E */
-            dec_e(state);
-            state.cycles += 4;
-        }
-        0x1E => {
-            /* LD E,n */
-            state.e = read_immediate_byte(state);
-            state.cycles += 8;
-        }
-        0x1F => {
-            /* RRA */
-            rra(state);
-            state.cycles += 4;
-        }
-        0x20 => {
-            /* JR NZ */
-            if jr_nz(state) {
-                state.cycles += 12;
-            } else {
-                state.cycles += 8;
-            }
-        }
-        0x21 => {
-            /* LD HL,n */
-            state.set_hl(state.read_word(state.pc));
-            state.pc += 2;
-            state.cycles += 12;
-        }
-        0x22 => {
-            /* LDI (HL),A */
-            state.write(state.hl(), state.a);
-            state.set_hl(state.hl().wrapping_add(1));
-            state.cycles += 8;
-        }
-        0x23 => {
-            /* INC HL */
-            inc_hl(state);
-            state.cycles += 8;
-        }
-        0x24 => {
-            /* INC H */
-            inc_h(state);
-            state.cycles += 4;
-        }
-        0x25 => {
-            /* DEC H */
-            dec_h(state);
-            state.cycles += 4;
-        }
-        0x26 => {
-            /* LD H,n */
-            state.h = read_immediate_byte(state);
-            state.cycles += 8;
-        }
-        0x27 => {
-            /* DAA */
-            daa(state);
-            state.cycles += 4;
-        }
-        0x28 => {
-            /* JR Z */
-            if jr_z(state) {
-                state.cycles += 12;
-            } else {
-                state.cycles += 8;
-            }
-        }
-        0x29 => {
-            /* ADD HL,HL */
-            add_hl_hl(state);
-            state.cycles += 8;
-        }
-        0x2A => {
-            /* LDI A,(HL) */
-            state.a = state.read(state.hl());
-            state.set_hl(state.hl().wrapping_add(1));
-            state.cycles += 8;
-        }
-        0x2B => {
-            /* DEC HL */
-            dec_hl(state);
-            state.cycles += 8;
-        }
-        0x2C => {
-            /* INC L */
-            inc_l(state);
-            state.cycles += 4;
-        }
-        0x2D => {
-            /* DEC L */
-            dec_l(state);
-            state.cycles += 4;
-        }
-        0x2E => {
-            /* LD L,n */
-            state.l = read_immediate_byte(state);
-            state.cycles += 8;
-        }
-        0x2F => {
-            /* CPL */
-            cpl(state);
-            state.cycles += 4;
-        }
-        0x30 => {
-            /* JR NC */
-            if jr_nc(state) {
-                state.cycles += 12;
-            } else {
-                state.cycles += 8;
-            }
-        }
-        0x31 => {
-            /* LD SP,n */
-            state.set_sp(state.read_word(state.pc));
-            state.pc += 2;
-            state.cycles += 12;
-        }
-        0x32 => {
-            /* LDD (HL),A */
-            state.write(state.hl(), state.a);
-            state.set_hl(state.hl().wrapping_sub(1));
-            state.cycles += 8;
-        }
-        0x33 => {
-            /* INC SP */
-            inc_sp(state);
-            state.cycles += 8;
-        }
-        0x34 => {
-            /* INC (HL) */
-            inc_hl_indirect(state);
-            state.cycles += 12;
-        }
-        0x35 => {
-            /* DEC (HL) */
-            dec_hl_indirect(state);
-            state.cycles += 12;
-        }
-        0x36 => {
-            /* LD (HL),n */
-            let value = read_immediate_byte(state);
-            state.write(state.hl(), value);
-            state.cycles += 12;
-        }
-        0x37 => {
-            /* SCF */
-            scf(state);
-            state.cycles += 4;
-        }
-        0x38 => {
-            /* JR C */
-            if jr_c(state) {
-                state.cycles += 12;
-            } else {
-                state.cycles += 8;
-            }
-        }
-        0x39 => {
-            /* ADD HL,SP */
-            add_hl_sp(state);
-            state.cycles += 8;
-        }
-        0x3A => {
-            /* LDD A,(HL) */
-            state.a = state.read(state.hl());
-            state.set_hl(state.hl().wrapping_sub(1));
-            state.cycles += 8;
-        }
-        0x3B => {
-            /* DEC SP */
-            dec_sp(state);
-            state.cycles += 8;
-        }
-        0x3C => {
-            /* INC A */
-            inc_a(state);
-            state.cycles += 4;
-        }
-        0x3D => {
-            /* DEC A */
-            dec_a(state);
-            state.cycles += 4;
-        }
-        0x3E => {
-            /* LD A,n */
-            state.a = read_immediate_byte(state);
-            state.cycles += 8;
-        }
-        0x3F => {
-            /* CCF */
-            ccf(state);
-            state.cycles += 4;
-        }
-        0x40 => {
-            /* LD B,B */
-            state.cycles += 4;
-        }
-        0x41 => {
-            /* LD B,C */
-            state.b = state.c;
-            state.cycles += 4;
-        }
-        0x42 => {
-            /* LD B,D */
-            state.b = state.d;
-            state.cycles += 4;
-        }
-        0x43 => {
-            /* LD B,E */
-            state.b = state.e;
-            state.cycles += 4;
-        }
-        0x44 => {
-            /* LD B,H */
-            state.b = state.h;
-            state.cycles += 4;
-        }
-        0x45 => {
-            /* LD B,L */
-            state.b = state.l;
-            state.cycles += 4;
-        }
-        0x46 => {
-            /* LD B,(HL) */
-            state.b = state.read(state.hl());
-            state.cycles += 8;
-        }
-        0x47 => {
-            /* LD B,A */
-            state.b = state.a;
-            state.cycles += 4;
-        }
-        0x48 => {
-            /* LD C,B */
-            state.c = state.b;
-            state.cycles += 4;
-        }
-        0x49 => {
-            /* LD C,C */
-            state.cycles += 4;
-        }
-        0x4A => {
-            /* LD C,D */
-            state.c = state.d;
-            state.cycles += 4;
-        }
-        0x4B => {
-            /* LD C,E */
-            state.c = state.e;
-            state.cycles += 4;
-        }
-        0x4C => {
-            /* LD C,H */
-            state.c = state.h;
-            state.cycles += 4;
-        }
-        0x4D => {
-            /* LD C,L */
-            state.c = state.l;
-            state.cycles += 4;
-        }
-        0x4E => {
-            /* LD C,(HL) */
-            state.c = state.read(state.hl());
-            state.cycles += 8;
-        }
-        0x4F => {
-            /* LD C,A */
-            state.c = state.a;
-            state.cycles += 4;
-        }
-        0x50 => {
-            /* LD D,B */
-            state.d = state.b;
-            state.cycles += 4;
-        }
-        0x51 => {
-            /* LD D,C */
-            state.d = state.c;
-            state.cycles += 4;
-        }
-        0x52 => {
-            /* LD D,D */
-            state.cycles += 4;
-        }
-        0x53 => {
-            /* LD D,E */
-            state.d = state.e;
-            state.cycles += 4;
-        }
-        0x54 => {
-            /* LD D,H */
-            state.d = state.h;
-            state.cycles += 4;
-        }
-        0x55 => {
-            /* LD D,L */
-            state.d = state.l;
-            state.cycles += 4;
-        }
-        0x56 => {
-            /* LD D,(HL) */
-            state.d = state.read(state.hl());
-            state.cycles += 8;
-        }
-        0x57 => {
-            /* LD D,A */
-            state.d = state.a;
-            state.cycles += 4;
-        }
-        0x58 => {
-            /* LD E,B */
-            state.e = state.b;
-            state.cycles += 4;
-        }
-        0x59 => {
-            /* LD E,C */
-            state.e = state.c;
-            state.cycles += 4;
-        }
-        0x5A => {
-            /* LD E,D */
-            state.e = state.d;
-            state.cycles += 4;
-        }
-        0x5B => {
-            /* LD E,E */
-            state.cycles += 4;
-        }
-        0x5C => {
-            /* LD E,H */
-            state.e = state.h;
-            state.cycles += 4;
-        }
-        0x5D => {
-            /* LD E,L */
-            state.e = state.l;
-            state.cycles += 4;
-        }
-        0x5E => {
-            /* LD E,(HL) */
-            state.e = state.read(state.hl());
-            state.cycles += 8;
-        }
-        0x5F => {
-            /* LD E,A */
-            state.e = state.a;
-            state.cycles += 4;
-        }
-        0x60 => {
-            /* LD H,B */
-            state.h = state.b;
-            state.cycles += 4;
-        }
-        0x61 => {
-            /* LD H,C */
-            state.h = state.c;
-            state.cycles += 4;
-        }
-        0x62 => {
-            /* LD H,D */
-            state.h = state.d;
-            state.cycles += 4;
-        }
-        0x63 => {
-            /* LD H,E */
-            state.h = state.e;
-            state.cycles += 4;
-        }
-        0x64 => {
-            /* LD H,H */
-            state.cycles += 4;
-        }
-        0x65 => {
-            /* LD H,L */
-            state.h = state.l;
-            state.cycles += 4;
-        }
-        0x66 => {
-            /* LD H,(HL) */
-            state.h = state.read(state.hl());
-            state.cycles += 8;
-        }
-        0x67 => {
-            /* LD H,A */
-            state.h = state.a;
-            state.cycles += 4;
-        }
-        0x68 => {
-            /* LD L,B */
-            state.l = state.b;
-            state.cycles += 4;
-        }
-        0x69 => {
-            /* LD L,C */
-            state.l = state.c;
-            state.cycles += 4;
-        }
-        0x6A => {
-            /* LD L,D */
-            state.l = state.d;
-            state.cycles += 4;
-        }
-        0x6B => {
-            /* LD L,E */
-            state.l = state.e;
-            state.cycles += 4;
-        }
-        0x6C => {
-            /* LD L,H */
-            state.l = state.h;
-            state.cycles += 4;
-        }
-        0x6D => {
-            /* LD L,L */
-            state.cycles += 4;
-        }
-        0x6E => {
-            /* LD L,(HL) */
-            state.l = state.read(state.hl());
-            state.cycles += 8;
-        }
-        0x6F => {
-            /* LD L,A */
-            state.l = state.a;
-            state.cycles += 4;
-        }
-        0x70 => {
-            /* LD (HL),B */
-            state.write(state.hl(), state.b);
-            state.cycles += 8;
-        }
-        0x71 => {
-            /* LD (HL),C */
-            state.write(state.hl(), state.c);
-            state.cycles += 8;
-        }
-        0x72 => {
-            /* LD (HL),D */
-            state.write(state.hl(), state.d);
-            state.cycles += 8;
-        }
-        0x73 => {
-            /* LD (HL),E */
-            state.write(state.hl(), state.e);
-            state.cycles += 8;
-        }
-        0x74 => {
-            /* LD (HL),H */
-            state.write(state.hl(), state.h);
-            state.cycles += 8;
-        }
-        0x75 => {
-            /* LD (HL),L */
-            state.write(state.hl(), state.l);
-            state.cycles += 8;
-        }
-        0x76 => {
-            /* HALT */
-            halt(state);
-            state.cycles += 4;
-        }
-        0x77 => {
-            /* LD (HL),A */
-            state.write(state.hl(), state.a);
-            state.cycles += 8;
-        }
-        0x78 => {
-            /* LD A,B */
-            state.a = state.b;
-            state.cycles += 4;
-        }
-        0x79 => {
-            /* LD A,C */
-            state.a = state.c;
-            state.cycles += 4;
-        }
-        0x7A => {
-            /* LD A,D */
-            state.a = state.d;
-            state.cycles += 4;
-        }
-        0x7B => {
-            /* LD A,E */
-            state.a = state.e;
-            state.cycles += 4;
-        }
-        0x7C => {
-            /* LD A,H */
-            state.a = state.h;
-            state.cycles += 4;
-        }
-        0x7D => {
-            /* LD A,L */
-            state.a = state.l;
-            state.cycles += 4;
-        }
-        0x7E => {
-            /* LD A,(HL) */
-            state.a = state.read(state.hl());
-            state.cycles += 8;
-        }
-        0x7F => {
-            /* LD A,A */
-            // No-op, but still takes cycles
-            state.cycles += 4;
-        }
-        0x80 => {
-            /* ADD A,B */
-            add_a(state.b, state);
-            state.cycles += 4;
-        }
-        0x81 => {
-            /* ADD A,C */
-            add_a(state.c, state);
-            state.cycles += 4;
-        }
-        0x82 => {
-            /* ADD A,D */
-            add_a(state.d, state);
-            state.cycles += 4;
-        }
-        0x83 => {
-            /* ADD A,E */
-            add_a(state.e, state);
-            state.cycles += 4;
-        }
-        0x84 => {
-            /* ADD A,H */
-            add_a(state.h, state);
-            state.cycles += 4;
-        }
-        0x85 => {
-            /* ADD A,L */
-            add_a(state.l, state);
-            state.cycles += 4;
-        }
-        0x86 => {
-            /* ADD A,(HL) */
-            let value = state.read(state.hl());
-            add_a(value, state);
-            state.cycles += 8;
-        }
-        0x87 => {
-            /* ADD A,A */
-            add_a(state.a, state);
-            state.cycles += 4;
-        }
-        0x88 => {
-            /* ADC A,B */
-            adc_a(state.b, state);
-            state.cycles += 4;
-        }
-        0x89 => {
-            /* ADC A,C */
-            adc_a(state.c, state);
-            state.cycles += 4;
-        }
-        0x8A => {
-            /* ADC A,D */
-            adc_a(state.d, state);
-            state.cycles += 4;
-        }
-        0x8B => {
-            /* ADC A,E */
-            adc_a(state.e, state);
-            state.cycles += 4;
-        }
-        0x8C => {
-            /* ADC A,H */
-            adc_a(state.h, state);
-            state.cycles += 4;
-        }
-        0x8D => {
-            /* ADC A,L */
-            adc_a(state.l, state);
-            state.cycles += 4;
-        }
-        0x8E => {
-            /* ADC A,(HL) */
-            let value = state.read(state.hl());
-            adc_a(value, state);
-            state.cycles += 8;
-        }
-        0x8F => {
-            /* ADC A,A */
-            adc_a(state.a, state);
-            state.cycles += 4;
-        }
-        0x90 => {
-            /* SUB B */
-            sub_a(state.b, state);
-            state.cycles += 4;
-        }
-        0x91 => {
-            /* SUB C */
-            sub_a(state.c, state);
-            state.cycles += 4;
-        }
-        0x92 => {
-            /* SUB D */
-            sub_a(state.d, state);
-            state.cycles += 4;
-        }
-        0x93 => {
-            /* SUB E */
-            sub_a(state.e, state);
-            state.cycles += 4;
-        }
-        0x94 => {
-            /* SUB H */
-            sub_a(state.h, state);
-            state.cycles += 4;
-        }
-        0x95 => {
-            /* SUB L */
-            sub_a(state.l, state);
-            state.cycles += 4;
-        }
-        0x96 => {
-            /* SUB (HL) */
-            let value = state.read(state.hl());
-            sub_a(value, state);
-            state.cycles += 8;
-        }
-        0x97 => {
-            /* SUB A */
-            sub_a(state.a, state);
-            state.cycles += 4;
-        }
-        0x98 => {
-            /* SBC A,B */
-            sbc_a(state.b, state);
-            state.cycles += 4;
-        }
-        0x99 => {
-            /* SBC A,C */
-            sbc_a(state.c, state);
-            state.cycles += 4;
-        }
-        0x9A => {
-            /* SBC A,D */
-            sbc_a(state.d, state);
-            state.cycles += 4;
-        }
-        0x9B => {
-            /* SBC A,E */
-            sbc_a(state.e, state);
-            state.cycles += 4;
-        }
-        0x9C => {
-            /* SBC A,H */
-            sbc_a(state.h, state);
-            state.cycles += 4;
-        }
-        0x9D => {
-            /* SBC A,L */
-            sbc_a(state.l, state);
-            state.cycles += 4;
-        }
-        0x9E => {
-            /* SBC A,(HL) */
-            let value = state.read(state.hl());
-            sbc_a(value, state);
-            state.cycles += 8;
-        }
-        0x9F => {
-            /* SBC A,A */
-            sbc_a(state.a, state);
-            state.cycles += 4;
-        }
-        0xA0 => {
-            /* AND B */
-            and_a(state.b, state);
-            state.cycles += 4;
-        }
-        0xA1 => {
-            /* AND C */
-            and_a(state.c, state);
-            state.cycles += 4;
-        }
-        0xA2 => {
-            /* AND D */
-            and_a(state.d, state);
-            state.cycles += 4;
-        }
-        0xA3 => {
-            /* AND E */
-            and_a(state.e, state);
-            state.cycles += 4;
-        }
-        0xA4 => {
-            /* AND H */
-            and_a(state.h, state);
-            state.cycles += 4;
-        }
-        0xA5 => {
-            /* AND L */
-            and_a(state.l, state);
-            state.cycles += 4;
-        }
-        0xA6 => {
-            /* AND (HL) */
-            let value = state.read(state.hl());
-            and_a(value, state);
-            state.cycles += 8;
-        }
-        0xA7 => {
-            /* AND A */
-            and_a(state.a, state);
-            state.cycles += 4;
-        }
-        0xA8 => {
-            /* XOR B */
-            xor_a(state.b, state);
-            state.cycles += 4;
-        }
-        0xA9 => {
-            /* XOR C */
-            xor_a(state.c, state);
-            state.cycles += 4;
-        }
-        0xAA => {
-            /* XOR D */
-            xor_a(state.d, state);
-            state.cycles += 4;
-        }
-        0xAB => {
-            /* XOR E */
-            xor_a(state.e, state);
-            state.cycles += 4;
-        }
-        0xAC => {
-            /* XOR H */
-            xor_a(state.h, state);
-            state.cycles += 4;
-        }
-        0xAD => {
-            /* XOR L */
-            xor_a(state.l, state);
-            state.cycles += 4;
-        }
-        0xAE => {
-            /* XOR (HL) */
-            let value = state.read(state.hl());
-            xor_a(value, state);
-            state.cycles += 8;
-        }
-        0xAF => {
-            /* XOR A */
-            xor_a(state.a, state);
-            state.cycles += 4;
-        }
-        0xB0 => {
-            /* OR B */
-            or_a(state.b, state);
-            state.cycles += 4;
-        }
-        0xB1 => {
-            /* OR C */
-            or_a(state.c, state);
-            state.cycles += 4;
-        }
-        0xB2 => {
-            /* OR D */
-            or_a(state.d, state);
-            state.cycles += 4;
-        }
-        0xB3 => {
-            /* OR E */
-            or_a(state.e, state);
-            state.cycles += 4;
-        }
-        0xB4 => {
-            /* OR H */
-            or_a(state.h, state);
-            state.cycles += 4;
-        }
-        0xB5 => {
-            /* OR L */
-            or_a(state.l, state);
-            state.cycles += 4;
-        }
-        0xB6 => {
-            /* OR (HL) */
-            let value = state.read(state.hl());
-            or_a(value, state);
-            state.cycles += 8;
-        }
-        0xB7 => {
-            /* OR A */
-            or_a(state.a, state);
-            state.cycles += 4;
-        }
-        0xB8 => {
-            /* CP B */
-            cp_a(state.b, state);
-            state.cycles += 4;
-        }
-        0xB9 => {
-            /* CP C */
-            cp_a(state.c, state);
-            state.cycles += 4;
-        }
-        0xBA => {
-            /* CP D */
-            cp_a(state.d, state);
-            state.cycles += 4;
-        }
-        0xBB => {
-            /* CP E */
-            cp_a(state.e, state);
-            state.cycles += 4;
-        }
-        0xBC => {
-            /* CP H */
-            cp_a(state.h, state);
-            state.cycles += 4;
-        }
-        0xBD => {
-            /* CP L */
-            cp_a(state.l, state);
-            state.cycles += 4;
-        }
-        0xBE => {
-            /* CP (HL) */
-            let value = state.read(state.hl());
-            cp_a(value, state);
-            state.cycles += 8;
-        }
-        0xBF => {
-            /* CP A */
-            cp_a(state.a, state);
-            state.cycles += 4;
-        }
-        0xC0 => {
-            /* RET NZ */
-            ret_nz(state);
-            // Conditional return: 8 cycles if not taken, 20 cycles if taken
-            state.cycles += if !state.flag_z() { 20 } else { 8 };
-        }
-        0xC1 => {
-            /* POP BC */
-            pop_bc(state);
-            state.cycles += 12;
-        }
-        0xC2 => {
-            /* JP NZ */
-            jp_nz(state);
-            // Conditional jump: 12 cycles if not taken, 16 cycles if taken
-            state.cycles += if !state.flag_z() { 16 } else { 12 };
-        }
-        0xC3 => {
-            /* JP */
-            jp(state);
-            state.cycles += 16;
-        }
-        0xC4 => {
-            /* CALL NZ */
-            call_nz(state);
-            // Conditional call: 12 cycles if not taken, 24 cycles if taken
-            state.cycles += if !state.flag_z() { 24 } else { 12 };
-        }
-        0xC5 => {
-            /* PUSH BC */
-            push_bc(state);
-            state.cycles += 16;
-        }
-        0xC6 => {
-            /* ADD A,n */
-            let value = read_immediate_byte(state);
-            add_a(value, state);
-            state.cycles += 8;
-        }
-        0xC7 => {
-            /* RST 00H */
-            rst_00(state);
-            state.cycles += 16;
-        }
-        0xC8 => {
-            /* RET Z */
-            ret_z(state);
-            // Conditional return: 8 cycles if not taken, 20 cycles if taken
-            state.cycles += if state.flag_z() { 20 } else { 8 };
-        }
-        0xC9 => {
-            /* RET */
-            ret(state);
-            state.cycles += 16;
-        }
-        0xCA => {
-            /* JP Z */
-            jp_z(state);
-            // Conditional jump: 12 cycles if not taken, 16 cycles if taken
-            state.cycles += if state.flag_z() { 16 } else { 12 };
-        }
-        0xCB => {
-            /* CB prefix - Extended instruction set */
-            let cb_op = read_immediate_byte(state);
-            match cb_op {
-                0x00 => {
-                    /* RLC B */
-                    rlc_b(state);
-                    state.cycles += 8;
-                }
-                0x01 => {
-                    /* RLC C */
-                    rlc_c(state);
-                    state.cycles += 8;
-                }
-                0x02 => {
-                    /* RLC D */
-                    rlc_d(state);
-                    state.cycles += 8;
-                }
-                0x03 => {
-                    /* RLC E */
-                    rlc_e(state);
-                    state.cycles += 8;
-                }
-                0x04 => {
-                    /* RLC H */
-                    rlc_h(state);
-                    state.cycles += 8;
-                }
-                0x05 => {
-                    /* RLC L */
-                    rlc_l(state);
-                    state.cycles += 8;
-                }
-                0x06 => {
-                    /* RLC (HL) */
-                    rlc_hl_indirect(state);
-                    state.cycles += 16;
-                }
-                0x07 => {
-                    /* RLC A */
-                    rlc_a(state);
-                    state.cycles += 8;
-                }
-                0x08 => {
-                    /* RRC B */
-                    rrc_b(state);
-                    state.cycles += 8;
-                }
-                0x09 => {
-                    /* RRC C */
-                    rrc_c(state);
-                    state.cycles += 8;
-                }
-                0x0A => {
-                    /* RRC D */
-                    rrc_d(state);
-                    state.cycles += 8;
-                }
-                0x0B => {
-                    /* RRC E */
-                    rrc_e(state);
-                    state.cycles += 8;
-                }
-                0x0C => {
-                    /* RRC H */
-                    rrc_h(state);
-                    state.cycles += 8;
-                }
-                0x0D => {
-                    /* RRC L */
-                    rrc_l(state);
-                    state.cycles += 8;
-                }
-                0x0E => {
-                    /* RRC (HL) */
-                    rrc_hl_indirect(state);
-                    state.cycles += 16;
-                }
-                0x0F => {
-                    /* RRC A */
-                    rrc_a(state);
-                    state.cycles += 8;
-                }
-                0x10 => {
-                    /* RL B */
-                    rl_b(state);
-                    state.cycles += 8;
-                }
-                0x11 => {
-                    /* RL C */
-                    rl_c(state);
-                    state.cycles += 8;
-                }
-                0x12 => {
-                    /* RL D */
-                    rl_d(state);
-                    state.cycles += 8;
-                }
-                0x13 => {
-                    /* RL E */
-                    rl_e(state);
-                    state.cycles += 8;
-                }
-                0x14 => {
-                    /* RL H */
-                    rl_h(state);
-                    state.cycles += 8;
-                }
-                0x15 => {
-                    /* RL L */
-                    rl_l(state);
-                    state.cycles += 8;
-                }
-                0x16 => {
-                    /* RL (HL) */
-                    rl_hl_indirect(state);
-                    state.cycles += 16;
-                }
-                0x17 => {
-                    /* RL A */
-                    rl_a(state);
-                    state.cycles += 8;
-                }
-                0x18 => {
-                    /* RR B */
-                    rr_b(state);
-                    state.cycles += 8;
-                }
-                0x19 => {
-                    /* RR C */
-                    rr_c(state);
-                    state.cycles += 8;
-                }
-                0x1A => {
-                    /* RR D */
-                    rr_d(state);
-                    state.cycles += 8;
-                }
-                0x1B => {
-                    /* RR E */
-                    rr_e(state);
-                    state.cycles += 8;
-                }
-                0x1C => {
-                    /* RR H */
-                    rr_h(state);
-                    state.cycles += 8;
-                }
-                0x1D => {
-                    /* RR L */
-                    rr_l(state);
-                    state.cycles += 8;
-                }
-                0x1E => {
-                    /* RR (HL) */
-                    rr_hl_indirect(state);
-                    state.cycles += 16;
-                }
-                0x1F => {
-                    /* RR A */
-                    rr_a(state);
-                    state.cycles += 8;
-                }
-                0x20 => {
-                    /* SLA B */
-                    sla_b(state);
-                    state.cycles += 8;
-                }
-                0x21 => {
-                    /* SLA C */
-                    sla_c(state);
-                    state.cycles += 8;
-                }
-                0x22 => {
-                    /* SLA D */
-                    sla_d(state);
-                    state.cycles += 8;
-                }
-                0x23 => {
-                    /* SLA E */
-                    sla_e(state);
-                    state.cycles += 8;
-                }
-                0x24 => {
-                    /* SLA H */
-                    sla_h(state);
-                    state.cycles += 8;
-                }
-                0x25 => {
-                    /* SLA L */
-                    sla_l(state);
-                    state.cycles += 8;
-                }
-                0x26 => {
-                    /* SLA (HL) */
-                    sla_hl_indirect(state);
-                    state.cycles += 16;
-                }
-                0x27 => {
-                    /* SLA A */
-                    sla_a(state);
-                    state.cycles += 8;
-                }
-                0x28 => {
-                    /* SRA B */
-                    sra_b(state);
-                    state.cycles += 8;
-                }
-                0x29 => {
-                    /* SRA C */
-                    sra_c(state);
-                    state.cycles += 8;
-                }
-                0x2A => {
-                    /* SRA D */
-                    sra_d(state);
-                    state.cycles += 8;
-                }
-                0x2B => {
-                    /* SRA E */
-                    sra_e(state);
-                    state.cycles += 8;
-                }
-                0x2C => {
-                    /* SRA H */
-                    sra_h(state);
-                    state.cycles += 8;
-                }
-                0x2D => {
-                    /* SRA L */
-                    sra_l(state);
-                    state.cycles += 8;
-                }
-                0x2E => {
-                    /* SRA (HL) */
-                    sra_hl_indirect(state);
-                    state.cycles += 16;
-                }
-                0x2F => {
-                    /* SRA A */
-                    sra_a(state);
-                    state.cycles += 8;
-                }
-                0x30 => {
-                    /* SWAP B */
-                    swap_b(state);
-                    state.cycles += 8;
-                }
-                0x31 => {
-                    /* SWAP C */
-                    swap_c(state);
-                    state.cycles += 8;
-                }
-                0x32 => {
-                    /* SWAP D */
-                    swap_d(state);
-                    state.cycles += 8;
-                }
-                0x33 => {
-                    /* SWAP E */
-                    swap_e(state);
-                    state.cycles += 8;
-                }
-                0x34 => {
-                    /* SWAP H */
-                    swap_h(state);
-                    state.cycles += 8;
-                }
-                0x35 => {
-                    /* SWAP L */
-                    swap_l(state);
-                    state.cycles += 8;
-                }
-                0x36 => {
-                    /* SWAP (HL) */
-                    swap_hl_indirect(state);
-                    state.cycles += 16;
-                }
-                0x37 => {
-                    /* SWAP A */
-                    swap_a(state);
-                    state.cycles += 8;
-                }
-                0x38 => {
-                    /* SRL B */
-                    srl_b(state);
-                    state.cycles += 8;
-                }
-                0x39 => {
-                    /* SRL C */
-                    srl_c(state);
-                    state.cycles += 8;
-                }
-                0x3A => {
-                    /* SRL D */
-                    srl_d(state);
-                    state.cycles += 8;
-                }
-                0x3B => {
-                    /* SRL E */
-                    srl_e(state);
-                    state.cycles += 8;
-                }
-                0x3C => {
-                    /* SRL H */
-                    srl_h(state);
-                    state.cycles += 8;
-                }
-                0x3D => {
-                    /* SRL L */
-                    srl_l(state);
-                    state.cycles += 8;
-                }
-                0x3E => {
-                    /* SRL (HL) */
-                    srl_hl_indirect(state);
-                    state.cycles += 16;
-                }
-                0x3F => {
-                    /* SRL A */
-                    srl_a(state);
-                    state.cycles += 8;
-                }
-                // BIT instructions (0x40-0x7F) - Test bit b in register r
-                // Pattern: 0b01bbbrrr where bbb = bit (0-7), rrr = register
-                0x40..=0x7F => {
-                    let bit = (cb_op >> 3) & 0x07; // Extract bit number (bits 3-5)
-                    let reg = cb_op & 0x07; // Extract register index (bits 0-2)
-
-                    let value = match reg {
-                        0 => state.b,
-                        1 => state.c,
-                        2 => state.d,
-                        3 => state.e,
-                        4 => state.h,
-                        5 => state.l,
-                        6 => state.read(state.hl()), // (HL)
-                        7 => state.a,
-                        _ => unreachable!(),
-                    };
-
-                    bit_test(value, bit, state);
-                    state.cycles += if reg == 6 { 12 } else { 8 }; // (HL) takes 12 cycles, others 8
-                }
-                // RES instructions (0x80-0xBF) - Reset (clear) bit b in register r
-                // Pattern: 0b10bbbrrr where bbb = bit (0-7), rrr = register
-                0x80..=0xBF => {
-                    let bit = (cb_op >> 3) & 0x07; // Extract bit number (bits 3-5)
-                    let reg = cb_op & 0x07; // Extract register index (bits 0-2)
-
-                    let value = match reg {
-                        0 => state.b,
-                        1 => state.c,
-                        2 => state.d,
-                        3 => state.e,
-                        4 => state.h,
-                        5 => state.l,
-                        6 => state.read(state.hl()), // (HL)
-                        7 => state.a,
-                        _ => unreachable!(),
-                    };
-
-                    let result = res_bit(value, bit);
-
-                    match reg {
-                        0 => state.b = result,
-                        1 => state.c = result,
-                        2 => state.d = result,
-                        3 => state.e = result,
-                        4 => state.h = result,
-                        5 => state.l = result,
-                        6 => state.write(state.hl(), result), // (HL)
-                        7 => state.a = result,
-                        _ => unreachable!(),
-                    }
-
-                    state.cycles += if reg == 6 { 16 } else { 8 }; // (HL) takes 16 cycles, others 8
-                }
-                // SET instructions (0xC0-0xFF) - Set bit b in register r
-                // Pattern: 0b11bbbrrr where bbb = bit (0-7), rrr = register
-                0xC0..=0xFF => {
-                    let bit = (cb_op >> 3) & 0x07; // Extract bit number (bits 3-5)
-                    let reg = cb_op & 0x07; // Extract register index (bits 0-2)
-
-                    let value = match reg {
-                        0 => state.b,
-                        1 => state.c,
-                        2 => state.d,
-                        3 => state.e,
-                        4 => state.h,
-                        5 => state.l,
-                        6 => state.read(state.hl()), // (HL)
-                        7 => state.a,
-                        _ => unreachable!(),
-                    };
-
-                    let result = set_bit(value, bit);
-
-                    match reg {
-                        0 => state.b = result,
-                        1 => state.c = result,
-                        2 => state.d = result,
-                        3 => state.e = result,
-                        4 => state.h = result,
-                        5 => state.l = result,
-                        6 => state.write(state.hl(), result), // (HL)
-                        7 => state.a = result,
-                        _ => unreachable!(),
-                    }
-
-                    state.cycles += if reg == 6 { 16 } else { 8 }; // (HL) takes 16 cycles, others 8
-                }
-            }
-        }
-        0xCC => {
-            /* CALL Z */
-            call_z(state);
-            // Conditional call: 12 cycles if not taken, 24 cycles if taken
-            state.cycles += if state.flag_z() { 24 } else { 12 };
-        }
-        0xCD => {
-            /* CALL */
-            call(state);
-            state.cycles += 24;
-        }
-        0xCE => {
-            /* ADC A,n */
-            let value = read_immediate_byte(state);
-            adc_a(value, state);
-            state.cycles += 8;
-        }
-        0xCF => {
-            /* RST 08h */
-            rst_08(state);
-            state.cycles += 16;
-        }
-        0xD0 => {
-            /* RET NC */
-            ret_nc(state);
-            // Conditional return: 8 cycles if not taken, 20 cycles if taken
-            state.cycles += if !state.flag_c() { 20 } else { 8 };
-        }
-        0xD1 => {
-            /* POP DE */
-            pop_de(state);
-            state.cycles += 12;
-        }
-        0xD2 => {
-            /* JP NC */
-            jp_nc(state);
-            // Conditional jump: 12 cycles if not taken, 16 cycles if taken
-            state.cycles += if !state.flag_c() { 16 } else { 12 };
-        }
-        0xD3 => {
-            /* Illegal opcode */
-            illegal_opcode(0xD3, state);
-        }
-        0xD4 => {
-            /* CALL NC */
-            call_nc(state);
-            // Conditional call: 12 cycles if not taken, 24 cycles if taken
-            state.cycles += if !state.flag_c() { 24 } else { 12 };
-        }
-        0xD5 => {
-            /* PUSH DE */
-            push_de(state);
-            state.cycles += 16;
-        }
-        0xD6 => {
-            /* SUB n */
-            let value = read_immediate_byte(state);
-            sub_a(value, state);
-            state.cycles += 8;
-        }
-        0xD7 => {
-            /* RST 10h */
-            rst_10(state);
-            state.cycles += 16;
-        }
-        0xD8 => {
-            /* RET C */
-            ret_c(state);
-            // Conditional return: 8 cycles if not taken, 20 cycles if taken
-            state.cycles += if state.flag_c() { 20 } else { 8 };
-        }
-        0xD9 => {
-            /* RETI */
-            reti(state);
-            state.cycles += 16;
-        }
-        0xDA => {
-            /* JP C */
-            jp_c(state);
-            // Conditional jump: 12 cycles if not taken, 16 cycles if taken
-            state.cycles += if state.flag_c() { 16 } else { 12 };
-        }
-        0xDB => {
-            /* Illegal opcode */
-            illegal_opcode(0xDB, state);
-        }
-        0xDC => {
-            /* CALL C */
-            call_c(state);
-            // Conditional call: 12 cycles if not taken, 24 cycles if taken
-            state.cycles += if state.flag_c() { 24 } else { 12 };
-        }
-        0xDD => {
-            /* Illegal opcode */
-            illegal_opcode(0xDD, state);
-        }
-        0xDE => {
-            /* SBC A,n */
-            let value = read_immediate_byte(state);
-            sbc_a(value, state);
-            state.cycles += 8;
-        }
-        0xDF => {
-            /* RST 18h */
-            rst_18(state);
-            state.cycles += 16;
-        }
-        0xE0 => {
-            /* LDH (n),A */
-            ldh_n_a(state);
-            state.cycles += 12;
-        }
-        0xE1 => {
-            /* POP HL */
-            pop_hl(state);
-            state.cycles += 12;
-        }
-        0xE2 => {
-            /* LDH (C),A */
-            ldh_c_a(state);
-            state.cycles += 8;
-        }
-        0xE3 => {
-            /* Illegal opcode */
-            illegal_opcode(0xE3, state);
-        }
-        0xE4 => {
-            /* Illegal opcode */
-            illegal_opcode(0xE4, state);
-        }
-        0xE5 => {
-            /* PUSH HL */
-            push_hl(state);
-            state.cycles += 16;
-        }
-        0xE6 => {
-            /* AND n */
-            let value = read_immediate_byte(state);
-            and_a(value, state);
-            state.cycles += 8;
-        }
-        0xE7 => {
-            /* RST 20h */
-            rst_20(state);
-            state.cycles += 16;
-        }
-        0xE8 => {
-            /* ADD SP,n */
-            add_sp_n(state);
-            state.cycles += 16;
-        }
-        0xE9 => {
-            /* JP HL */
-            jp_hl(state);
-            state.cycles += 4;
-        }
-        0xEA => {
-            /* LD (nn),A */
-            ld_nn_a(state);
-            state.cycles += 16;
-        }
-        0xEB => {
-            /* Illegal opcode */
-            illegal_opcode(0xEB, state);
-        }
-        0xEC => {
-            /* Illegal opcode */
-            illegal_opcode(0xEC, state);
-        }
-        0xED => {
-            /* Illegal opcode */
-            illegal_opcode(0xED, state);
-        }
-        0xEE => {
-            /* XOR n */
-            let value = read_immediate_byte(state);
-            xor_a(value, state);
-            state.cycles += 8;
-        }
-        0xEF => {
-            /* RST 28h */
-            rst_28(state);
-            state.cycles += 16;
-        }
-        0xF0 => {
-            /* LDH A,(n) */
-            ldh_a_n(state);
-            state.cycles += 12;
-        }
-        0xF1 => {
-            /* POP AF */
-            pop_af(state);
-            state.cycles += 12;
-        }
-        0xF2 => {
-            /* LDH A,(C) */
-            ldh_a_c(state);
-            state.cycles += 8;
-        }
-        0xF3 => {
-            /* DI */
-            state.di_delay = true;
-            state.cycles += 4;
-        }
-        0xF4 => {
-            /* Illegal opcode */
-            illegal_opcode(0xF4, state);
-        }
-        0xF5 => {
-            /* PUSH AF */
-            push_af(state);
-            state.cycles += 16;
-        }
-        0xF6 => {
-            /* OR n */
-            let value = read_immediate_byte(state);
-            or_a(value, state);
-            state.cycles += 8;
-        }
-        0xF7 => {
-            /* RST 30h */
-            rst_30(state);
-            state.cycles += 16;
-        }
-        0xF8 => {
-            /* LD HL,SP+n */
-            ld_hl_sp_n(state);
-            state.cycles += 12;
-        }
-        0xF9 => {
-            /* LD SP,HL */
-            state.sp = state.hl();
-            state.cycles += 8;
-        }
-        0xFA => {
-            /* LD A,(nn) */
-            ld_a_nn(state);
-            state.cycles += 16;
-        }
-        0xFB => {
-            /* EI */
-            state.ei_delay = true;
-            state.cycles += 4;
-        }
-        0xFC => {
-            /* Illegal opcode */
-            illegal_opcode(0xFC, state);
-        }
-        0xFD => {
-            /* Illegal opcode */
-            illegal_opcode(0xFD, state);
-        }
-        0xFE => {
-            /* CP n */
-            let value = read_immediate_byte(state);
-            cp_a(value, state);
-            state.cycles += 8;
-        }
-        0xFF => {
-            /* RST 38h */
-            rst_38(state);
-            state.cycles += 16;
-        }
-    }
+    let handler = &Dispatch::<M>::OPCODES[op as usize];
+    handler(state);
 }
 
 #[cfg(test)]
@@ -3882,7 +1858,7 @@ mod tests {
         state.write(0xFFF0, 0xCD); // Low byte
         state.write(0xFFF1, 0xAB); // High byte
 
-        ret_nz(&mut state);
+        ret_cond(Cond::NZ, &mut state);
 
         assert_eq!(state.pc, 0xABCD); // PC set to return address
         assert_eq!(state.sp, 0xFFF2); // SP incremented by 2
@@ -3899,7 +1875,7 @@ mod tests {
         state.write(0xFFF0, 0xCD); // Low byte
         state.write(0xFFF1, 0xAB); // High byte
 
-        ret_nz(&mut state);
+        ret_cond(Cond::NZ, &mut state);
 
         assert_eq!(state.pc, 0x1234); // PC unchanged
         assert_eq!(state.sp, 0xFFF0); // SP unchanged
@@ -3916,7 +1892,7 @@ mod tests {
         state.write(0xFFF0, 0x78); // Low byte
         state.write(0xFFF1, 0x56); // High byte
 
-        ret_z(&mut state);
+        ret_cond(Cond::Z, &mut state);
 
         assert_eq!(state.pc, 0x5678); // PC set to return address
         assert_eq!(state.sp, 0xFFF2); // SP incremented by 2
@@ -3933,7 +1909,7 @@ mod tests {
         state.write(0xFFF0, 0x78); // Low byte
         state.write(0xFFF1, 0x56); // High byte
 
-        ret_z(&mut state);
+        ret_cond(Cond::Z, &mut state);
 
         assert_eq!(state.pc, 0x1234); // PC unchanged
         assert_eq!(state.sp, 0xFFF0); // SP unchanged
@@ -3965,7 +1941,7 @@ mod tests {
         state.write(0xFFF0, 0xCD); // Low byte
         state.write(0xFFF1, 0xAB); // High byte
 
-        ret_nc(&mut state);
+        ret_cond(Cond::NC, &mut state);
 
         assert_eq!(state.pc, 0xABCD); // PC set to return address
         assert_eq!(state.sp, 0xFFF2); // SP incremented by 2
@@ -3982,7 +1958,7 @@ mod tests {
         state.write(0xFFF0, 0xCD); // Low byte
         state.write(0xFFF1, 0xAB); // High byte
 
-        ret_nc(&mut state);
+        ret_cond(Cond::NC, &mut state);
 
         assert_eq!(state.pc, 0x1234); // PC unchanged
         assert_eq!(state.sp, 0xFFF0); // SP unchanged
@@ -3999,7 +1975,7 @@ mod tests {
         state.write(0xFFF0, 0xCD); // Low byte
         state.write(0xFFF1, 0xAB); // High byte
 
-        ret_c(&mut state);
+        ret_cond(Cond::C, &mut state);
 
         assert_eq!(state.pc, 0xABCD); // PC set to return address
         assert_eq!(state.sp, 0xFFF2); // SP incremented by 2
@@ -4016,7 +1992,7 @@ mod tests {
         state.write(0xFFF0, 0xCD); // Low byte
         state.write(0xFFF1, 0xAB); // High byte
 
-        ret_c(&mut state);
+        ret_cond(Cond::C, &mut state);
 
         assert_eq!(state.pc, 0x1234); // PC unchanged
         assert_eq!(state.sp, 0xFFF0); // SP unchanged
@@ -4052,7 +2028,7 @@ mod tests {
         state.write(0xFFF0, 0x34); // Low byte (C)
         state.write(0xFFF1, 0x12); // High byte (B)
 
-        pop_bc(&mut state);
+        pop_r16(R16Stack::BC, &mut state);
 
         assert_eq!(state.c, 0x34);
         assert_eq!(state.b, 0x12);
@@ -4068,7 +2044,7 @@ mod tests {
         state.write(0x2000, 0xCD); // Low byte goes to C
         state.write(0x2001, 0xAB); // High byte goes to B
 
-        pop_bc(&mut state);
+        pop_r16(R16Stack::BC, &mut state);
 
         assert_eq!(state.c, 0xCD);
         assert_eq!(state.b, 0xAB);
@@ -4085,7 +2061,7 @@ mod tests {
         state.write(0x3000, 0x11);
         state.write(0x3001, 0x22);
 
-        pop_bc(&mut state);
+        pop_r16(R16Stack::BC, &mut state);
 
         assert_eq!(state.c, 0x11);
         assert_eq!(state.b, 0x22);
@@ -4103,7 +2079,7 @@ mod tests {
         state.write(0xFFF0, 0x78); // Low byte (E)
         state.write(0xFFF1, 0x56); // High byte (D)
 
-        pop_de(&mut state);
+        pop_r16(R16Stack::DE, &mut state);
 
         assert_eq!(state.e, 0x78);
         assert_eq!(state.d, 0x56);
@@ -4148,7 +2124,7 @@ mod tests {
         state.write(0x150, 0xCD); // Low byte
         state.write(0x151, 0xAB); // High byte
 
-        jp_nz(&mut state);
+        jp_cond(Cond::NZ, &mut state);
 
         assert_eq!(state.pc, 0xABCD);
     }
@@ -4162,7 +2138,7 @@ mod tests {
         state.write(0x150, 0xCD); // Low byte
         state.write(0x151, 0xAB); // High byte
 
-        jp_nz(&mut state);
+        jp_cond(Cond::NZ, &mut state);
 
         // PC should be incremented by 2 (past the address bytes) but not jump
         assert_eq!(state.pc, 0x152);
@@ -4177,7 +2153,7 @@ mod tests {
         state.write(0x200, 0x34); // Low byte
         state.write(0x201, 0x12); // High byte
 
-        jp_z(&mut state);
+        jp_cond(Cond::Z, &mut state);
 
         assert_eq!(state.pc, 0x1234);
     }
@@ -4191,7 +2167,7 @@ mod tests {
         state.write(0x200, 0x34); // Low byte
         state.write(0x201, 0x12); // High byte
 
-        jp_z(&mut state);
+        jp_cond(Cond::Z, &mut state);
 
         // PC should be incremented by 2 (past the address bytes) but not jump
         assert_eq!(state.pc, 0x202);
@@ -4206,7 +2182,7 @@ mod tests {
         state.write(0x200, 0x00); // Low byte
         state.write(0x201, 0x30); // High byte
 
-        jp_nc(&mut state);
+        jp_cond(Cond::NC, &mut state);
 
         assert_eq!(state.pc, 0x3000);
     }
@@ -4220,7 +2196,7 @@ mod tests {
         state.write(0x200, 0x00); // Low byte
         state.write(0x201, 0x30); // High byte
 
-        jp_nc(&mut state);
+        jp_cond(Cond::NC, &mut state);
 
         // PC should be incremented by 2 (past the address bytes) but not jump
         assert_eq!(state.pc, 0x202);
@@ -4235,7 +2211,7 @@ mod tests {
         state.write(0x400, 0x00); // Low byte
         state.write(0x401, 0x50); // High byte
 
-        jp_c(&mut state);
+        jp_cond(Cond::C, &mut state);
 
         assert_eq!(state.pc, 0x5000);
     }
@@ -4249,7 +2225,7 @@ mod tests {
         state.write(0x400, 0x00); // Low byte
         state.write(0x401, 0x50); // High byte
 
-        jp_c(&mut state);
+        jp_cond(Cond::C, &mut state);
 
         // PC should be incremented by 2 (past the address bytes) but not jump
         assert_eq!(state.pc, 0x402);
@@ -4287,7 +2263,7 @@ mod tests {
         state.write(0x200, 0x34); // Low byte
         state.write(0x201, 0x12); // High byte
 
-        call_nz(&mut state);
+        call_cond(Cond::NZ, &mut state);
 
         // Should have called (jumped and pushed return address)
         assert_eq!(state.pc, 0x1234);
@@ -4306,7 +2282,7 @@ mod tests {
         state.write(0x200, 0x34); // Low byte
         state.write(0x201, 0x12); // High byte
 
-        call_nz(&mut state);
+        call_cond(Cond::NZ, &mut state);
 
         // Should not have called (PC advanced, SP unchanged)
         assert_eq!(state.pc, 0x202);
@@ -4323,7 +2299,7 @@ mod tests {
         state.write(0x300, 0x78); // Low byte
         state.write(0x301, 0x56); // High byte
 
-        call_z(&mut state);
+        call_cond(Cond::Z, &mut state);
 
         // Should have called (jumped and pushed return address)
         assert_eq!(state.pc, 0x5678);
@@ -4342,7 +2318,7 @@ mod tests {
         state.write(0x300, 0x78); // Low byte
         state.write(0x301, 0x56); // High byte
 
-        call_z(&mut state);
+        call_cond(Cond::Z, &mut state);
 
         // Should not have called (PC advanced, SP unchanged)
         assert_eq!(state.pc, 0x302);
@@ -4359,7 +2335,7 @@ mod tests {
         state.write(0x300, 0x56); // Low byte
         state.write(0x301, 0x34); // High byte
 
-        call_nc(&mut state);
+        call_cond(Cond::NC, &mut state);
 
         // Should have called (jumped and pushed return address)
         assert_eq!(state.pc, 0x3456);
@@ -4378,7 +2354,7 @@ mod tests {
         state.write(0x300, 0x56); // Low byte
         state.write(0x301, 0x34); // High byte
 
-        call_nc(&mut state);
+        call_cond(Cond::NC, &mut state);
 
         // Should not have called (PC advanced, SP unchanged)
         assert_eq!(state.pc, 0x302);
@@ -4395,7 +2371,7 @@ mod tests {
         state.write(0x500, 0x78); // Low byte
         state.write(0x501, 0x56); // High byte
 
-        call_c(&mut state);
+        call_cond(Cond::C, &mut state);
 
         // Should have called (jumped and pushed return address)
         assert_eq!(state.pc, 0x5678);
@@ -4414,7 +2390,7 @@ mod tests {
         state.write(0x500, 0x78); // Low byte
         state.write(0x501, 0x56); // High byte
 
-        call_c(&mut state);
+        call_cond(Cond::C, &mut state);
 
         // Should not have called (PC advanced, SP unchanged)
         assert_eq!(state.pc, 0x502);
@@ -4443,7 +2419,7 @@ mod tests {
         state.pc = 0xABCD;
         state.sp = 0xFFFE;
 
-        rst_00(&mut state);
+        rst(0x00, &mut state);
 
         // PC should be at RST vector 0x0000
         assert_eq!(state.pc, 0x0000);
@@ -4460,7 +2436,7 @@ mod tests {
         state.pc = 0x1234;
         state.sp = 0xFFFE;
 
-        rst_08(&mut state);
+        rst(0x08, &mut state);
 
         // PC should be at RST vector 0x0008
         assert_eq!(state.pc, 0x0008);
@@ -4477,7 +2453,7 @@ mod tests {
         state.pc = 0x5678;
         state.sp = 0xFFFE;
 
-        rst_10(&mut state);
+        rst(0x10, &mut state);
 
         // PC should be at RST vector 0x0010
         assert_eq!(state.pc, 0x0010);
@@ -4494,7 +2470,7 @@ mod tests {
         state.pc = 0x9ABC;
         state.sp = 0xFFFE;
 
-        rst_18(&mut state);
+        rst(0x18, &mut state);
 
         // PC should be at RST vector 0x0018
         assert_eq!(state.pc, 0x0018);
@@ -4513,7 +2489,7 @@ mod tests {
         state.d = 0xAB;
         state.e = 0xCD;
 
-        push_de(&mut state);
+        push_r16(R16Stack::DE, &mut state);
 
         // SP decremented by 2
         assert_eq!(state.sp, 0x2FFE);
@@ -4530,7 +2506,7 @@ mod tests {
         state.b = 0x12;
         state.c = 0x34;
 
-        push_bc(&mut state);
+        push_r16(R16Stack::BC, &mut state);
 
         // SP decremented by 2
         assert_eq!(state.sp, 0x2FFE);
@@ -4552,7 +2528,7 @@ mod tests {
         state.write(0x2000, 0x34); // Low byte (L)
         state.write(0x2001, 0x12); // High byte (H)
 
-        pop_hl(&mut state);
+        pop_r16(R16Stack::HL, &mut state);
 
         assert_eq!(state.l, 0x34);
         assert_eq!(state.h, 0x12);
@@ -4566,7 +2542,7 @@ mod tests {
         state.h = 0x56;
         state.l = 0x78;
 
-        push_hl(&mut state);
+        push_r16(R16Stack::HL, &mut state);
 
         // SP decremented by 2
         assert_eq!(state.sp, 0x3FFE);
@@ -4610,7 +2586,7 @@ mod tests {
         let mut state = GameBoy::<FlatMemory>::new();
         state.a = 0x42;
 
-        inc_a(&mut state);
+        inc_r8(R8::A, &mut state);
 
         assert_eq!(state.a, 0x43);
         assert!(!state.flag_z());
@@ -4623,7 +2599,7 @@ mod tests {
         let mut state = GameBoy::<FlatMemory>::new();
         state.b = 0xFF;
 
-        inc_b(&mut state);
+        inc_r8(R8::B, &mut state);
 
         assert_eq!(state.b, 0x00);
         assert!(state.flag_z()); // Result is zero
@@ -4636,7 +2612,7 @@ mod tests {
         let mut state = GameBoy::<FlatMemory>::new();
         state.c = 0x0F;
 
-        inc_c(&mut state);
+        inc_r8(R8::C, &mut state);
 
         assert_eq!(state.c, 0x10);
         assert!(!state.flag_z());
@@ -4649,25 +2625,25 @@ mod tests {
         let mut state = GameBoy::<FlatMemory>::new();
 
         state.a = 0x00;
-        inc_a(&mut state);
+        inc_r8(R8::A, &mut state);
         assert_eq!(state.a, 0x01);
         state.b = 0x00;
-        inc_b(&mut state);
+        inc_r8(R8::B, &mut state);
         assert_eq!(state.b, 0x01);
         state.c = 0x00;
-        inc_c(&mut state);
+        inc_r8(R8::C, &mut state);
         assert_eq!(state.c, 0x01);
         state.d = 0x00;
-        inc_d(&mut state);
+        inc_r8(R8::D, &mut state);
         assert_eq!(state.d, 0x01);
         state.e = 0x00;
-        inc_e(&mut state);
+        inc_r8(R8::E, &mut state);
         assert_eq!(state.e, 0x01);
         state.h = 0x00;
-        inc_h(&mut state);
+        inc_r8(R8::H, &mut state);
         assert_eq!(state.h, 0x01);
         state.l = 0x00;
-        inc_l(&mut state);
+        inc_r8(R8::L, &mut state);
         assert_eq!(state.l, 0x01);
     }
 
@@ -4676,7 +2652,7 @@ mod tests {
         let mut state = GameBoy::<FlatMemory>::new();
         state.set_bc(0x1234);
 
-        inc_bc(&mut state);
+        inc_r16(R16::BC, &mut state);
 
         assert_eq!(state.bc(), 0x1235);
     }
@@ -4686,7 +2662,7 @@ mod tests {
         let mut state = GameBoy::<FlatMemory>::new();
         state.set_de(0xFFFF);
 
-        inc_de(&mut state);
+        inc_r16(R16::DE, &mut state);
 
         assert_eq!(state.de(), 0x0000);
     }
@@ -4696,16 +2672,16 @@ mod tests {
         let mut state = GameBoy::<FlatMemory>::new();
 
         state.set_bc(0x0000);
-        inc_bc(&mut state);
+        inc_r16(R16::BC, &mut state);
         assert_eq!(state.bc(), 0x0001);
         state.set_de(0x0000);
-        inc_de(&mut state);
+        inc_r16(R16::DE, &mut state);
         assert_eq!(state.de(), 0x0001);
         state.set_hl(0x0000);
-        inc_hl(&mut state);
+        inc_r16(R16::HL, &mut state);
         assert_eq!(state.hl(), 0x0001);
         state.set_sp(0x0000);
-        inc_sp(&mut state);
+        inc_r16(R16::SP, &mut state);
         assert_eq!(state.sp(), 0x0001);
     }
 
@@ -4714,7 +2690,7 @@ mod tests {
         let mut state = GameBoy::<FlatMemory>::new();
         state.a = 0x42;
 
-        dec_a(&mut state);
+        dec_r8(R8::A, &mut state);
 
         assert_eq!(state.a, 0x41);
         assert!(!state.flag_z());
@@ -4727,7 +2703,7 @@ mod tests {
         let mut state = GameBoy::<FlatMemory>::new();
         state.b = 0x01;
 
-        dec_b(&mut state);
+        dec_r8(R8::B, &mut state);
 
         assert_eq!(state.b, 0x00);
         assert!(state.flag_z()); // Result is zero
@@ -4740,7 +2716,7 @@ mod tests {
         let mut state = GameBoy::<FlatMemory>::new();
         state.c = 0x10;
 
-        dec_c(&mut state);
+        dec_r8(R8::C, &mut state);
 
         assert_eq!(state.c, 0x0F);
         assert!(!state.flag_z());
@@ -4753,7 +2729,7 @@ mod tests {
         let mut state = GameBoy::<FlatMemory>::new();
         state.d = 0x00;
 
-        dec_d(&mut state);
+        dec_r8(R8::D, &mut state);
 
         assert_eq!(state.d, 0xFF);
         assert!(!state.flag_z());
@@ -4766,25 +2742,25 @@ mod tests {
         let mut state = GameBoy::<FlatMemory>::new();
 
         state.a = 0x02;
-        dec_a(&mut state);
+        dec_r8(R8::A, &mut state);
         assert_eq!(state.a, 0x01);
         state.b = 0x02;
-        dec_b(&mut state);
+        dec_r8(R8::B, &mut state);
         assert_eq!(state.b, 0x01);
         state.c = 0x02;
-        dec_c(&mut state);
+        dec_r8(R8::C, &mut state);
         assert_eq!(state.c, 0x01);
         state.d = 0x02;
-        dec_d(&mut state);
+        dec_r8(R8::D, &mut state);
         assert_eq!(state.d, 0x01);
         state.e = 0x02;
-        dec_e(&mut state);
+        dec_r8(R8::E, &mut state);
         assert_eq!(state.e, 0x01);
         state.h = 0x02;
-        dec_h(&mut state);
+        dec_r8(R8::H, &mut state);
         assert_eq!(state.h, 0x01);
         state.l = 0x02;
-        dec_l(&mut state);
+        dec_r8(R8::L, &mut state);
         assert_eq!(state.l, 0x01);
     }
 
@@ -4793,7 +2769,7 @@ mod tests {
         let mut state = GameBoy::<FlatMemory>::new();
         state.set_bc(0x1234);
 
-        dec_bc(&mut state);
+        dec_r16(R16::BC, &mut state);
 
         assert_eq!(state.bc(), 0x1233);
     }
@@ -4803,7 +2779,7 @@ mod tests {
         let mut state = GameBoy::<FlatMemory>::new();
         state.set_de(0x0000);
 
-        dec_de(&mut state);
+        dec_r16(R16::DE, &mut state);
 
         assert_eq!(state.de(), 0xFFFF);
     }
@@ -4813,16 +2789,16 @@ mod tests {
         let mut state = GameBoy::<FlatMemory>::new();
 
         state.set_bc(0x0002);
-        dec_bc(&mut state);
+        dec_r16(R16::BC, &mut state);
         assert_eq!(state.bc(), 0x0001);
         state.set_de(0x0002);
-        dec_de(&mut state);
+        dec_r16(R16::DE, &mut state);
         assert_eq!(state.de(), 0x0001);
         state.set_hl(0x0002);
-        dec_hl(&mut state);
+        dec_r16(R16::HL, &mut state);
         assert_eq!(state.hl(), 0x0001);
         state.set_sp(0x0002);
-        dec_sp(&mut state);
+        dec_r16(R16::SP, &mut state);
         assert_eq!(state.sp(), 0x0001);
     }
 
@@ -4831,7 +2807,7 @@ mod tests {
         let mut state = GameBoy::<FlatMemory>::new();
         state.a = 0b0100_1010; // 0x4A
 
-        rlc_a(&mut state);
+        shift_r8(ShiftOp::Rlc, R8::A, &mut state);
 
         assert_eq!(state.a, 0b1001_0100); // 0x94
         assert!(!state.flag_z());
@@ -4845,7 +2821,7 @@ mod tests {
         let mut state = GameBoy::<FlatMemory>::new();
         state.b = 0b1100_1010; // 0xCA
 
-        rlc_b(&mut state);
+        shift_r8(ShiftOp::Rlc, R8::B, &mut state);
 
         assert_eq!(state.b, 0b1001_0101); // 0x95
         assert!(!state.flag_z());
@@ -4859,7 +2835,7 @@ mod tests {
         let mut state = GameBoy::<FlatMemory>::new();
         state.c = 0x00;
 
-        rlc_c(&mut state);
+        shift_r8(ShiftOp::Rlc, R8::C, &mut state);
 
         assert_eq!(state.c, 0x00);
         assert!(state.flag_z()); // Result is zero
@@ -4873,7 +2849,7 @@ mod tests {
         let mut state = GameBoy::<FlatMemory>::new();
         state.d = 0x80; // 0b1000_0000
 
-        rlc_d(&mut state);
+        shift_r8(ShiftOp::Rlc, R8::D, &mut state);
 
         assert_eq!(state.d, 0x01); // 0b0000_0001 - bit 7 wraps to bit 0
         assert!(!state.flag_z());
@@ -4887,31 +2863,31 @@ mod tests {
         let mut state = GameBoy::<FlatMemory>::new();
 
         state.a = 0x01;
-        rlc_a(&mut state);
+        shift_r8(ShiftOp::Rlc, R8::A, &mut state);
         assert_eq!(state.a, 0x02);
 
         state.b = 0x01;
-        rlc_b(&mut state);
+        shift_r8(ShiftOp::Rlc, R8::B, &mut state);
         assert_eq!(state.b, 0x02);
 
         state.c = 0x01;
-        rlc_c(&mut state);
+        shift_r8(ShiftOp::Rlc, R8::C, &mut state);
         assert_eq!(state.c, 0x02);
 
         state.d = 0x01;
-        rlc_d(&mut state);
+        shift_r8(ShiftOp::Rlc, R8::D, &mut state);
         assert_eq!(state.d, 0x02);
 
         state.e = 0x01;
-        rlc_e(&mut state);
+        shift_r8(ShiftOp::Rlc, R8::E, &mut state);
         assert_eq!(state.e, 0x02);
 
         state.h = 0x01;
-        rlc_h(&mut state);
+        shift_r8(ShiftOp::Rlc, R8::H, &mut state);
         assert_eq!(state.h, 0x02);
 
         state.l = 0x01;
-        rlc_l(&mut state);
+        shift_r8(ShiftOp::Rlc, R8::L, &mut state);
         assert_eq!(state.l, 0x02);
     }
 
@@ -4920,7 +2896,7 @@ mod tests {
         let mut state = GameBoy::<FlatMemory>::new();
         state.a = 0xFF;
 
-        rlc_a(&mut state);
+        shift_r8(ShiftOp::Rlc, R8::A, &mut state);
 
         assert_eq!(state.a, 0xFF); // All bits rotate, stays same
         assert!(!state.flag_z());
@@ -4963,7 +2939,7 @@ mod tests {
         state.set_hl(0x1000);
         state.write(0x1000, 0b0100_1010); // 0x4A
 
-        rlc_hl_indirect(&mut state);
+        shift_r8(ShiftOp::Rlc, R8::HlIndirect, &mut state);
 
         assert_eq!(state.read(0x1000), 0b1001_0100); // 0x94
         assert!(!state.flag_z());
@@ -4978,7 +2954,7 @@ mod tests {
         state.set_hl(0x2000);
         state.write(0x2000, 0b1010_0101); // 0xA5
 
-        rrc_hl_indirect(&mut state);
+        shift_r8(ShiftOp::Rrc, R8::HlIndirect, &mut state);
 
         assert_eq!(state.read(0x2000), 0b1101_0010); // 0xD2 - bit 0 rotated to bit 7
         assert!(!state.flag_z());
@@ -4993,7 +2969,7 @@ mod tests {
         state.set_hl(0x3000);
         state.write(0x3000, 0x00);
 
-        rrc_hl_indirect(&mut state);
+        shift_r8(ShiftOp::Rrc, R8::HlIndirect, &mut state);
 
         assert_eq!(state.read(0x3000), 0x00);
         assert!(state.flag_z());
@@ -5009,7 +2985,7 @@ mod tests {
         state.write(0x4000, 0b0100_1010); // 0x4A
         state.set_flag_c(false);
 
-        rl_hl_indirect(&mut state);
+        shift_r8(ShiftOp::Rl, R8::HlIndirect, &mut state);
 
         assert_eq!(state.read(0x4000), 0b1001_0100); // 0x94 - shifted left, carry in = 0
         assert!(!state.flag_z());
@@ -5025,7 +3001,7 @@ mod tests {
         state.write(0x5000, 0b0100_1010); // 0x4A
         state.set_flag_c(true);
 
-        rl_hl_indirect(&mut state);
+        shift_r8(ShiftOp::Rl, R8::HlIndirect, &mut state);
 
         assert_eq!(state.read(0x5000), 0b1001_0101); // 0x95 - shifted left, carry in = 1
         assert!(!state.flag_z());
@@ -5041,7 +3017,7 @@ mod tests {
         state.write(0x6000, 0b1010_1010); // 0xAA
         state.set_flag_c(false);
 
-        rl_hl_indirect(&mut state);
+        shift_r8(ShiftOp::Rl, R8::HlIndirect, &mut state);
 
         assert_eq!(state.read(0x6000), 0b0101_0100); // 0x54 - bit 7 shifted out
         assert!(!state.flag_z());
@@ -5057,7 +3033,7 @@ mod tests {
         state.write(0x7000, 0b1001_0100); // 0x94
         state.set_flag_c(false);
 
-        rr_hl_indirect(&mut state);
+        shift_r8(ShiftOp::Rr, R8::HlIndirect, &mut state);
 
         assert_eq!(state.read(0x7000), 0b0100_1010); // 0x4A - shifted right, carry in = 0
         assert!(!state.flag_z());
@@ -5073,7 +3049,7 @@ mod tests {
         state.write(0x8000, 0b1001_0100); // 0x94
         state.set_flag_c(true);
 
-        rr_hl_indirect(&mut state);
+        shift_r8(ShiftOp::Rr, R8::HlIndirect, &mut state);
 
         assert_eq!(state.read(0x8000), 0b1100_1010); // 0xCA - shifted right, carry in = 1
         assert!(!state.flag_z());
@@ -5089,7 +3065,7 @@ mod tests {
         state.write(0x9000, 0b0101_0101); // 0x55
         state.set_flag_c(false);
 
-        rr_hl_indirect(&mut state);
+        shift_r8(ShiftOp::Rr, R8::HlIndirect, &mut state);
 
         assert_eq!(state.read(0x9000), 0b0010_1010); // 0x2A - bit 0 shifted out
         assert!(!state.flag_z());
@@ -5105,7 +3081,7 @@ mod tests {
         state.write(0xA000, 0x00);
         state.set_flag_c(false);
 
-        rr_hl_indirect(&mut state);
+        shift_r8(ShiftOp::Rr, R8::HlIndirect, &mut state);
 
         assert_eq!(state.read(0xA000), 0x00);
         assert!(state.flag_z());
@@ -5120,7 +3096,7 @@ mod tests {
         state.set_hl(0xB000);
         state.write(0xB000, 0b0100_1010); // 0x4A
 
-        sla_hl_indirect(&mut state);
+        shift_r8(ShiftOp::Sla, R8::HlIndirect, &mut state);
 
         assert_eq!(state.read(0xB000), 0b1001_0100); // 0x94 - shifted left, bit 0 = 0
         assert!(!state.flag_z());
@@ -5135,7 +3111,7 @@ mod tests {
         state.set_hl(0xC000);
         state.write(0xC000, 0b1010_1010); // 0xAA
 
-        sla_hl_indirect(&mut state);
+        shift_r8(ShiftOp::Sla, R8::HlIndirect, &mut state);
 
         assert_eq!(state.read(0xC000), 0b0101_0100); // 0x54 - bit 7 shifted out
         assert!(!state.flag_z());
@@ -5150,7 +3126,7 @@ mod tests {
         state.set_hl(0xD000);
         state.write(0xD000, 0x00);
 
-        sla_hl_indirect(&mut state);
+        shift_r8(ShiftOp::Sla, R8::HlIndirect, &mut state);
 
         assert_eq!(state.read(0xD000), 0x00);
         assert!(state.flag_z());
@@ -5165,7 +3141,7 @@ mod tests {
         state.set_hl(0xE000);
         state.write(0xE000, 0b1000_0000); // 0x80
 
-        sla_hl_indirect(&mut state);
+        shift_r8(ShiftOp::Sla, R8::HlIndirect, &mut state);
 
         assert_eq!(state.read(0xE000), 0x00); // Shifted out, result is 0
         assert!(state.flag_z());
@@ -5180,7 +3156,7 @@ mod tests {
         state.set_hl(0xF000);
         state.write(0xF000, 0b0100_1010); // 0x4A - positive number (bit 7 = 0)
 
-        sra_hl_indirect(&mut state);
+        shift_r8(ShiftOp::Sra, R8::HlIndirect, &mut state);
 
         assert_eq!(state.read(0xF000), 0b0010_0101); // 0x25 - bit 7 stays 0
         assert!(!state.flag_z());
@@ -5195,7 +3171,7 @@ mod tests {
         state.set_hl(0xF100);
         state.write(0xF100, 0b1010_1010); // 0xAA - negative number (bit 7 = 1)
 
-        sra_hl_indirect(&mut state);
+        shift_r8(ShiftOp::Sra, R8::HlIndirect, &mut state);
 
         assert_eq!(state.read(0xF100), 0b1101_0101); // 0xD5 - bit 7 stays 1 (preserves sign)
         assert!(!state.flag_z());
@@ -5210,7 +3186,7 @@ mod tests {
         state.set_hl(0xF200);
         state.write(0xF200, 0b0101_0101); // 0x55
 
-        sra_hl_indirect(&mut state);
+        shift_r8(ShiftOp::Sra, R8::HlIndirect, &mut state);
 
         assert_eq!(state.read(0xF200), 0b0010_1010); // 0x2A - bit 0 shifted out
         assert!(!state.flag_z());
@@ -5225,7 +3201,7 @@ mod tests {
         state.set_hl(0xF300);
         state.write(0xF300, 0x00);
 
-        sra_hl_indirect(&mut state);
+        shift_r8(ShiftOp::Sra, R8::HlIndirect, &mut state);
 
         assert_eq!(state.read(0xF300), 0x00);
         assert!(state.flag_z());
@@ -5240,7 +3216,7 @@ mod tests {
         state.set_hl(0xF400);
         state.write(0xF400, 0xFF); // All 1s
 
-        sra_hl_indirect(&mut state);
+        shift_r8(ShiftOp::Sra, R8::HlIndirect, &mut state);
 
         assert_eq!(state.read(0xF400), 0xFF); // Still all 1s (sign preserved)
         assert!(!state.flag_z());
@@ -5255,7 +3231,7 @@ mod tests {
         state.set_hl(0xF500);
         state.write(0xF500, 0x12); // Upper nibble = 1, lower nibble = 2
 
-        swap_hl_indirect(&mut state);
+        shift_r8(ShiftOp::Swap, R8::HlIndirect, &mut state);
 
         assert_eq!(state.read(0xF500), 0x21); // Swapped to 2 and 1
         assert!(!state.flag_z());
@@ -5270,7 +3246,7 @@ mod tests {
         state.set_hl(0xF600);
         state.write(0xF600, 0x00);
 
-        swap_hl_indirect(&mut state);
+        shift_r8(ShiftOp::Swap, R8::HlIndirect, &mut state);
 
         assert_eq!(state.read(0xF600), 0x00);
         assert!(state.flag_z());
@@ -5285,7 +3261,7 @@ mod tests {
         state.set_hl(0xF700);
         state.write(0xF700, 0xAB); // Upper = A, lower = B
 
-        swap_hl_indirect(&mut state);
+        shift_r8(ShiftOp::Swap, R8::HlIndirect, &mut state);
 
         assert_eq!(state.read(0xF700), 0xBA); // Upper = B, lower = A
         assert!(!state.flag_z());
@@ -5300,10 +3276,10 @@ mod tests {
         state.set_hl(0xF800);
         state.write(0xF800, 0x34);
 
-        swap_hl_indirect(&mut state);
+        shift_r8(ShiftOp::Swap, R8::HlIndirect, &mut state);
         assert_eq!(state.read(0xF800), 0x43);
 
-        swap_hl_indirect(&mut state);
+        shift_r8(ShiftOp::Swap, R8::HlIndirect, &mut state);
         assert_eq!(state.read(0xF800), 0x34); // Back to original
     }
 
@@ -5314,7 +3290,7 @@ mod tests {
         state.write(0xF900, 0x56);
         state.set_flag_c(true); // Set carry before swap
 
-        swap_hl_indirect(&mut state);
+        shift_r8(ShiftOp::Swap, R8::HlIndirect, &mut state);
 
         assert_eq!(state.read(0xF900), 0x65);
         assert!(!state.flag_c()); // SWAP always clears carry
@@ -5326,7 +3302,7 @@ mod tests {
         state.set_hl(0xFA00);
         state.write(0xFA00, 0b1001_0100); // 0x94
 
-        srl_hl_indirect(&mut state);
+        shift_r8(ShiftOp::Srl, R8::HlIndirect, &mut state);
 
         assert_eq!(state.read(0xFA00), 0b0100_1010); // 0x4A - bit 7 becomes 0
         assert!(!state.flag_z());
@@ -5341,7 +3317,7 @@ mod tests {
         state.set_hl(0xFB00);
         state.write(0xFB00, 0b1111_1110); // 0xFE - negative number
 
-        srl_hl_indirect(&mut state);
+        shift_r8(ShiftOp::Srl, R8::HlIndirect, &mut state);
 
         assert_eq!(state.read(0xFB00), 0b0111_1111); // 0x7F - bit 7 becomes 0 (unlike SRA)
         assert!(!state.flag_z());
@@ -5356,7 +3332,7 @@ mod tests {
         state.set_hl(0xFC00);
         state.write(0xFC00, 0b0101_0101); // 0x55
 
-        srl_hl_indirect(&mut state);
+        shift_r8(ShiftOp::Srl, R8::HlIndirect, &mut state);
 
         assert_eq!(state.read(0xFC00), 0b0010_1010); // 0x2A - bit 0 shifted out
         assert!(!state.flag_z());
@@ -5371,7 +3347,7 @@ mod tests {
         state.set_hl(0xFD00);
         state.write(0xFD00, 0x01); // Only bit 0 set
 
-        srl_hl_indirect(&mut state);
+        shift_r8(ShiftOp::Srl, R8::HlIndirect, &mut state);
 
         assert_eq!(state.read(0xFD00), 0x00); // Shifted to 0
         assert!(state.flag_z());
@@ -5386,7 +3362,7 @@ mod tests {
         state.set_hl(0xFE00);
         state.write(0xFE00, 0xFF);
 
-        srl_hl_indirect(&mut state);
+        shift_r8(ShiftOp::Srl, R8::HlIndirect, &mut state);
 
         assert_eq!(state.read(0xFE00), 0x7F); // 0xFF >> 1 = 0x7F (bit 7 becomes 0)
         assert!(!state.flag_z());
@@ -5469,7 +3445,7 @@ mod tests {
         let value = 0xFF;
         for bit in 0..8 {
             let result = res_bit(value, bit);
-            assert_eq!(result, 0xFF & !(1 << bit));
+            assert_eq!(result, !(1 << bit));
         }
     }
 
@@ -5508,7 +3484,7 @@ mod tests {
         let mut state = GameBoy::<FlatMemory>::new();
         state.a = 0b0100_1010; // 0x4A
 
-        rrc_a(&mut state);
+        shift_r8(ShiftOp::Rrc, R8::A, &mut state);
 
         assert_eq!(state.a, 0b0010_0101); // 0x25
         assert!(!state.flag_z());
@@ -5522,7 +3498,7 @@ mod tests {
         let mut state = GameBoy::<FlatMemory>::new();
         state.b = 0b1100_1011; // 0xCB
 
-        rrc_b(&mut state);
+        shift_r8(ShiftOp::Rrc, R8::B, &mut state);
 
         assert_eq!(state.b, 0b1110_0101); // 0xE5
         assert!(!state.flag_z());
@@ -5536,7 +3512,7 @@ mod tests {
         let mut state = GameBoy::<FlatMemory>::new();
         state.c = 0x00;
 
-        rrc_c(&mut state);
+        shift_r8(ShiftOp::Rrc, R8::C, &mut state);
 
         assert_eq!(state.c, 0x00);
         assert!(state.flag_z()); // Result is zero
@@ -5550,7 +3526,7 @@ mod tests {
         let mut state = GameBoy::<FlatMemory>::new();
         state.d = 0x01; // 0b0000_0001
 
-        rrc_d(&mut state);
+        shift_r8(ShiftOp::Rrc, R8::D, &mut state);
 
         assert_eq!(state.d, 0x80); // 0b1000_0000 - bit 0 wraps to bit 7
         assert!(!state.flag_z());
@@ -5564,31 +3540,31 @@ mod tests {
         let mut state = GameBoy::<FlatMemory>::new();
 
         state.a = 0x80;
-        rrc_a(&mut state);
+        shift_r8(ShiftOp::Rrc, R8::A, &mut state);
         assert_eq!(state.a, 0x40);
 
         state.b = 0x80;
-        rrc_b(&mut state);
+        shift_r8(ShiftOp::Rrc, R8::B, &mut state);
         assert_eq!(state.b, 0x40);
 
         state.c = 0x80;
-        rrc_c(&mut state);
+        shift_r8(ShiftOp::Rrc, R8::C, &mut state);
         assert_eq!(state.c, 0x40);
 
         state.d = 0x80;
-        rrc_d(&mut state);
+        shift_r8(ShiftOp::Rrc, R8::D, &mut state);
         assert_eq!(state.d, 0x40);
 
         state.e = 0x80;
-        rrc_e(&mut state);
+        shift_r8(ShiftOp::Rrc, R8::E, &mut state);
         assert_eq!(state.e, 0x40);
 
         state.h = 0x80;
-        rrc_h(&mut state);
+        shift_r8(ShiftOp::Rrc, R8::H, &mut state);
         assert_eq!(state.h, 0x40);
 
         state.l = 0x80;
-        rrc_l(&mut state);
+        shift_r8(ShiftOp::Rrc, R8::L, &mut state);
         assert_eq!(state.l, 0x40);
     }
 
@@ -5597,7 +3573,7 @@ mod tests {
         let mut state = GameBoy::<FlatMemory>::new();
         state.a = 0xFF;
 
-        rrc_a(&mut state);
+        shift_r8(ShiftOp::Rrc, R8::A, &mut state);
 
         assert_eq!(state.a, 0xFF); // All bits rotate, stays same
         assert!(!state.flag_z());
@@ -5640,7 +3616,7 @@ mod tests {
         state.a = 0b0100_1010; // 0x4A
         state.set_flag_c(false);
 
-        rl_a(&mut state);
+        shift_r8(ShiftOp::Rl, R8::A, &mut state);
 
         assert_eq!(state.a, 0b1001_0100); // 0x94
         assert!(!state.flag_z());
@@ -5655,7 +3631,7 @@ mod tests {
         state.b = 0b0100_1010; // 0x4A
         state.set_flag_c(true);
 
-        rl_b(&mut state);
+        shift_r8(ShiftOp::Rl, R8::B, &mut state);
 
         assert_eq!(state.b, 0b1001_0101); // 0x95 (carry flag becomes bit 0)
         assert!(!state.flag_z());
@@ -5670,7 +3646,7 @@ mod tests {
         state.c = 0b1100_1010; // 0xCA
         state.set_flag_c(false);
 
-        rl_c(&mut state);
+        shift_r8(ShiftOp::Rl, R8::C, &mut state);
 
         assert_eq!(state.c, 0b1001_0100); // 0x94
         assert!(!state.flag_z());
@@ -5685,7 +3661,7 @@ mod tests {
         state.d = 0x00;
         state.set_flag_c(false);
 
-        rl_d(&mut state);
+        shift_r8(ShiftOp::Rl, R8::D, &mut state);
 
         assert_eq!(state.d, 0x00);
         assert!(state.flag_z()); // Result is zero
@@ -5700,7 +3676,7 @@ mod tests {
         state.e = 0x80; // 0b1000_0000
         state.set_flag_c(true);
 
-        rl_e(&mut state);
+        shift_r8(ShiftOp::Rl, R8::E, &mut state);
 
         assert_eq!(state.e, 0x01); // 0b0000_0001 (carry in becomes bit 0)
         assert!(!state.flag_z());
@@ -5715,37 +3691,37 @@ mod tests {
 
         state.a = 0x01;
         state.set_flag_c(false);
-        rl_a(&mut state);
+        shift_r8(ShiftOp::Rl, R8::A, &mut state);
         assert_eq!(state.a, 0x02);
 
         state.b = 0x01;
         state.set_flag_c(false);
-        rl_b(&mut state);
+        shift_r8(ShiftOp::Rl, R8::B, &mut state);
         assert_eq!(state.b, 0x02);
 
         state.c = 0x01;
         state.set_flag_c(false);
-        rl_c(&mut state);
+        shift_r8(ShiftOp::Rl, R8::C, &mut state);
         assert_eq!(state.c, 0x02);
 
         state.d = 0x01;
         state.set_flag_c(false);
-        rl_d(&mut state);
+        shift_r8(ShiftOp::Rl, R8::D, &mut state);
         assert_eq!(state.d, 0x02);
 
         state.e = 0x01;
         state.set_flag_c(false);
-        rl_e(&mut state);
+        shift_r8(ShiftOp::Rl, R8::E, &mut state);
         assert_eq!(state.e, 0x02);
 
         state.h = 0x01;
         state.set_flag_c(false);
-        rl_h(&mut state);
+        shift_r8(ShiftOp::Rl, R8::H, &mut state);
         assert_eq!(state.h, 0x02);
 
         state.l = 0x01;
         state.set_flag_c(false);
-        rl_l(&mut state);
+        shift_r8(ShiftOp::Rl, R8::L, &mut state);
         assert_eq!(state.l, 0x02);
     }
 
@@ -5755,7 +3731,7 @@ mod tests {
         state.a = 0xFF;
         state.set_flag_c(true);
 
-        rl_a(&mut state);
+        shift_r8(ShiftOp::Rl, R8::A, &mut state);
 
         assert_eq!(state.a, 0xFF); // All bits set, carry in becomes bit 0
         assert!(!state.flag_z());
@@ -5899,7 +3875,7 @@ mod tests {
         state.set_hl(0x1000);
         state.set_bc(0x0234);
 
-        add_hl_bc(&mut state);
+        add_hl_r16(R16::BC, &mut state);
 
         assert_eq!(state.hl(), 0x1234);
         assert!(!state.flag_n());
@@ -5913,7 +3889,7 @@ mod tests {
         state.set_hl(0x2000);
         state.set_de(0x0500);
 
-        add_hl_de(&mut state);
+        add_hl_r16(R16::DE, &mut state);
 
         assert_eq!(state.hl(), 0x2500);
         assert!(!state.flag_n());
@@ -5927,7 +3903,7 @@ mod tests {
         state.set_hl(0x0FFF);
         state.set_bc(0x0001);
 
-        add_hl_bc(&mut state);
+        add_hl_r16(R16::BC, &mut state);
 
         assert_eq!(state.hl(), 0x1000);
         assert!(!state.flag_n());
@@ -5941,7 +3917,7 @@ mod tests {
         state.set_hl(0xFFFF);
         state.set_de(0x0001);
 
-        add_hl_de(&mut state);
+        add_hl_r16(R16::DE, &mut state);
 
         assert_eq!(state.hl(), 0x0000);
         assert!(!state.flag_n());
@@ -5955,7 +3931,7 @@ mod tests {
         state.set_hl(0xFFFF);
         state.set_bc(0xFFFF);
 
-        add_hl_bc(&mut state);
+        add_hl_r16(R16::BC, &mut state);
 
         assert_eq!(state.hl(), 0xFFFE);
         assert!(!state.flag_n());
@@ -5968,7 +3944,7 @@ mod tests {
         let mut state = GameBoy::<FlatMemory>::new();
         state.set_hl(0x1234);
 
-        add_hl_hl(&mut state);
+        add_hl_r16(R16::HL, &mut state);
 
         assert_eq!(state.hl(), 0x2468);
         assert!(!state.flag_n());
@@ -5982,7 +3958,7 @@ mod tests {
         state.set_hl(0x1000);
         state.set_sp(0x0200);
 
-        add_hl_sp(&mut state);
+        add_hl_r16(R16::SP, &mut state);
 
         assert_eq!(state.hl(), 0x1200);
         assert!(!state.flag_n());
@@ -5997,7 +3973,7 @@ mod tests {
         state.set_bc(0x1000);
         state.set_flag_z(true); // Set Z flag
 
-        add_hl_bc(&mut state);
+        add_hl_r16(R16::BC, &mut state);
 
         assert_eq!(state.hl(), 0x2000);
         assert!(state.flag_z()); // Z flag should be preserved
@@ -6010,7 +3986,7 @@ mod tests {
         state.set_hl(0x0800);
         state.set_de(0x0800);
 
-        add_hl_de(&mut state);
+        add_hl_r16(R16::DE, &mut state);
 
         assert_eq!(state.hl(), 0x1000);
         assert!(!state.flag_n());
@@ -6024,7 +4000,7 @@ mod tests {
         state.set_hl(0x07FF);
         state.set_bc(0x0800);
 
-        add_hl_bc(&mut state);
+        add_hl_r16(R16::BC, &mut state);
 
         assert_eq!(state.hl(), 0x0FFF);
         assert!(!state.flag_n());
@@ -6038,7 +4014,7 @@ mod tests {
         state.set_hl(0x8000);
         state.set_de(0x8000);
 
-        add_hl_de(&mut state);
+        add_hl_r16(R16::DE, &mut state);
 
         assert_eq!(state.hl(), 0x0000);
         assert!(!state.flag_n());
@@ -6052,7 +4028,7 @@ mod tests {
         state.a = 0b1001_0100; // 0x94
         state.set_flag_c(false);
 
-        rr_a(&mut state);
+        shift_r8(ShiftOp::Rr, R8::A, &mut state);
 
         assert_eq!(state.a, 0b0100_1010); // 0x4A
         assert!(!state.flag_z());
@@ -6067,7 +4043,7 @@ mod tests {
         state.b = 0b1001_0100; // 0x94
         state.set_flag_c(true);
 
-        rr_b(&mut state);
+        shift_r8(ShiftOp::Rr, R8::B, &mut state);
 
         assert_eq!(state.b, 0b1100_1010); // 0xCA (carry flag becomes bit 7)
         assert!(!state.flag_z());
@@ -6082,7 +4058,7 @@ mod tests {
         state.c = 0b1001_0101; // 0x95
         state.set_flag_c(false);
 
-        rr_c(&mut state);
+        shift_r8(ShiftOp::Rr, R8::C, &mut state);
 
         assert_eq!(state.c, 0b0100_1010); // 0x4A
         assert!(!state.flag_z());
@@ -6097,7 +4073,7 @@ mod tests {
         state.d = 0x00;
         state.set_flag_c(false);
 
-        rr_d(&mut state);
+        shift_r8(ShiftOp::Rr, R8::D, &mut state);
 
         assert_eq!(state.d, 0x00);
         assert!(state.flag_z()); // Result is zero
@@ -6112,7 +4088,7 @@ mod tests {
         state.e = 0x01; // 0b0000_0001
         state.set_flag_c(true);
 
-        rr_e(&mut state);
+        shift_r8(ShiftOp::Rr, R8::E, &mut state);
 
         assert_eq!(state.e, 0x80); // 0b1000_0000 (carry in becomes bit 7)
         assert!(!state.flag_z());
@@ -6127,37 +4103,37 @@ mod tests {
 
         state.a = 0x80;
         state.set_flag_c(false);
-        rr_a(&mut state);
+        shift_r8(ShiftOp::Rr, R8::A, &mut state);
         assert_eq!(state.a, 0x40);
 
         state.b = 0x80;
         state.set_flag_c(false);
-        rr_b(&mut state);
+        shift_r8(ShiftOp::Rr, R8::B, &mut state);
         assert_eq!(state.b, 0x40);
 
         state.c = 0x80;
         state.set_flag_c(false);
-        rr_c(&mut state);
+        shift_r8(ShiftOp::Rr, R8::C, &mut state);
         assert_eq!(state.c, 0x40);
 
         state.d = 0x80;
         state.set_flag_c(false);
-        rr_d(&mut state);
+        shift_r8(ShiftOp::Rr, R8::D, &mut state);
         assert_eq!(state.d, 0x40);
 
         state.e = 0x80;
         state.set_flag_c(false);
-        rr_e(&mut state);
+        shift_r8(ShiftOp::Rr, R8::E, &mut state);
         assert_eq!(state.e, 0x40);
 
         state.h = 0x80;
         state.set_flag_c(false);
-        rr_h(&mut state);
+        shift_r8(ShiftOp::Rr, R8::H, &mut state);
         assert_eq!(state.h, 0x40);
 
         state.l = 0x80;
         state.set_flag_c(false);
-        rr_l(&mut state);
+        shift_r8(ShiftOp::Rr, R8::L, &mut state);
         assert_eq!(state.l, 0x40);
     }
 
@@ -6167,7 +4143,7 @@ mod tests {
         state.a = 0xFF;
         state.set_flag_c(true);
 
-        rr_a(&mut state);
+        shift_r8(ShiftOp::Rr, R8::A, &mut state);
 
         assert_eq!(state.a, 0xFF); // All bits set, carry in becomes bit 7
         assert!(!state.flag_z());
@@ -6228,7 +4204,7 @@ mod tests {
         state.write(0x1000, 0x10); // Jump forward by 16 bytes
         state.set_flag_z(false); // Z flag clear
 
-        jr_nz(&mut state);
+        jr_cond(Cond::NZ, &mut state);
 
         // Should jump: PC = 0x1000 + 1 + 0x10 = 0x1011
         assert_eq!(state.pc, 0x1011);
@@ -6241,7 +4217,7 @@ mod tests {
         state.write(0x1000, 0x10); // Jump forward by 16 bytes
         state.set_flag_z(true); // Z flag set
 
-        jr_nz(&mut state);
+        jr_cond(Cond::NZ, &mut state);
 
         // Should not jump: PC = 0x1000 + 1 = 0x1001
         assert_eq!(state.pc, 0x1001);
@@ -6254,7 +4230,7 @@ mod tests {
         state.write(0x1000, 0xFE); // Jump backward by 2 bytes (-2)
         state.set_flag_z(false); // Z flag clear
 
-        jr_nz(&mut state);
+        jr_cond(Cond::NZ, &mut state);
 
         // Should jump: PC = 0x1000 + 1 + (-2) = 0x0FFF
         assert_eq!(state.pc, 0x0FFF);
@@ -6267,7 +4243,7 @@ mod tests {
         state.write(0x1000, 0x00); // No offset
         state.set_flag_z(false); // Z flag clear
 
-        jr_nz(&mut state);
+        jr_cond(Cond::NZ, &mut state);
 
         // Should "jump" to same location: PC = 0x1000 + 1 + 0 = 0x1001
         assert_eq!(state.pc, 0x1001);
@@ -6737,7 +4713,7 @@ mod tests {
         state.pc = 0xABCD;
         state.sp = 0xFFFE;
 
-        rst_20(&mut state);
+        rst(0x20, &mut state);
 
         // PC should be at RST vector 0x0020
         assert_eq!(state.pc, 0x0020);
@@ -6754,7 +4730,7 @@ mod tests {
         state.pc = 0x1234;
         state.sp = 0xFFFE;
 
-        rst_28(&mut state);
+        rst(0x28, &mut state);
 
         // PC should be at RST vector 0x0028
         assert_eq!(state.pc, 0x0028);
@@ -6926,7 +4902,7 @@ mod tests {
         state.write(0xFFF0, 0xF0); // Low byte (F)
         state.write(0xFFF1, 0x12); // High byte (A)
 
-        pop_af(&mut state);
+        pop_r16(R16Stack::AF, &mut state);
 
         assert_eq!(state.f, 0xF0);
         assert_eq!(state.a, 0x12);
@@ -6942,7 +4918,7 @@ mod tests {
         state.write(0x2000, 0xF0); // F register
         state.write(0x2001, 0x42); // A register
 
-        pop_af(&mut state);
+        pop_r16(R16Stack::AF, &mut state);
 
         assert_eq!(state.a, 0x42);
         assert_eq!(state.f, 0xF0);
@@ -7343,7 +5319,7 @@ mod tests {
         state.a = 0x42;
         state.f = 0xF0;
 
-        push_af(&mut state);
+        push_r16(R16Stack::AF, &mut state);
 
         // SP decremented by 2
         assert_eq!(state.sp, 0x2FFE);
@@ -7360,7 +5336,7 @@ mod tests {
         state.pc = 0x1234;
         state.sp = 0xFFFE;
 
-        rst_30(&mut state);
+        rst(0x30, &mut state);
 
         // PC should be at RST vector 0x0030
         assert_eq!(state.pc, 0x0030);
@@ -7377,7 +5353,7 @@ mod tests {
         state.pc = 0xABCD;
         state.sp = 0xFFFE;
 
-        rst_38(&mut state);
+        rst(0x38, &mut state);
 
         // PC should be at RST vector 0x0038
         assert_eq!(state.pc, 0x0038);
@@ -43,4 +43,21 @@ pub const OBP1: u16 = 0xFF49; // Object palette 1
 pub const WY: u16 = 0xFF4A; // Window Y position
 pub const WX: u16 = 0xFF4B; // Window X position
 
+// Boot ROM
+pub const BOOT: u16 = 0xFF50; // Boot ROM disable (write non-zero to unmap)
+
+// CGB registers
+pub const KEY1: u16 = 0xFF4D; // Speed switch
+pub const VBK: u16 = 0xFF4F; // VRAM bank
+pub const HDMA1: u16 = 0xFF51; // VRAM DMA source high
+pub const HDMA2: u16 = 0xFF52; // VRAM DMA source low
+pub const HDMA3: u16 = 0xFF53; // VRAM DMA destination high
+pub const HDMA4: u16 = 0xFF54; // VRAM DMA destination low
+pub const HDMA5: u16 = 0xFF55; // VRAM DMA length/mode/start
+pub const BCPS: u16 = 0xFF68; // Background palette index
+pub const BCPD: u16 = 0xFF69; // Background palette data
+pub const OCPS: u16 = 0xFF6A; // Object palette index
+pub const OCPD: u16 = 0xFF6B; // Object palette data
+pub const SVBK: u16 = 0xFF70; // WRAM bank
+
 pub const IE: u16 = 0xFFFF; // Interrupt enable
@@ -8,6 +8,15 @@
 /// Bit 2: P12 - Up or Select (0=pressed)
 /// Bit 1: P11 - Left or B (0=pressed)
 /// Bit 0: P10 - Right or A (0=pressed)
+///
+/// The joypad interrupt is requested when any selected input line (P10-P13)
+/// goes from high to low, whether because a button was pressed or because
+/// a write to P1 selected a row with a button already held.
+///
+/// On a Super Game Boy, P1 writes also carry command packets to the SNES
+/// (see `sgb`), and with several controllers enabled (MLT_REQ) the lower
+/// nibble reads the selected controller ID while no row is selected.
+use crate::sgb::{Packet, PacketReceiver};
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum Button {
@@ -21,17 +30,131 @@ pub enum Button {
     Start,
 }
 
+impl Button {
+    /// All buttons, in index order
+    pub const ALL: [Button; 8] = [
+        Button::Right,
+        Button::Left,
+        Button::Up,
+        Button::Down,
+        Button::A,
+        Button::B,
+        Button::Select,
+        Button::Start,
+    ];
+
+    /// Button for an index (0 = Right, 1 = Left, 2 = Up, 3 = Down,
+    /// 4 = A, 5 = B, 6 = Select, 7 = Start)
+    pub fn from_index(index: u8) -> Option<Button> {
+        Self::ALL.get(index as usize).copied()
+    }
+
+    /// Index of this button (see `from_index`)
+    pub fn to_index(self) -> u8 {
+        self as u8
+    }
+
+    /// Bit of this button in a `JoypadState`
+    fn mask(self) -> u8 {
+        1 << self.to_index()
+    }
+}
+
+/// Snapshot of the pressed buttons, one bit per button index
+///
+/// Bits 0-3 (Right, Left, Up, Down) and 4-7 (A, B, Select, Start) line up
+/// with P10-P13 of the direction and action rows.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Hash)]
+pub struct JoypadState(u8);
+
+impl JoypadState {
+    /// No buttons pressed
+    pub const NONE: JoypadState = JoypadState(0);
+
+    pub fn from_bits(bits: u8) -> Self {
+        JoypadState(bits)
+    }
+
+    pub fn bits(self) -> u8 {
+        self.0
+    }
+
+    /// Check if a button is pressed
+    pub fn contains(self, button: Button) -> bool {
+        self.0 & button.mask() != 0
+    }
+
+    /// This state with a button pressed
+    pub fn with(self, button: Button) -> Self {
+        JoypadState(self.0 | button.mask())
+    }
+
+    /// This state with a button released
+    pub fn without(self, button: Button) -> Self {
+        JoypadState(self.0 & !button.mask())
+    }
+}
+
+impl FromIterator<Button> for JoypadState {
+    fn from_iter<I: IntoIterator<Item = Button>>(buttons: I) -> Self {
+        buttons
+            .into_iter()
+            .fold(JoypadState::NONE, JoypadState::with)
+    }
+}
+
+/// What the game sees when opposing directions (Left+Right or Up+Down) are
+/// held at the same time, which a real D-pad cannot do
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum OpposingDirections {
+    /// Report both directions
+    #[default]
+    Allow,
+    /// Report only the most recently pressed direction
+    LastWins,
+    /// Report neither direction
+    Neutral,
+}
+
+impl OpposingDirections {
+    /// All policies with their names
+    pub const ALL: [(&'static str, OpposingDirections); 3] = [
+        ("allow", OpposingDirections::Allow),
+        ("last", OpposingDirections::LastWins),
+        ("neutral", OpposingDirections::Neutral),
+    ];
+
+    /// Look up a policy by name (case-insensitive)
+    pub fn from_name(name: &str) -> Option<OpposingDirections> {
+        Self::ALL
+            .iter()
+            .find(|(policy, _)| policy.eq_ignore_ascii_case(name))
+            .map(|(_, policy)| *policy)
+    }
+}
+
+/// Opposing direction pairs
+const OPPOSING_PAIRS: [(Button, Button); 2] =
+    [(Button::Left, Button::Right), (Button::Up, Button::Down)];
+
 pub struct Joypad {
     select_action: bool,
     select_direction: bool,
-    right: bool,
-    left: bool,
-    up: bool,
-    down: bool,
-    a: bool,
-    b: bool,
-    select: bool,
-    start: bool,
+    /// Buttons held by the player
+    held: JoypadState,
+    /// How simultaneous opposing directions are reported
+    opposing_directions: OpposingDirections,
+    /// Most recently pressed direction of each opposing pair
+    /// (None when both were pressed at once)
+    last_direction: [Option<Button>; 2],
+    /// SGB command packet decoder (None when not running on an SGB)
+    sgb_packets: Option<PacketReceiver>,
+    /// SGB multiplayer: number of controllers (1, 2 or 4) and the selected one
+    players: u8,
+    player: u8,
+    /// Set when a selected input line goes low; the system requests the
+    /// joypad interrupt and clears it
+    pub interrupt: bool,
 }
 
 impl Joypad {
@@ -39,45 +162,102 @@ impl Joypad {
         Self {
             select_action: true,
             select_direction: true,
-            right: false,
-            left: false,
-            up: false,
-            down: false,
-            a: false,
-            b: false,
-            select: false,
-            start: false,
+            held: JoypadState::NONE,
+            opposing_directions: OpposingDirections::default(),
+            last_direction: [None; 2],
+            sgb_packets: None,
+            players: 1,
+            player: 0,
+            interrupt: false,
         }
     }
 
-    pub fn press(&mut self, button: Button) {
-        match button {
-            Button::Right => self.right = true,
-            Button::Left => self.left = true,
-            Button::Up => self.up = true,
-            Button::Down => self.down = true,
-            Button::A => self.a = true,
-            Button::B => self.b = true,
-            Button::Select => self.select = true,
-            Button::Start => self.start = true,
+    /// Decode SGB command packets from P1 writes
+    pub fn enable_sgb(&mut self) {
+        self.sgb_packets = Some(PacketReceiver::new());
+    }
+
+    /// Take the next SGB command packet sent by the game
+    pub fn take_sgb_packet(&mut self) -> Option<Packet> {
+        self.sgb_packets.as_mut()?.take_packet()
+    }
+
+    /// Set the number of SGB controllers (MLT_REQ), selecting the first one
+    pub fn set_players(&mut self, players: u8) {
+        if players != self.players {
+            self.players = players;
+            self.player = 0;
         }
     }
 
+    pub fn press(&mut self, button: Button) {
+        self.set_state(self.held.with(button));
+    }
+
     pub fn release(&mut self, button: Button) {
-        match button {
-            Button::Right => self.right = false,
-            Button::Left => self.left = false,
-            Button::Up => self.up = false,
-            Button::Down => self.down = false,
-            Button::A => self.a = false,
-            Button::B => self.b = false,
-            Button::Select => self.select = false,
-            Button::Start => self.start = false,
+        self.set_state(self.held.without(button));
+    }
+
+    /// Buttons currently held
+    pub fn state(&self) -> JoypadState {
+        self.held
+    }
+
+    /// Replace the held buttons with a snapshot (e.g. one per frame)
+    pub fn set_state(&mut self, state: JoypadState) {
+        let lines = self.lines();
+
+        let pressed = JoypadState(state.0 & !self.held.0);
+        for (last, (first, second)) in self.last_direction.iter_mut().zip(OPPOSING_PAIRS) {
+            match (pressed.contains(first), pressed.contains(second)) {
+                (true, true) => *last = None,
+                (true, false) => *last = Some(first),
+                (false, true) => *last = Some(second),
+                (false, false) => {}
+            }
         }
+        self.held = state;
+
+        self.detect_falling_edge(lines);
+    }
+
+    pub fn opposing_directions(&self) -> OpposingDirections {
+        self.opposing_directions
+    }
+
+    /// Set how simultaneous opposing directions are reported
+    pub fn set_opposing_directions(&mut self, policy: OpposingDirections) {
+        let lines = self.lines();
+        self.opposing_directions = policy;
+        self.detect_falling_edge(lines);
+    }
+
+    /// Buttons the game sees after applying the opposing direction policy
+    fn effective_state(&self) -> JoypadState {
+        let mut state = self.held;
+
+        for (last, (first, second)) in self.last_direction.iter().zip(OPPOSING_PAIRS) {
+            if !state.contains(first) || !state.contains(second) {
+                continue;
+            }
+
+            match (self.opposing_directions, last) {
+                (OpposingDirections::Allow, _) => {}
+                (OpposingDirections::LastWins, Some(winner)) => {
+                    let loser = if *winner == first { second } else { first };
+                    state = state.without(loser);
+                }
+                (OpposingDirections::LastWins, None) | (OpposingDirections::Neutral, _) => {
+                    state = state.without(first).without(second);
+                }
+            }
+        }
+
+        state
     }
 
     pub fn read(&self) -> u8 {
-        let mut value = 0xCF;
+        let mut value = 0xC0;
 
         if !self.select_direction {
             value |= 0x10;
@@ -87,26 +267,65 @@ impl Joypad {
             value |= 0x20;
         }
 
+        // With no row selected, the lower nibble holds the controller ID
+        // (0xF for the first controller, 0xE for the second, ...)
+        if !self.select_direction && !self.select_action {
+            return value | (0x0F & !self.player);
+        }
+
+        value | self.lines()
+    }
+
+    /// Check if any selected input line is low (wakes the CPU from STOP)
+    pub fn has_input(&self) -> bool {
+        self.lines() != 0x0F
+    }
+
+    /// State of the input lines P10-P13 (0 = pressed button in a selected row)
+    fn lines(&self) -> u8 {
+        let mut value = 0x0F;
+
+        // Only the first controller has buttons attached
+        if self.player != 0 {
+            return value;
+        }
+
+        let buttons = self.effective_state().bits();
+
         if self.select_direction {
-            if self.down { value &= !0x08; }
-            if self.up { value &= !0x04; }
-            if self.left { value &= !0x02; }
-            if self.right { value &= !0x01; }
+            value &= !(buttons & 0x0F);
         }
 
         if self.select_action {
-            if self.start { value &= !0x08; }
-            if self.select { value &= !0x04; }
-            if self.b { value &= !0x02; }
-            if self.a { value &= !0x01; }
+            value &= !(buttons >> 4);
         }
 
         value
     }
 
+    /// Request the joypad interrupt if a line that was high is now low
+    fn detect_falling_edge(&mut self, previous_lines: u8) {
+        if previous_lines & !self.lines() != 0 {
+            self.interrupt = true;
+        }
+    }
+
     pub fn write(&mut self, value: u8) {
+        let was_action_selected = self.select_action;
+        let lines = self.lines();
+
         self.select_action = (value & 0x20) == 0;
         self.select_direction = (value & 0x10) == 0;
+
+        if let Some(receiver) = &mut self.sgb_packets {
+            // Releasing P15 selects the next controller
+            if was_action_selected && !self.select_action && self.players > 1 {
+                self.player = (self.player + 1) % self.players;
+            }
+            receiver.write(value);
+        }
+
+        self.detect_falling_edge(lines);
     }
 }
 
@@ -115,3 +334,113 @@ impl Default for Joypad {
         Self::new()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_press_in_selected_row_requests_interrupt() {
+        let mut joypad = Joypad::new();
+        joypad.write(0x20); // Select directions
+
+        joypad.press(Button::A);
+        assert!(!joypad.interrupt, "action row is not selected");
+
+        joypad.press(Button::Down);
+        assert!(joypad.interrupt);
+        assert_eq!(joypad.read(), 0xE7);
+    }
+
+    #[test]
+    fn test_release_does_not_request_interrupt() {
+        let mut joypad = Joypad::new();
+        joypad.write(0x10); // Select actions
+        joypad.press(Button::Start);
+        joypad.interrupt = false;
+
+        joypad.release(Button::Start);
+        assert!(!joypad.interrupt);
+    }
+
+    #[test]
+    fn test_selecting_row_with_held_button_requests_interrupt() {
+        let mut joypad = Joypad::new();
+        joypad.write(0x30);
+        joypad.press(Button::B);
+        assert!(!joypad.interrupt, "no row selected");
+
+        joypad.write(0x10);
+        assert!(joypad.interrupt);
+        assert!(joypad.has_input());
+
+        // Deselecting raises the line again, no new edge
+        joypad.interrupt = false;
+        joypad.write(0x30);
+        assert!(!joypad.interrupt);
+        assert!(!joypad.has_input());
+    }
+
+    #[test]
+    fn test_button_index_roundtrip() {
+        for (index, button) in Button::ALL.into_iter().enumerate() {
+            assert_eq!(button.to_index(), index as u8);
+            assert_eq!(Button::from_index(index as u8), Some(button));
+        }
+        assert_eq!(Button::from_index(8), None);
+    }
+
+    #[test]
+    fn test_set_state_snapshot() {
+        let mut joypad = Joypad::new();
+        joypad.write(0x10); // Select actions
+
+        let state: JoypadState = [Button::A, Button::Start, Button::Up].into_iter().collect();
+        joypad.set_state(state);
+        assert_eq!(joypad.state().bits(), 0x94);
+        assert!(joypad.interrupt);
+        assert_eq!(joypad.read(), 0xD6);
+
+        joypad.set_state(JoypadState::NONE);
+        assert_eq!(joypad.read(), 0xDF);
+    }
+
+    #[test]
+    fn test_opposing_directions_policies() {
+        let mut joypad = Joypad::new();
+        joypad.write(0x20); // Select directions
+        joypad.press(Button::Left);
+        joypad.press(Button::Right);
+
+        assert_eq!(joypad.read() & 0x03, 0x00, "both reported");
+
+        joypad.set_opposing_directions(OpposingDirections::LastWins);
+        assert_eq!(joypad.read() & 0x03, 0x02, "only Right reported");
+
+        joypad.set_opposing_directions(OpposingDirections::Neutral);
+        assert_eq!(joypad.read() & 0x03, 0x03, "neither reported");
+
+        // Releasing one side reports the other again
+        joypad.release(Button::Right);
+        assert_eq!(joypad.read() & 0x03, 0x01);
+    }
+
+    #[test]
+    fn test_last_wins_with_simultaneous_press() {
+        let mut joypad = Joypad::new();
+        joypad.set_opposing_directions(OpposingDirections::LastWins);
+        joypad.write(0x20);
+
+        joypad.set_state(JoypadState::NONE.with(Button::Up).with(Button::Down));
+        assert_eq!(
+            joypad.read() & 0x0C,
+            0x0C,
+            "no winner when pressed together"
+        );
+
+        joypad.press(Button::A);
+        joypad.release(Button::Up);
+        joypad.press(Button::Up);
+        assert_eq!(joypad.read() & 0x0C, 0x08, "Up pressed last");
+    }
+}
@@ -1,9 +1,21 @@
 // Core Game Boy emulator library
+#[cfg(feature = "archive")]
+pub mod archive;
 pub mod cartridge;
+pub mod crc;
+pub mod decode;
+pub mod harness;
+pub mod hdma;
 pub mod instructions;
 pub mod io;
 pub mod joypad;
 pub mod memory;
 pub mod mmu;
+pub mod model;
+pub mod oam_bug;
+pub mod patch;
 pub mod ppu;
+pub mod sgb;
 pub mod system;
+pub mod timer;
+pub mod video;
@@ -9,6 +9,15 @@ pub trait Memory {
 
     /// Write a byte to memory
     fn write(&mut self, addr: u16, value: u8);
+
+    /// OAM (0xFE00-0xFE9F), for changes the PPU makes without a bus write
+    fn oam_mut(&mut self) -> &mut [u8];
+
+    /// VRAM and OAM for the PPU to render from, or None if scanlines
+    /// aren't rendered from this memory
+    fn video_memory(&self) -> Option<(&[u8], &[u8])> {
+        None
+    }
 }
 
 /// Simple flat memory implementation for testing
@@ -42,4 +51,8 @@ impl Memory for FlatMemory {
     fn write(&mut self, addr: u16, value: u8) {
         self.mem[addr as usize] = value;
     }
+
+    fn oam_mut(&mut self) -> &mut [u8] {
+        &mut self.mem[0xFE00..0xFEA0]
+    }
 }
@@ -4,18 +4,27 @@
 /// - ROM banking (MBC1, MBC3, MBC5)
 /// - RAM banking
 /// - Memory-mapped I/O
-/// - Video RAM (VRAM)
-/// - Work RAM (WRAM)
+/// - Video RAM (VRAM), banked on CGB
+/// - Work RAM (WRAM), banked on CGB
 /// - High RAM (HRAM)
 /// - Object Attribute Memory (OAM)
+/// - Optional boot ROM, mapped over the cartridge until a write to 0xFF50
 use crate::cartridge::{Cartridge, CartridgeType};
+use crate::io::{BOOT, SVBK, VBK};
+use crate::model::Model;
+
+/// Boot ROM sizes: DMG/SGB boot ROMs cover 0x0000-0x00FF, CGB boot ROMs
+/// cover 0x0000-0x08FF except for the cartridge header at 0x0100-0x01FF
+pub const DMG_BOOT_ROM_SIZE: usize = 0x100;
+pub const CGB_BOOT_ROM_SIZE: usize = 0x900;
 
 /// Game Boy Memory Map:
-/// 0x0000-0x3FFF : ROM Bank 0 (16KB) - Fixed
+/// 0x0000-0x3FFF : ROM Bank 0 (16KB) - Fixed (boot ROM mapped on top at power on)
 /// 0x4000-0x7FFF : ROM Bank 1-N (16KB) - Switchable
-/// 0x8000-0x9FFF : VRAM (8KB)
+/// 0x8000-0x9FFF : VRAM (8KB) - Switchable on CGB (VBK)
 /// 0xA000-0xBFFF : External RAM (8KB) - Switchable
-/// 0xC000-0xDFFF : Work RAM (8KB)
+/// 0xC000-0xCFFF : Work RAM Bank 0 (4KB)
+/// 0xD000-0xDFFF : Work RAM Bank 1-7 (4KB) - Switchable on CGB (SVBK)
 /// 0xE000-0xFDFF : Echo RAM (mirror of 0xC000-0xDDFF)
 /// 0xFE00-0xFE9F : OAM - Sprite Attribute Table
 /// 0xFEA0-0xFEFF : Prohibited
@@ -26,6 +35,12 @@ pub struct Mmu {
     /// Cartridge (contains ROM)
     pub cartridge: Cartridge,
 
+    /// Hardware model (selected from the cartridge header)
+    model: Model,
+
+    /// Boot ROM, mapped until the boot ROM disable register is written
+    boot_rom: Option<Vec<u8>>,
+
     /// Current ROM bank (for 0x4000-0x7FFF region)
     rom_bank: usize,
 
@@ -38,11 +53,17 @@ pub struct Mmu {
     /// External RAM (if cartridge has RAM)
     external_ram: Vec<u8>,
 
-    /// Video RAM (8KB)
-    vram: [u8; 0x2000],
+    /// Video RAM (2 banks of 8KB, only bank 0 is used on DMG)
+    vram: Box<[u8; 0x4000]>,
+
+    /// Current VRAM bank (CGB only)
+    vram_bank: usize,
+
+    /// Work RAM (8 banks of 4KB, only banks 0-1 are used on DMG)
+    wram: Box<[u8; 0x8000]>,
 
-    /// Work RAM (8KB)
-    wram: [u8; 0x2000],
+    /// Current WRAM bank for 0xD000-0xDFFF (1-7, CGB only)
+    wram_bank: usize,
 
     /// High RAM (127 bytes)
     hram: [u8; 0x7F],
@@ -63,18 +84,28 @@ pub struct Mmu {
 impl Mmu {
     /// Create a new MMU with the given cartridge
     pub fn new(cartridge: Cartridge) -> Self {
+        let model = Model::for_header(&cartridge.header);
+        Self::with_model(cartridge, model)
+    }
+
+    /// Create a new MMU for a specific hardware model
+    pub fn with_model(cartridge: Cartridge, model: Model) -> Self {
         // Allocate external RAM based on cartridge header
         let ram_size = cartridge.header.ram_size;
         let external_ram = vec![0; ram_size];
 
         Mmu {
             cartridge,
+            model,
+            boot_rom: None,
             rom_bank: 1, // Start with bank 1 for 0x4000-0x7FFF
             ram_bank: 0,
             ram_enabled: false,
             external_ram,
-            vram: [0; 0x2000],
-            wram: [0; 0x2000],
+            vram: Box::new([0; 0x4000]),
+            vram_bank: 0,
+            wram: Box::new([0; 0x8000]),
+            wram_bank: 1,
             hram: [0; 0x7F],
             oam: [0; 0xA0],
             io: [0; 0x80],
@@ -83,11 +114,69 @@ impl Mmu {
         }
     }
 
+    /// Hardware model this MMU was configured for
+    pub fn model(&self) -> Model {
+        self.model
+    }
+
+    /// Map a boot ROM over the start of the cartridge ROM
+    ///
+    /// The image must be 256 bytes on DMG/SGB or 2304 bytes on CGB.
+    pub fn load_boot_rom(&mut self, boot_rom: Vec<u8>) -> Result<(), String> {
+        let expected = if self.model.is_cgb() {
+            CGB_BOOT_ROM_SIZE
+        } else {
+            DMG_BOOT_ROM_SIZE
+        };
+
+        if boot_rom.len() != expected {
+            return Err(format!(
+                "Invalid boot ROM size for {:?}: {} bytes (expected {})",
+                self.model,
+                boot_rom.len(),
+                expected
+            ));
+        }
+
+        self.boot_rom = Some(boot_rom);
+        Ok(())
+    }
+
+    /// Check if the boot ROM is still mapped
+    pub fn is_boot_rom_mapped(&self) -> bool {
+        self.boot_rom.is_some()
+    }
+
+    /// Read from the boot ROM if it is mapped at this address
+    fn read_boot_rom(&self, addr: u16) -> Option<u8> {
+        let boot_rom = self.boot_rom.as_ref()?;
+        match addr {
+            // The cartridge header stays visible to the CGB boot ROM
+            0x0000..=0x00FF | 0x0200..=0x08FF => boot_rom.get(addr as usize).copied(),
+            _ => None,
+        }
+    }
+
+    /// Map a VRAM address to an offset in the current bank
+    fn vram_offset(&self, addr: u16) -> usize {
+        self.vram_bank * 0x2000 + (addr - 0x8000) as usize
+    }
+
+    /// Map a WRAM address (0xC000-0xDFFF) to an offset in the banked array
+    fn wram_offset(&self, addr: u16) -> usize {
+        match addr {
+            0xC000..=0xCFFF => (addr - 0xC000) as usize,
+            _ => self.wram_bank * 0x1000 + (addr - 0xD000) as usize,
+        }
+    }
+
     /// Read a byte from memory
     pub fn read(&self, addr: u16) -> u8 {
         match addr {
-            // ROM Bank 0 (fixed)
-            0x0000..=0x3FFF => self.cartridge.read(addr),
+            // ROM Bank 0 (fixed), boot ROM on top while mapped
+            0x0000..=0x3FFF => self
+                .read_boot_rom(addr)
+                .unwrap_or_else(|| self.cartridge.read(addr)),
 
             // ROM Bank 1-N (switchable)
             0x4000..=0x7FFF => {
@@ -96,7 +185,7 @@ impl Mmu {
             }
 
             // Video RAM
-            0x8000..=0x9FFF => self.vram[(addr - 0x8000) as usize],
+            0x8000..=0x9FFF => self.vram[self.vram_offset(addr)],
 
             // External RAM (cartridge RAM, switchable)
             0xA000..=0xBFFF => {
@@ -109,10 +198,10 @@ impl Mmu {
             }
 
             // Work RAM
-            0xC000..=0xDFFF => self.wram[(addr - 0xC000) as usize],
+            0xC000..=0xDFFF => self.wram[self.wram_offset(addr)],
 
             // Echo RAM (mirrors 0xC000-0xDDFF)
-            0xE000..=0xFDFF => self.wram[(addr - 0xE000) as usize],
+            0xE000..=0xFDFF => self.wram[self.wram_offset(addr - 0x2000)],
 
             // Object Attribute Memory (OAM) - Sprites
             0xFE00..=0xFE9F => self.oam[(addr - 0xFE00) as usize],
@@ -120,6 +209,14 @@ impl Mmu {
             // Prohibited area
             0xFEA0..=0xFEFF => 0xFF,
 
+            // CGB bank registers (unused bits read as 1, open bus on DMG)
+            VBK if self.model.is_cgb() => 0xFE | self.vram_bank as u8,
+            SVBK if self.model.is_cgb() => 0xF8 | self.wram_bank as u8,
+            VBK | SVBK => 0xFF,
+
+            // Boot ROM disable register is write-only
+            BOOT => 0xFF,
+
             // I/O Registers
             0xFF00..=0xFF7F => self.io[(addr - 0xFF00) as usize],
 
@@ -138,7 +235,10 @@ impl Mmu {
             0x0000..=0x7FFF => self.mbc_write(addr, value),
 
             // Video RAM
-            0x8000..=0x9FFF => self.vram[(addr - 0x8000) as usize] = value,
+            0x8000..=0x9FFF => {
+                let offset = self.vram_offset(addr);
+                self.vram[offset] = value;
+            }
 
             // External RAM (cartridge RAM)
             0xA000..=0xBFFF => {
@@ -151,10 +251,16 @@ impl Mmu {
             }
 
             // Work RAM
-            0xC000..=0xDFFF => self.wram[(addr - 0xC000) as usize] = value,
+            0xC000..=0xDFFF => {
+                let offset = self.wram_offset(addr);
+                self.wram[offset] = value;
+            }
 
             // Echo RAM (writes to WRAM)
-            0xE000..=0xFDFF => self.wram[(addr - 0xE000) as usize] = value,
+            0xE000..=0xFDFF => {
+                let offset = self.wram_offset(addr - 0x2000);
+                self.wram[offset] = value;
+            }
 
             // OAM
             0xFE00..=0xFE9F => self.oam[(addr - 0xFE00) as usize] = value,
@@ -162,6 +268,26 @@ impl Mmu {
             // Prohibited area (ignored)
             0xFEA0..=0xFEFF => {}
 
+            // CGB bank registers (ignored on DMG)
+            VBK => {
+                if self.model.is_cgb() {
+                    self.vram_bank = (value & 0x01) as usize;
+                }
+            }
+            SVBK => {
+                if self.model.is_cgb() {
+                    // Bank 0 selects bank 1
+                    self.wram_bank = ((value & 0x07) as usize).max(1);
+                }
+            }
+
+            // Any non-zero write unmaps the boot ROM until the next power on
+            BOOT => {
+                if value != 0 {
+                    self.boot_rom = None;
+                }
+            }
+
             // I/O Registers
             0xFF00..=0xFF7F => self.io[(addr - 0xFF00) as usize] = value,
 
@@ -287,7 +413,7 @@ impl Mmu {
                 if value <= 0x03 {
                     // RAM bank
                     self.ram_bank = (value & 0x03) as usize;
-                } else if value >= 0x08 && value <= 0x0C {
+                } else if (0x08..=0x0C).contains(&value) {
                     // RTC register (not implemented yet)
                     // TODO: RTC support
                 }
@@ -347,14 +473,31 @@ impl Mmu {
     }
 
     /// Get reference to VRAM for PPU rendering
+    ///
+    /// Contains both banks: bank 1 starts at offset 0x2000.
     pub fn vram(&self) -> &[u8] {
-        &self.vram
+        &self.vram[..]
     }
 
     /// Get reference to OAM for PPU rendering
     pub fn oam(&self) -> &[u8] {
         &self.oam
     }
+
+    /// Get mutable reference to OAM, bypassing the I/O and DMA write paths
+    pub fn oam_mut(&mut self) -> &mut [u8] {
+        &mut self.oam
+    }
+
+    /// Cartridge RAM (empty if the cartridge has none)
+    pub fn external_ram(&self) -> &[u8] {
+        &self.external_ram
+    }
+
+    /// Mutable cartridge RAM, e.g. to restore a battery save
+    pub fn external_ram_mut(&mut self) -> &mut [u8] {
+        &mut self.external_ram
+    }
 }
 
 /// Implement Memory trait for Mmu
@@ -366,6 +509,14 @@ impl crate::memory::Memory for Mmu {
     fn write(&mut self, addr: u16, value: u8) {
         self.write(addr, value)
     }
+
+    fn oam_mut(&mut self) -> &mut [u8] {
+        self.oam_mut()
+    }
+
+    fn video_memory(&self) -> Option<(&[u8], &[u8])> {
+        Some((self.vram(), self.oam()))
+    }
 }
 
 #[cfg(test)]
@@ -374,7 +525,13 @@ mod tests {
 
     /// Helper to create a test cartridge with ROM ONLY
     fn create_test_cartridge(rom_size: usize) -> Cartridge {
+        create_test_cartridge_with_cgb_flag(rom_size, 0x00)
+    }
+
+    /// Helper to create a ROM ONLY test cartridge with the given CGB flag
+    fn create_test_cartridge_with_cgb_flag(rom_size: usize, cgb_flag: u8) -> Cartridge {
         let mut rom = vec![0; rom_size];
+        rom[0x0143] = cgb_flag;
         rom[0x0147] = 0x00; // ROM ONLY
         rom[0x0148] = if rom_size == 32 * 1024 {
             0x00
@@ -472,4 +629,105 @@ mod tests {
         mmu.write(0xFFFF, 0x1F);
         assert_eq!(mmu.read(0xFFFF), 0x1F);
     }
+
+    #[test]
+    fn test_mmu_cgb_vram_banks() {
+        let cart = create_test_cartridge_with_cgb_flag(32 * 1024, 0x80);
+        let mut mmu = Mmu::new(cart);
+        assert_eq!(mmu.model(), Model::Cgb);
+
+        mmu.write(0x8000, 0x11);
+        mmu.write(VBK, 0x01);
+        assert_eq!(mmu.read(VBK), 0xFF);
+        assert_eq!(mmu.read(0x8000), 0x00);
+
+        mmu.write(0x8000, 0x22);
+        assert_eq!(mmu.vram()[0x2000], 0x22);
+
+        mmu.write(VBK, 0x00);
+        assert_eq!(mmu.read(VBK), 0xFE);
+        assert_eq!(mmu.read(0x8000), 0x11);
+    }
+
+    #[test]
+    fn test_mmu_cgb_wram_banks() {
+        let cart = create_test_cartridge_with_cgb_flag(32 * 1024, 0xC0);
+        let mut mmu = Mmu::new(cart);
+
+        mmu.write(0xC000, 0xAA);
+        mmu.write(0xD000, 0x01);
+
+        mmu.write(SVBK, 0x07);
+        assert_eq!(mmu.read(SVBK), 0xFF);
+        assert_eq!(mmu.read(0xD000), 0x00);
+        mmu.write(0xD000, 0x07);
+
+        // Bank 0 always stays mapped at 0xC000
+        assert_eq!(mmu.read(0xC000), 0xAA);
+
+        // Writing 0 selects bank 1
+        mmu.write(SVBK, 0x00);
+        assert_eq!(mmu.read(SVBK), 0xF9);
+        assert_eq!(mmu.read(0xD000), 0x01);
+
+        // Echo RAM follows the selected bank
+        mmu.write(SVBK, 0x07);
+        assert_eq!(mmu.read(0xF000), 0x07);
+    }
+
+    #[test]
+    fn test_mmu_dmg_ignores_bank_registers() {
+        let cart = create_test_cartridge(32 * 1024);
+        let mut mmu = Mmu::new(cart);
+        assert_eq!(mmu.model(), Model::Dmg);
+
+        mmu.write(0x8000, 0x11);
+        mmu.write(VBK, 0x01);
+        mmu.write(SVBK, 0x03);
+        assert_eq!(mmu.read(VBK), 0xFF);
+        assert_eq!(mmu.read(SVBK), 0xFF);
+        assert_eq!(mmu.read(0x8000), 0x11);
+    }
+
+    #[test]
+    fn test_mmu_boot_rom_mapping() {
+        let mut cartridge = create_test_cartridge(32 * 1024);
+        cartridge.rom[0x0000] = 0xC3;
+        cartridge.rom[0x0100] = 0x00;
+        let mut mmu = Mmu::new(cartridge);
+
+        assert!(mmu.load_boot_rom(vec![0x31; 0x80]).is_err());
+        mmu.load_boot_rom(vec![0x31; DMG_BOOT_ROM_SIZE]).unwrap();
+
+        assert_eq!(mmu.read(0x0000), 0x31);
+        assert_eq!(mmu.read(0x00FF), 0x31);
+        assert_eq!(
+            mmu.read(0x0100),
+            0x00,
+            "cartridge visible after the boot ROM"
+        );
+
+        mmu.write(BOOT, 0x00);
+        assert!(mmu.is_boot_rom_mapped());
+
+        mmu.write(BOOT, 0x01);
+        assert!(!mmu.is_boot_rom_mapped());
+        assert_eq!(mmu.read(0x0000), 0xC3);
+        assert_eq!(mmu.read(BOOT), 0xFF);
+    }
+
+    #[test]
+    fn test_mmu_cgb_boot_rom_leaves_header_visible() {
+        let cartridge = create_test_cartridge_with_cgb_flag(32 * 1024, 0x80);
+        let mut mmu = Mmu::new(cartridge);
+
+        assert!(mmu.load_boot_rom(vec![0xAA; DMG_BOOT_ROM_SIZE]).is_err());
+        mmu.load_boot_rom(vec![0xAA; CGB_BOOT_ROM_SIZE]).unwrap();
+
+        assert_eq!(mmu.read(0x00FF), 0xAA);
+        assert_eq!(mmu.read(0x0143), 0x80);
+        assert_eq!(mmu.read(0x0200), 0xAA);
+        assert_eq!(mmu.read(0x08FF), 0xAA);
+        assert_eq!(mmu.read(0x0900), 0x00);
+    }
 }
@@ -0,0 +1,54 @@
+/// Game Boy hardware model
+///
+/// The model is selected from the cartridge header: cartridges that support
+/// or require Game Boy Color features (0x0143) run on CGB hardware, games
+/// with Super Game Boy enhancements (0x0146) run on an SGB, everything else
+/// runs on the original DMG.
+use crate::cartridge::{CartridgeHeader, CgbSupport};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Model {
+    /// Original Game Boy
+    #[default]
+    Dmg,
+    /// Super Game Boy (DMG hardware in a SNES cartridge)
+    Sgb,
+    /// Game Boy Color
+    Cgb,
+}
+
+impl Model {
+    /// Select the hardware model for a cartridge
+    pub fn for_header(header: &CartridgeHeader) -> Self {
+        match header.cgb_support {
+            CgbSupport::None if header.sgb_support => Model::Sgb,
+            CgbSupport::None => Model::Dmg,
+            CgbSupport::Compatible | CgbSupport::Required => Model::Cgb,
+        }
+    }
+
+    /// All models with their names
+    pub const ALL: [(&'static str, Model); 3] = [
+        ("dmg", Model::Dmg),
+        ("sgb", Model::Sgb),
+        ("cgb", Model::Cgb),
+    ];
+
+    /// Look up a model by name (case-insensitive)
+    pub fn from_name(name: &str) -> Option<Model> {
+        Self::ALL
+            .iter()
+            .find(|(model, _)| model.eq_ignore_ascii_case(name))
+            .map(|(_, model)| *model)
+    }
+
+    /// Check if this is Game Boy Color hardware
+    pub fn is_cgb(&self) -> bool {
+        *self == Model::Cgb
+    }
+
+    /// Check if this is Super Game Boy hardware
+    pub fn is_sgb(&self) -> bool {
+        *self == Model::Sgb
+    }
+}
@@ -0,0 +1,168 @@
+/// DMG OAM corruption bug
+///
+/// During mode 2 the PPU reads OAM one 8-byte row per M-cycle. If the CPU
+/// puts an address in 0xFE00-0xFEFF on the bus at the same time (a read or
+/// write, or a 16-bit register increment/decrement through the IDU), the
+/// row being read is corrupted with data from the row before it.
+///
+/// Rows are made of four 16-bit little-endian words. Row 0 is never
+/// corrupted. CGB models are not affected.
+///
+/// Corruption patterns (a = first word of the accessed row, b = first word
+/// of the preceding row, c = third word of the preceding row):
+/// - Write: first word = ((a ^ c) & (b ^ c)) ^ c
+/// - Read: first word = b | (a & c)
+/// - In both cases the last three words are copied from the preceding row
+/// - A read while the address is incremented or decremented first mixes
+///   the preceding row with the rows around it (see `corrupt_read_idu`)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OamCorruption {
+    /// Write to OAM, or INC/DEC of a register pair pointing into it
+    Write,
+    /// Read from OAM
+    Read,
+    /// Read from OAM while the address register is incremented or
+    /// decremented (LD A,(HL+), LD A,(HL-), POP)
+    ReadIdu,
+}
+
+/// Number of OAM rows (40 objects, two per row)
+pub const OAM_ROWS: usize = 20;
+
+/// Bytes per OAM row
+const ROW_SIZE: usize = 8;
+
+/// Corrupt OAM as if the CPU accessed it while the PPU read `row`
+pub fn corrupt(oam: &mut [u8], row: usize, kind: OamCorruption) {
+    if row == 0 || row >= OAM_ROWS {
+        return;
+    }
+
+    match kind {
+        OamCorruption::Write => corrupt_row(oam, row, |a, b, c| ((a ^ c) & (b ^ c)) ^ c),
+        OamCorruption::Read => corrupt_row(oam, row, read_pattern),
+        OamCorruption::ReadIdu => {
+            corrupt_read_idu(oam, row);
+            corrupt_row(oam, row, read_pattern);
+        }
+    }
+}
+
+fn read_pattern(a: u16, b: u16, c: u16) -> u16 {
+    b | (a & c)
+}
+
+/// Replace the first word of `row` using `pattern(a, b, c)` and copy the
+/// rest of the preceding row over it
+fn corrupt_row(oam: &mut [u8], row: usize, pattern: impl Fn(u16, u16, u16) -> u16) {
+    let a = word(oam, row, 0);
+    let b = word(oam, row - 1, 0);
+    let c = word(oam, row - 1, 2);
+    set_word(oam, row, 0, pattern(a, b, c));
+
+    let (before, current) = oam.split_at_mut(row * ROW_SIZE);
+    current[2..ROW_SIZE].copy_from_slice(&before[(row - 1) * ROW_SIZE + 2..]);
+}
+
+/// Extra corruption of a read during an increment/decrement
+///
+/// Not applied for the first four rows or the last one. The first word of
+/// the preceding row becomes (b & (a | c | d)) | (a & c & d), with a = first
+/// word two rows before, b = first word of the preceding row, c = first word
+/// of the accessed row and d = third word of the preceding row. The
+/// preceding row is then copied to the accessed row and two rows before.
+fn corrupt_read_idu(oam: &mut [u8], row: usize) {
+    if !(4..OAM_ROWS - 1).contains(&row) {
+        return;
+    }
+
+    let a = word(oam, row - 2, 0);
+    let b = word(oam, row - 1, 0);
+    let c = word(oam, row, 0);
+    let d = word(oam, row - 1, 2);
+    set_word(oam, row - 1, 0, (b & (a | c | d)) | (a & c & d));
+
+    let preceding = (row - 1) * ROW_SIZE;
+    oam.copy_within(preceding..preceding + ROW_SIZE, row * ROW_SIZE);
+    oam.copy_within(preceding..preceding + ROW_SIZE, (row - 2) * ROW_SIZE);
+}
+
+fn word(oam: &[u8], row: usize, index: usize) -> u16 {
+    let offset = row * ROW_SIZE + index * 2;
+    u16::from_le_bytes([oam[offset], oam[offset + 1]])
+}
+
+fn set_word(oam: &mut [u8], row: usize, index: usize, value: u16) {
+    let offset = row * ROW_SIZE + index * 2;
+    oam[offset..offset + 2].copy_from_slice(&value.to_le_bytes());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// OAM where every byte holds its row number in the high nibble and
+    /// its offset in the low nibble
+    fn numbered_oam() -> [u8; 0xA0] {
+        let mut oam = [0; 0xA0];
+        for (i, byte) in oam.iter_mut().enumerate() {
+            *byte = (((i / ROW_SIZE) << 4) | (i % ROW_SIZE)) as u8;
+        }
+        oam
+    }
+
+    #[test]
+    fn test_first_row_is_never_corrupted() {
+        let mut oam = numbered_oam();
+        for kind in [
+            OamCorruption::Write,
+            OamCorruption::Read,
+            OamCorruption::ReadIdu,
+        ] {
+            corrupt(&mut oam, 0, kind);
+        }
+        assert_eq!(oam, numbered_oam());
+    }
+
+    #[test]
+    fn test_write_corruption() {
+        let mut oam = numbered_oam();
+        corrupt(&mut oam, 2, OamCorruption::Write);
+
+        // a = 0x2120, b = 0x1110, c = 0x1514
+        let expected = ((0x2120u16 ^ 0x1514) & (0x1110 ^ 0x1514)) ^ 0x1514;
+        assert_eq!(word(&oam, 2, 0), expected);
+        assert_eq!(oam[0x12..0x18], [0x12, 0x13, 0x14, 0x15, 0x16, 0x17]);
+        assert_eq!(oam[0x18..0x20], numbered_oam()[0x18..0x20]);
+    }
+
+    #[test]
+    fn test_read_corruption() {
+        let mut oam = numbered_oam();
+        corrupt(&mut oam, 1, OamCorruption::Read);
+
+        // a = 0x1110, b = 0x0100, c = 0x0504
+        assert_eq!(word(&oam, 1, 0), 0x0100 | (0x1110 & 0x0504));
+        assert_eq!(oam[0x0A..0x10], [0x02, 0x03, 0x04, 0x05, 0x06, 0x07]);
+    }
+
+    #[test]
+    fn test_read_idu_corruption() {
+        let mut oam = numbered_oam();
+        corrupt(&mut oam, 5, OamCorruption::ReadIdu);
+
+        // a = 0x3130, b = 0x4140, c = 0x5150, d = 0x4544
+        let mixed = (0x4140u16 & (0x3130 | 0x5150 | 0x4544)) | (0x3130 & 0x5150 & 0x4544);
+        assert_eq!(word(&oam, 4, 0), mixed);
+        assert_eq!(oam[0x18..0x20], oam[0x20..0x28], "copied two rows before");
+        assert_eq!(word(&oam, 5, 0), mixed | (mixed & 0x4544));
+        assert_eq!(oam[0x2A..0x30], oam[0x22..0x28]);
+
+        // Only the plain read corruption near the start of OAM
+        let mut oam = numbered_oam();
+        let mut expected = numbered_oam();
+        corrupt(&mut oam, 3, OamCorruption::ReadIdu);
+        corrupt(&mut expected, 3, OamCorruption::Read);
+        assert_eq!(oam, expected);
+    }
+}
@@ -0,0 +1,688 @@
+/// ROM soft-patching
+///
+/// Applies IPS, BPS and UPS patches (translations, romhacks, bug fixes) to
+/// ROM bytes before the cartridge header is parsed, leaving the ROM file on
+/// disk untouched.
+///
+/// - IPS: offset/data records, including RLE records and the truncation
+///   extension written by Lunar IPS. IPS has no checksums.
+/// - BPS: copy/read commands, checked against the CRC-32 of the source ROM,
+///   the patched ROM and the patch itself
+/// - UPS: XOR runs, checked against the same three CRC-32s
+use crate::crc::crc32;
+use std::fmt;
+
+const IPS_MAGIC: &[u8] = b"PATCH";
+const IPS_EOF: usize = 0x454F46;
+const BPS_MAGIC: &[u8] = b"BPS1";
+const UPS_MAGIC: &[u8] = b"UPS1";
+
+/// Largest patched ROM accepted from a BPS or UPS patch (MBC5 maximum,
+/// 512 banks), checked before the output is allocated
+pub const MAX_TARGET_SIZE: usize = 8 * 1024 * 1024;
+
+/// Patch file extensions, in the order frontends look for them
+pub const PATCH_EXTENSIONS: [&str; 3] = ["ips", "bps", "ups"];
+
+/// Patch file format
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PatchFormat {
+    Ips,
+    Bps,
+    Ups,
+}
+
+impl PatchFormat {
+    /// Detect the format from the patch's magic bytes
+    pub fn detect(patch: &[u8]) -> Option<Self> {
+        if patch.starts_with(IPS_MAGIC) {
+            Some(PatchFormat::Ips)
+        } else if patch.starts_with(BPS_MAGIC) {
+            Some(PatchFormat::Bps)
+        } else if patch.starts_with(UPS_MAGIC) {
+            Some(PatchFormat::Ups)
+        } else {
+            None
+        }
+    }
+}
+
+impl fmt::Display for PatchFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PatchFormat::Ips => write!(f, "IPS"),
+            PatchFormat::Bps => write!(f, "BPS"),
+            PatchFormat::Ups => write!(f, "UPS"),
+        }
+    }
+}
+
+/// Which data a patch checksum covers
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChecksumTarget {
+    /// The unpatched ROM; a mismatch usually means the wrong ROM revision
+    Source,
+    /// The patched ROM
+    Target,
+    /// The patch file itself
+    Patch,
+}
+
+impl fmt::Display for ChecksumTarget {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ChecksumTarget::Source => write!(f, "source ROM"),
+            ChecksumTarget::Target => write!(f, "patched ROM"),
+            ChecksumTarget::Patch => write!(f, "patch"),
+        }
+    }
+}
+
+/// Error applying a patch
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PatchError {
+    /// Not an IPS, BPS or UPS patch
+    UnknownFormat,
+    /// The patch ends in the middle of a record
+    Truncated(PatchFormat),
+    /// A record reads or writes outside the ROM
+    OutOfBounds(PatchFormat),
+    /// The ROM isn't the size the patch was made for
+    SourceSize {
+        format: PatchFormat,
+        expected: usize,
+        actual: usize,
+    },
+    /// A CRC-32 stored in the patch doesn't match
+    ChecksumMismatch {
+        format: PatchFormat,
+        target: ChecksumTarget,
+        expected: u32,
+        actual: u32,
+    },
+}
+
+impl fmt::Display for PatchError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PatchError::UnknownFormat => {
+                write!(f, "Unknown patch format (expected IPS, BPS or UPS)")
+            }
+            PatchError::Truncated(format) => write!(f, "{} patch is truncated", format),
+            PatchError::OutOfBounds(format) => {
+                write!(f, "{} patch reads or writes outside the ROM", format)
+            }
+            PatchError::SourceSize {
+                format,
+                expected,
+                actual,
+            } => write!(
+                f,
+                "{} patch expects a {} byte ROM, got {} bytes",
+                format, expected, actual
+            ),
+            PatchError::ChecksumMismatch {
+                format,
+                target,
+                expected,
+                actual,
+            } => write!(
+                f,
+                "{} {} checksum mismatch: expected {:08X}, got {:08X}",
+                format, target, expected, actual
+            ),
+        }
+    }
+}
+
+impl std::error::Error for PatchError {}
+
+/// Apply an IPS, BPS or UPS patch, detected from its magic bytes
+pub fn apply(rom: &[u8], patch: &[u8]) -> Result<Vec<u8>, PatchError> {
+    match PatchFormat::detect(patch) {
+        Some(PatchFormat::Ips) => apply_ips(rom, patch),
+        Some(PatchFormat::Bps) => apply_bps(rom, patch),
+        Some(PatchFormat::Ups) => apply_ups(rom, patch),
+        None => Err(PatchError::UnknownFormat),
+    }
+}
+
+/// Cursor over patch bytes
+struct Reader<'a> {
+    data: &'a [u8],
+    pos: usize,
+    format: PatchFormat,
+}
+
+impl<'a> Reader<'a> {
+    fn new(data: &'a [u8], pos: usize, format: PatchFormat) -> Self {
+        Reader { data, pos, format }
+    }
+
+    fn bytes(&mut self, len: usize) -> Result<&'a [u8], PatchError> {
+        let bytes = self
+            .pos
+            .checked_add(len)
+            .and_then(|end| self.data.get(self.pos..end))
+            .ok_or(PatchError::Truncated(self.format))?;
+        self.pos += len;
+        Ok(bytes)
+    }
+
+    fn u8(&mut self) -> Result<u8, PatchError> {
+        Ok(self.bytes(1)?[0])
+    }
+
+    /// Big-endian integer, as used by IPS
+    fn be(&mut self, len: usize) -> Result<usize, PatchError> {
+        Ok(self
+            .bytes(len)?
+            .iter()
+            .fold(0, |value, &byte| value << 8 | byte as usize))
+    }
+
+    /// Variable-length integer used by BPS and UPS: 7 bits per byte, low
+    /// bits first, the last byte flagged with bit 7, and each continuation
+    /// adding one so every value has a single encoding
+    fn varint(&mut self) -> Result<usize, PatchError> {
+        let mut value: usize = 0;
+        let mut shift: usize = 1;
+        loop {
+            let byte = self.u8()?;
+            value = (byte as usize & 0x7F)
+                .checked_mul(shift)
+                .and_then(|bits| value.checked_add(bits))
+                .ok_or(PatchError::OutOfBounds(self.format))?;
+            if byte & 0x80 != 0 {
+                return Ok(value);
+            }
+            shift = shift
+                .checked_mul(0x80)
+                .ok_or(PatchError::OutOfBounds(self.format))?;
+            value = value
+                .checked_add(shift)
+                .ok_or(PatchError::OutOfBounds(self.format))?;
+        }
+    }
+}
+
+/// Apply an IPS patch
+///
+/// Records past the end of the ROM grow it (zero-filled). A 3-byte size
+/// after the EOF marker truncates the patched ROM to that size.
+pub fn apply_ips(rom: &[u8], patch: &[u8]) -> Result<Vec<u8>, PatchError> {
+    let format = PatchFormat::Ips;
+    let mut reader = Reader::new(patch, IPS_MAGIC.len(), format);
+    let mut output = rom.to_vec();
+
+    loop {
+        let offset = reader.be(3)?;
+        if offset == IPS_EOF {
+            break;
+        }
+
+        let size = reader.be(2)?;
+        let (size, data) = if size == 0 {
+            // RLE record: a 16-bit run length and the byte to repeat
+            let run = reader.be(2)?;
+            (run, None)
+        } else {
+            (size, Some(reader.bytes(size)?))
+        };
+
+        let end = offset + size;
+        if end > output.len() {
+            output.resize(end, 0);
+        }
+        match data {
+            Some(data) => output[offset..end].copy_from_slice(data),
+            None => output[offset..end].fill(reader.u8()?),
+        }
+    }
+
+    if let Ok(size) = reader.be(3) {
+        output.truncate(size);
+    }
+    Ok(output)
+}
+
+/// Check the CRC-32s at the end of a BPS or UPS patch that cover the
+/// source ROM and the patch itself
+///
+/// Returns the target CRC-32 and the end of the patch body.
+fn check_footer(rom: &[u8], patch: &[u8], format: PatchFormat) -> Result<(u32, usize), PatchError> {
+    let body_end = patch
+        .len()
+        .checked_sub(12)
+        .filter(|&end| end >= 4)
+        .ok_or(PatchError::Truncated(format))?;
+    let footer = |index: usize| {
+        let offset = body_end + index * 4;
+        u32::from_le_bytes(patch[offset..offset + 4].try_into().unwrap())
+    };
+
+    let checks = [
+        (
+            ChecksumTarget::Patch,
+            footer(2),
+            crc32(&patch[..body_end + 8]),
+        ),
+        (ChecksumTarget::Source, footer(0), crc32(rom)),
+    ];
+    for (target, expected, actual) in checks {
+        if expected != actual {
+            return Err(PatchError::ChecksumMismatch {
+                format,
+                target,
+                expected,
+                actual,
+            });
+        }
+    }
+    Ok((footer(1), body_end))
+}
+
+fn check_source_size(rom: &[u8], expected: usize, format: PatchFormat) -> Result<(), PatchError> {
+    if rom.len() != expected {
+        return Err(PatchError::SourceSize {
+            format,
+            expected,
+            actual: rom.len(),
+        });
+    }
+    Ok(())
+}
+
+fn check_target(output: &[u8], expected: u32, format: PatchFormat) -> Result<(), PatchError> {
+    let actual = crc32(output);
+    if actual != expected {
+        return Err(PatchError::ChecksumMismatch {
+            format,
+            target: ChecksumTarget::Target,
+            expected,
+            actual,
+        });
+    }
+    Ok(())
+}
+
+/// Check the patched ROM size a BPS or UPS patch declares
+fn check_target_size(target_size: usize, format: PatchFormat) -> Result<(), PatchError> {
+    if target_size > MAX_TARGET_SIZE {
+        return Err(PatchError::OutOfBounds(format));
+    }
+    Ok(())
+}
+
+/// Apply a BPS patch
+pub fn apply_bps(rom: &[u8], patch: &[u8]) -> Result<Vec<u8>, PatchError> {
+    let format = PatchFormat::Bps;
+    let (target_crc, body_end) = check_footer(rom, patch, format)?;
+    let mut reader = Reader::new(&patch[..body_end], BPS_MAGIC.len(), format);
+
+    let source_size = reader.varint()?;
+    let target_size = reader.varint()?;
+    let metadata_size = reader.varint()?;
+    reader.bytes(metadata_size)?;
+    check_source_size(rom, source_size, format)?;
+    check_target_size(target_size, format)?;
+
+    let mut output = Vec::with_capacity(target_size);
+    let mut source_offset: usize = 0;
+    let mut target_offset: usize = 0;
+
+    // Relative offsets are stored as magnitude << 1 | sign
+    let seek = |offset: usize, data: usize| {
+        let delta = data >> 1;
+        if data & 1 != 0 {
+            offset.checked_sub(delta)
+        } else {
+            offset.checked_add(delta)
+        }
+        .ok_or(PatchError::OutOfBounds(format))
+    };
+
+    while reader.pos < body_end {
+        let data = reader.varint()?;
+        let length = (data >> 2) + 1;
+        if output.len() + length > target_size {
+            return Err(PatchError::OutOfBounds(format));
+        }
+
+        match data & 3 {
+            // SourceRead: the source bytes at the same position
+            0 => {
+                let start = output.len();
+                let bytes = rom
+                    .get(start..start + length)
+                    .ok_or(PatchError::OutOfBounds(format))?;
+                output.extend_from_slice(bytes);
+            }
+            // TargetRead: literal bytes from the patch
+            1 => output.extend_from_slice(reader.bytes(length)?),
+            // SourceCopy: source bytes from anywhere
+            2 => {
+                source_offset = seek(source_offset, reader.varint()?)?;
+                let bytes = rom
+                    .get(source_offset..source_offset + length)
+                    .ok_or(PatchError::OutOfBounds(format))?;
+                output.extend_from_slice(bytes);
+                source_offset += length;
+            }
+            // TargetCopy: already written bytes, byte by byte since the
+            // run may overlap what it produces
+            _ => {
+                target_offset = seek(target_offset, reader.varint()?)?;
+                for _ in 0..length {
+                    let byte = *output
+                        .get(target_offset)
+                        .ok_or(PatchError::OutOfBounds(format))?;
+                    output.push(byte);
+                    target_offset += 1;
+                }
+            }
+        }
+    }
+
+    if output.len() != target_size {
+        return Err(PatchError::Truncated(format));
+    }
+    check_target(&output, target_crc, format)?;
+    Ok(output)
+}
+
+/// Apply a UPS patch
+pub fn apply_ups(rom: &[u8], patch: &[u8]) -> Result<Vec<u8>, PatchError> {
+    let format = PatchFormat::Ups;
+    let (target_crc, body_end) = check_footer(rom, patch, format)?;
+    let mut reader = Reader::new(&patch[..body_end], UPS_MAGIC.len(), format);
+
+    let source_size = reader.varint()?;
+    let target_size = reader.varint()?;
+    check_source_size(rom, source_size, format)?;
+    check_target_size(target_size, format)?;
+
+    let mut output = rom.to_vec();
+    output.resize(target_size, 0);
+
+    // Each hunk skips unchanged bytes, then XORs bytes into the ROM up to
+    // and including a zero byte
+    let mut offset: usize = 0;
+    while reader.pos < body_end {
+        offset = offset
+            .checked_add(reader.varint()?)
+            .ok_or(PatchError::OutOfBounds(format))?;
+        loop {
+            let byte = reader.u8()?;
+            if let Some(out) = output.get_mut(offset) {
+                *out ^= byte;
+            }
+            offset += 1;
+            if byte == 0 {
+                break;
+            }
+        }
+    }
+
+    check_target(&output, target_crc, format)?;
+    Ok(output)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn push_varint(out: &mut Vec<u8>, mut value: usize) {
+        loop {
+            let bits = (value & 0x7F) as u8;
+            value >>= 7;
+            if value == 0 {
+                out.push(0x80 | bits);
+                return;
+            }
+            out.push(bits);
+            value -= 1;
+        }
+    }
+
+    /// Append the source, target and patch CRC-32s
+    fn push_footer(patch: &mut Vec<u8>, source: &[u8], target: &[u8]) {
+        patch.extend_from_slice(&crc32(source).to_le_bytes());
+        patch.extend_from_slice(&crc32(target).to_le_bytes());
+        patch.extend_from_slice(&crc32(patch).to_le_bytes());
+    }
+
+    fn source() -> Vec<u8> {
+        (0..64).collect()
+    }
+
+    #[test]
+    fn test_varint_round_trip() {
+        for value in [0, 1, 0x7F, 0x80, 0x407F, 0x4080, 0x12_3456, 8 << 20] {
+            let mut bytes = Vec::new();
+            push_varint(&mut bytes, value);
+            let mut reader = Reader::new(&bytes, 0, PatchFormat::Bps);
+            assert_eq!(reader.varint().unwrap(), value);
+            assert_eq!(reader.pos, bytes.len());
+        }
+    }
+
+    #[test]
+    fn test_detect() {
+        assert_eq!(PatchFormat::detect(b"PATCHEOF"), Some(PatchFormat::Ips));
+        assert_eq!(PatchFormat::detect(b"BPS1"), Some(PatchFormat::Bps));
+        assert_eq!(PatchFormat::detect(b"UPS1"), Some(PatchFormat::Ups));
+        assert_eq!(PatchFormat::detect(b"PK\x03\x04"), None);
+        assert_eq!(apply(&source(), b"nope"), Err(PatchError::UnknownFormat));
+    }
+
+    #[test]
+    fn test_ips_records() {
+        let mut patch = b"PATCH".to_vec();
+        patch.extend_from_slice(&[0x00, 0x00, 0x02, 0x00, 0x02, 0xAA, 0xBB]);
+        // RLE: 4 x 0xCC at 0x10
+        patch.extend_from_slice(&[0x00, 0x00, 0x10, 0x00, 0x00, 0x00, 0x04, 0xCC]);
+        // Past the end of the ROM
+        patch.extend_from_slice(&[0x00, 0x00, 0x42, 0x00, 0x01, 0xDD]);
+        patch.extend_from_slice(b"EOF");
+
+        let output = apply(&source(), &patch).unwrap();
+        assert_eq!(output.len(), 0x43);
+        assert_eq!(output[0..4], [0x00, 0x01, 0xAA, 0xBB]);
+        assert_eq!(output[0x10..0x15], [0xCC, 0xCC, 0xCC, 0xCC, 0x14]);
+        assert_eq!(output[0x40..0x43], [0x00, 0x00, 0xDD]);
+    }
+
+    #[test]
+    fn test_ips_truncation() {
+        let mut patch = b"PATCH".to_vec();
+        patch.extend_from_slice(b"EOF");
+        patch.extend_from_slice(&[0x00, 0x00, 0x20]);
+
+        let output = apply(&source(), &patch).unwrap();
+        assert_eq!(output, source()[..0x20]);
+    }
+
+    #[test]
+    fn test_ips_truncated_record() {
+        let mut patch = b"PATCH".to_vec();
+        patch.extend_from_slice(&[0x00, 0x00, 0x02, 0x00, 0x04, 0xAA]);
+        assert_eq!(
+            apply(&source(), &patch),
+            Err(PatchError::Truncated(PatchFormat::Ips))
+        );
+    }
+
+    /// BPS patch that exercises every command to turn `source()` into
+    /// `bps_target()`
+    fn bps_patch() -> Vec<u8> {
+        let target = bps_target();
+        let mut patch = b"BPS1".to_vec();
+        push_varint(&mut patch, 64);
+        push_varint(&mut patch, target.len());
+        push_varint(&mut patch, 4);
+        patch.extend_from_slice(b"test");
+
+        // SourceRead 8 bytes
+        push_varint(&mut patch, (8 - 1) << 2);
+        // TargetRead 2 bytes
+        push_varint(&mut patch, ((2 - 1) << 2) | 1);
+        patch.extend_from_slice(&[0xAA, 0xBB]);
+        // SourceCopy 4 bytes from 0x30
+        push_varint(&mut patch, ((4 - 1) << 2) | 2);
+        push_varint(&mut patch, 0x30 << 1);
+        // SourceCopy 2 bytes from 0x20 (relative -0x14)
+        push_varint(&mut patch, ((2 - 1) << 2) | 2);
+        push_varint(&mut patch, (0x14 << 1) | 1);
+        // TargetCopy 6 bytes from 12, overlapping what it writes
+        push_varint(&mut patch, ((6 - 1) << 2) | 3);
+        push_varint(&mut patch, 12 << 1);
+
+        push_footer(&mut patch, &source(), &target);
+        patch
+    }
+
+    fn bps_target() -> Vec<u8> {
+        let mut target: Vec<u8> = (0..8).collect();
+        target.extend_from_slice(&[0xAA, 0xBB, 0x30, 0x31, 0x32, 0x33, 0x20, 0x21]);
+        target.extend_from_slice(&[0x32, 0x33, 0x20, 0x21, 0x32, 0x33]);
+        target
+    }
+
+    #[test]
+    fn test_bps() {
+        assert_eq!(apply(&source(), &bps_patch()).unwrap(), bps_target());
+    }
+
+    #[test]
+    fn test_bps_wrong_source() {
+        let mut rom = source();
+        rom[0] = 0xFF;
+        match apply(&rom, &bps_patch()) {
+            Err(PatchError::ChecksumMismatch {
+                format: PatchFormat::Bps,
+                target: ChecksumTarget::Source,
+                expected,
+                actual,
+            }) => {
+                assert_eq!(expected, crc32(&source()));
+                assert_eq!(actual, crc32(&rom));
+            }
+            result => panic!("Expected a source checksum mismatch, got {:?}", result),
+        }
+    }
+
+    #[test]
+    fn test_bps_corrupt_patch() {
+        let mut patch = bps_patch();
+        patch[10] ^= 0xFF;
+        assert!(matches!(
+            apply(&source(), &patch),
+            Err(PatchError::ChecksumMismatch {
+                target: ChecksumTarget::Patch,
+                ..
+            })
+        ));
+    }
+
+    #[test]
+    fn test_bps_wrong_target() {
+        // A valid patch whose target checksum doesn't match what it writes
+        let mut patch = bps_patch();
+        let target_crc = patch.len() - 8;
+        patch[target_crc] ^= 0xFF;
+        let patch_crc = crc32(&patch[..patch.len() - 4]);
+        let len = patch.len();
+        patch[len - 4..].copy_from_slice(&patch_crc.to_le_bytes());
+
+        assert!(matches!(
+            apply(&source(), &patch),
+            Err(PatchError::ChecksumMismatch {
+                target: ChecksumTarget::Target,
+                ..
+            })
+        ));
+    }
+
+    #[test]
+    fn test_bps_source_size() {
+        let mut patch = b"BPS1".to_vec();
+        push_varint(&mut patch, 32);
+        push_varint(&mut patch, 32);
+        push_varint(&mut patch, 0);
+        push_footer(&mut patch, &source(), &source()[..32]);
+        assert_eq!(
+            apply(&source(), &patch),
+            Err(PatchError::SourceSize {
+                format: PatchFormat::Bps,
+                expected: 32,
+                actual: 64
+            })
+        );
+    }
+
+    #[test]
+    fn test_target_size_limit() {
+        for (magic, format) in [(b"BPS1", PatchFormat::Bps), (b"UPS1", PatchFormat::Ups)] {
+            // Checksums are valid, only the declared target size is absurd
+            let mut patch = magic.to_vec();
+            push_varint(&mut patch, 64);
+            push_varint(&mut patch, 1 << 40);
+            if format == PatchFormat::Bps {
+                push_varint(&mut patch, 0);
+            }
+            push_footer(&mut patch, &source(), &source());
+            assert_eq!(
+                apply(&source(), &patch),
+                Err(PatchError::OutOfBounds(format))
+            );
+        }
+    }
+
+    #[test]
+    fn test_ups() {
+        let mut target = source();
+        target[4] ^= 0x11;
+        target[5] ^= 0x22;
+        target[0x3F] ^= 0x33;
+        target.extend_from_slice(&[0x44, 0x55]);
+
+        let mut patch = b"UPS1".to_vec();
+        push_varint(&mut patch, 64);
+        push_varint(&mut patch, 66);
+        // Skip 4, XOR 2 bytes, then the terminator leaves byte 6 unchanged
+        push_varint(&mut patch, 4);
+        patch.extend_from_slice(&[0x11, 0x22, 0x00]);
+        // Skip to 0x3F and run past the end of the source
+        push_varint(&mut patch, 0x3F - 7);
+        patch.extend_from_slice(&[0x33, 0x44, 0x55, 0x00]);
+        push_footer(&mut patch, &source(), &target);
+
+        assert_eq!(apply(&source(), &patch).unwrap(), target);
+
+        let mut rom = source();
+        rom[1] = 0;
+        assert!(matches!(
+            apply(&rom, &patch),
+            Err(PatchError::ChecksumMismatch {
+                format: PatchFormat::Ups,
+                target: ChecksumTarget::Source,
+                ..
+            })
+        ));
+    }
+
+    #[test]
+    fn test_error_message() {
+        let error = PatchError::ChecksumMismatch {
+            format: PatchFormat::Bps,
+            target: ChecksumTarget::Source,
+            expected: 0x1234_ABCD,
+            actual: 0xDEAD_BEEF,
+        };
+        assert_eq!(
+            error.to_string(),
+            "BPS source ROM checksum mismatch: expected 1234ABCD, got DEADBEEF"
+        );
+    }
+}
@@ -1,4 +1,6 @@
 /// PPU (Picture Processing Unit) implementation
+use crate::model::Model;
+use crate::video::{self, ColorCorrection, Palette};
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum Mode {
@@ -11,9 +13,24 @@ pub enum Mode {
 pub const SCREEN_WIDTH: usize = 160;
 pub const SCREEN_HEIGHT: usize = 144;
 
-pub type Framebuffer = [[u8; SCREEN_WIDTH]; SCREEN_HEIGHT];
+/// Rendered screen
+///
+/// On DMG each pixel is a 2-bit shade (0 = lightest, 3 = darkest).
+/// On CGB each pixel is a 15-bit color (bits 0-4 red, 5-9 green, 10-14 blue).
+pub type Framebuffer = [[u16; SCREEN_WIDTH]; SCREEN_HEIGHT];
+
+/// Dots (PPU clock ticks) per scanline and per full frame
+pub const DOTS_PER_LINE: u16 = 456;
+pub const DOTS_PER_FRAME: u64 = DOTS_PER_LINE as u64 * 154;
+/// Dots per second: the 4.194304 MHz master clock, about 59.73 frames
+pub const DOTS_PER_SECOND: u64 = 4_194_304;
+
+/// Dot within a scanline at which LY is compared against LYC.
+/// For the first dots of a line the coincidence flag reads as clear.
+const LYC_COMPARE_DOT: u16 = 4;
 
 pub struct Ppu {
+    model: Model,
     ly: u8,
     dots: u16,
     mode: Mode,
@@ -27,12 +44,47 @@ pub struct Ppu {
     obp1: u8,
     wy: u8,
     wx: u8,
+    /// CGB background palette index (BCPS) and memory (8 palettes x 4 colors)
+    bcps: u8,
+    bg_palette_ram: [u8; 64],
+    /// CGB object palette index (OCPS) and memory (8 palettes x 4 colors)
+    ocps: u8,
+    obj_palette_ram: [u8; 64],
     framebuffer: Box<Framebuffer>,
+    /// Colors used to display DMG shades
+    palette: Palette,
+    /// Correction applied to CGB colors for display
+    color_correction: ColorCorrection,
+    /// Background/window color indices and priorities of the current line
+    bg_line: [BgPixel; SCREEN_WIDTH],
     sprite_buffer: Vec<SpriteData>,
+    /// Internal STAT interrupt line (all enabled sources ORed together).
+    /// An interrupt is only requested on a rising edge of this line.
+    stat_line: bool,
+    /// First line after the LCD is switched on: it starts in mode 0 and
+    /// skips the OAM search
+    first_line: bool,
+    /// First frame after the LCD is switched on: it is not displayed
+    first_frame: bool,
+    /// Dots since the last frame (VBlank start) while the LCD is off
+    off_dots: u64,
+    /// Set when a frame has completed; consumers clear it.
+    /// Keeps ticking at the normal frame rate while the LCD is off.
+    pub frame_ready: bool,
     pub vblank_interrupt: bool,
     pub stat_interrupt: bool,
     pub should_scan_oam: bool,
     pub should_render_scanline: bool,
+    /// Set on each transition into HBlank (used to drive CGB HBlank DMA)
+    pub entered_hblank: bool,
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+struct BgPixel {
+    /// Raw color index (0-3) before palette lookup
+    color: u8,
+    /// CGB BG-to-OAM priority attribute
+    priority: bool,
 }
 
 #[derive(Debug, Clone, Copy)]
@@ -45,7 +97,21 @@ struct SpriteData {
 
 impl Ppu {
     pub fn new() -> Self {
+        Self::with_model(Model::Dmg)
+    }
+
+    /// Create a PPU for the given hardware model
+    pub fn with_model(model: Model) -> Self {
+        // Palettes start out white (0x7FFF) after the CGB boot ROM
+        let mut palette_ram = [0; 64];
+        for color in palette_ram.chunks_exact_mut(2) {
+            color.copy_from_slice(&0x7FFFu16.to_le_bytes());
+        }
+
+        let blank = blank_color(model);
+
         Self {
+            model,
             ly: 0,
             dots: 252,
             mode: Mode::HBlank,
@@ -59,17 +125,37 @@ impl Ppu {
             obp1: 0xFF,
             wy: 0,
             wx: 0,
-            framebuffer: Box::new([[0; SCREEN_WIDTH]; SCREEN_HEIGHT]),
+            bcps: 0,
+            bg_palette_ram: palette_ram,
+            ocps: 0,
+            obj_palette_ram: palette_ram,
+            framebuffer: Box::new([[blank; SCREEN_WIDTH]; SCREEN_HEIGHT]),
+            palette: Palette::default(),
+            color_correction: ColorCorrection::default(),
+            bg_line: [BgPixel::default(); SCREEN_WIDTH],
             sprite_buffer: Vec::with_capacity(10),
+            stat_line: false,
+            first_line: false,
+            first_frame: false,
+            off_dots: 0,
+            frame_ready: false,
             vblank_interrupt: false,
             stat_interrupt: false,
             should_scan_oam: false,
             should_render_scanline: false,
+            entered_hblank: false,
         }
     }
 
     pub fn step(&mut self, cycles: u64) {
         if !self.is_lcd_enabled() {
+            // Keep frame time so frontends still see one (blank) frame
+            // every DOTS_PER_FRAME dots
+            self.off_dots += cycles;
+            if self.off_dots >= DOTS_PER_FRAME {
+                self.off_dots %= DOTS_PER_FRAME;
+                self.frame_ready = true;
+            }
             return;
         }
 
@@ -81,69 +167,118 @@ impl Ppu {
                     self.set_mode(Mode::PixelTransfer);
                 }
                 Mode::PixelTransfer if self.dots == 252 => {
-                    self.should_render_scanline = true;
+                    // The first frame after LCD enable is never shown
+                    self.should_render_scanline = !self.first_frame;
+                    self.entered_hblank = true;
                     self.set_mode(Mode::HBlank);
                 }
-                Mode::HBlank if self.dots == 456 => {
+                Mode::HBlank if self.first_line && self.dots == 80 => {
+                    self.first_line = false;
+                    self.set_mode(Mode::PixelTransfer);
+                }
+                Mode::HBlank if self.dots == DOTS_PER_LINE => {
                     self.dots = 0;
                     self.ly += 1;
+                    self.clear_lyc_flag();
 
                     if self.ly == 144 {
                         self.set_mode(Mode::VBlank);
                         self.vblank_interrupt = true;
+                        self.frame_ready = !self.first_frame;
+                        self.first_frame = false;
                     } else {
                         self.should_scan_oam = true;
                         self.set_mode(Mode::OamSearch);
                     }
-
-                    self.update_lyc_flag();
                 }
-                Mode::VBlank if self.dots == 456 => {
+                Mode::VBlank if self.dots == DOTS_PER_LINE => {
                     self.dots = 0;
                     self.ly += 1;
+                    self.clear_lyc_flag();
 
                     if self.ly == 154 {
                         self.ly = 0;
                         self.should_scan_oam = true;
                         self.set_mode(Mode::OamSearch);
+                    } else {
+                        self.update_stat_line();
                     }
-
-                    self.update_lyc_flag();
                 }
                 _ => {}
             }
+
+            if self.dots == LYC_COMPARE_DOT {
+                self.update_lyc_flag();
+            }
         }
     }
 
     fn set_mode(&mut self, mode: Mode) {
-        let old_mode = self.mode;
         self.mode = mode;
         self.stat = (self.stat & 0xFC) | (mode as u8);
+        self.update_stat_line();
+    }
 
-        let stat_int = match mode {
-            Mode::HBlank => self.stat & 0x08 != 0,
-            Mode::VBlank => self.stat & 0x10 != 0,
-            Mode::OamSearch => self.stat & 0x20 != 0,
-            Mode::PixelTransfer => false,
-        };
-
-        if stat_int && old_mode != mode {
-            self.stat_interrupt = true;
-        }
+    /// Clear the LY=LYC flag at the start of a line, until the comparison
+    /// is performed at `LYC_COMPARE_DOT`
+    fn clear_lyc_flag(&mut self) {
+        self.stat &= !0x04;
     }
 
     fn update_lyc_flag(&mut self) {
-        let lyc_eq_ly = self.ly == self.lyc;
-
-        if lyc_eq_ly {
+        if self.ly == self.lyc {
             self.stat |= 0x04;
         } else {
             self.stat &= !0x04;
         }
 
-        if lyc_eq_ly && (self.stat & 0x40 != 0) {
+        self.update_stat_line();
+    }
+
+    /// Compute the level of the internal STAT interrupt line
+    ///
+    /// Hardware ORs every enabled source into a single signal:
+    /// - Bit 6: LY=LYC coincidence
+    /// - Bit 5: Mode 2 (OAM search)
+    /// - Bit 4: Mode 1 (VBlank)
+    /// - Bit 3: Mode 0 (HBlank)
+    ///
+    /// At the start of line 144 the PPU still signals mode 2 for a few dots
+    /// before switching to mode 1, so the OAM source also fires there.
+    fn stat_line_level(&self) -> bool {
+        if !self.is_lcd_enabled() {
+            return false;
+        }
+
+        let lyc = self.stat & 0x40 != 0 && self.stat & 0x04 != 0;
+        let mode = match self.mode {
+            Mode::HBlank => self.stat & 0x08 != 0,
+            Mode::VBlank => {
+                let oam_quirk = self.ly == 144 && self.dots < LYC_COMPARE_DOT;
+                self.stat & 0x10 != 0 || (oam_quirk && self.stat & 0x20 != 0)
+            }
+            Mode::OamSearch => self.stat & 0x20 != 0,
+            Mode::PixelTransfer => false,
+        };
+
+        lyc || mode
+    }
+
+    /// Re-evaluate the STAT line and request an interrupt on a rising edge
+    ///
+    /// While the line stays high, further sources becoming active are
+    /// "blocked" and do not request another interrupt.
+    fn update_stat_line(&mut self) {
+        let level = self.stat_line_level();
+        if level && !self.stat_line {
             self.stat_interrupt = true;
         }
+        self.stat_line = level;
+    }
+
+    /// Hardware model this PPU emulates
+    pub fn model(&self) -> Model {
+        self.model
     }
 
     pub fn is_lcd_enabled(&self) -> bool {
@@ -154,6 +289,17 @@ impl Ppu {
         self.mode
     }
 
+    /// OAM row (two objects, 8 bytes) read during OAM search, if any
+    ///
+    /// Used to emulate the OAM corruption bug.
+    pub fn oam_row(&self) -> Option<usize> {
+        if self.is_lcd_enabled() && self.mode == Mode::OamSearch {
+            Some(self.dots as usize / 4)
+        } else {
+            None
+        }
+    }
+
     pub fn is_vblank(&self) -> bool {
         self.mode == Mode::VBlank
     }
@@ -162,6 +308,45 @@ impl Ppu {
         &self.framebuffer
     }
 
+    /// Convert the framebuffer to RGBA for display
+    ///
+    /// `out` must hold at least `video::RGBA_FRAME_SIZE` bytes.
+    pub fn framebuffer_rgba(&self, out: &mut [u8]) {
+        video::framebuffer_to_rgba(
+            &self.framebuffer,
+            self.model,
+            &self.palette,
+            self.color_correction,
+            out,
+        );
+    }
+
+    pub fn palette(&self) -> Palette {
+        self.palette
+    }
+
+    /// Set the colors used to display DMG shades
+    pub fn set_palette(&mut self, palette: Palette) {
+        self.palette = palette;
+    }
+
+    pub fn color_correction(&self) -> ColorCorrection {
+        self.color_correction
+    }
+
+    /// Set the correction applied to CGB colors for display
+    pub fn set_color_correction(&mut self, correction: ColorCorrection) {
+        self.color_correction = correction;
+    }
+
+    /// Whether the frame currently being drawn will be presented on screen
+    ///
+    /// The first frame after the LCD is switched on is not displayed
+    /// by the hardware, the screen stays blank until the next one.
+    pub fn frame_presented(&self) -> bool {
+        self.is_lcd_enabled() && !self.first_frame
+    }
+
     pub fn read_lcdc(&self) -> u8 {
         self.lcdc
     }
@@ -173,11 +358,37 @@ impl Ppu {
         self.lcdc = value;
 
         if lcd_was_on && !lcd_now_on {
+            // Carry the time since the last VBlank over so frames keep
+            // coming at the same points in time
+            let position = self.ly as u64 * DOTS_PER_LINE as u64 + self.dots as u64;
+            let vblank_start = SCREEN_HEIGHT as u64 * DOTS_PER_LINE as u64;
+            self.off_dots = (position + DOTS_PER_FRAME - vblank_start) % DOTS_PER_FRAME;
             self.ly = 0;
             self.dots = 0;
-            self.mode = Mode::OamSearch;
-            self.stat = (self.stat & 0xFC) | (Mode::OamSearch as u8);
+            self.mode = Mode::HBlank;
+            self.stat &= 0xFC;
+            self.first_line = false;
+            self.first_frame = false;
+
+            // A disabled LCD shows a blank (white) screen
+            let blank = blank_color(self.model);
+            for row in self.framebuffer.iter_mut() {
+                row.fill(blank);
+            }
+        } else if !lcd_was_on && lcd_now_on {
+            // Line 0 restarts in mode 0 without an OAM search, then goes
+            // straight to pixel transfer. The first M-cycle is already
+            // under way when the write completes.
+            self.ly = 0;
+            self.dots = 4;
+            self.mode = Mode::HBlank;
+            self.stat &= 0xFC;
+            self.first_line = true;
+            self.first_frame = true;
+            self.update_lyc_flag();
         }
+
+        self.update_stat_line();
     }
 
     pub fn read_stat(&self) -> u8 {
@@ -185,7 +396,15 @@ impl Ppu {
     }
 
     pub fn write_stat(&mut self, value: u8) {
+        // DMG STAT write bug: for one cycle the write behaves as if every
+        // source was enabled, so an active mode 0/1 or LYC match fires
+        if !self.model.is_cgb() {
+            self.stat = (self.stat & 0x07) | 0x78;
+            self.update_stat_line();
+        }
+
         self.stat = (self.stat & 0x07) | (value & 0x78);
+        self.update_stat_line();
     }
 
     pub fn read_scy(&self) -> u8 {
@@ -257,6 +476,67 @@ impl Ppu {
         self.wx = value;
     }
 
+    /// Palette memory is inaccessible to the CPU during pixel transfer
+    fn palette_locked(&self) -> bool {
+        self.is_lcd_enabled() && self.mode == Mode::PixelTransfer
+    }
+
+    pub fn read_bcps(&self) -> u8 {
+        if self.model.is_cgb() {
+            self.bcps | 0x40
+        } else {
+            0xFF
+        }
+    }
+
+    pub fn write_bcps(&mut self, value: u8) {
+        if self.model.is_cgb() {
+            self.bcps = value & 0xBF;
+        }
+    }
+
+    pub fn read_bcpd(&self) -> u8 {
+        if !self.model.is_cgb() || self.palette_locked() {
+            return 0xFF;
+        }
+        self.bg_palette_ram[(self.bcps & 0x3F) as usize]
+    }
+
+    pub fn write_bcpd(&mut self, value: u8) {
+        if self.model.is_cgb() {
+            let locked = self.palette_locked();
+            write_palette_data(&mut self.bcps, &mut self.bg_palette_ram, value, locked);
+        }
+    }
+
+    pub fn read_ocps(&self) -> u8 {
+        if self.model.is_cgb() {
+            self.ocps | 0x40
+        } else {
+            0xFF
+        }
+    }
+
+    pub fn write_ocps(&mut self, value: u8) {
+        if self.model.is_cgb() {
+            self.ocps = value & 0xBF;
+        }
+    }
+
+    pub fn read_ocpd(&self) -> u8 {
+        if !self.model.is_cgb() || self.palette_locked() {
+            return 0xFF;
+        }
+        self.obj_palette_ram[(self.ocps & 0x3F) as usize]
+    }
+
+    pub fn write_ocpd(&mut self, value: u8) {
+        if self.model.is_cgb() {
+            let locked = self.palette_locked();
+            write_palette_data(&mut self.ocps, &mut self.obj_palette_ram, value, locked);
+        }
+    }
+
     fn bg_window_enabled(&self) -> bool {
         self.lcdc & 0x01 != 0
     }
@@ -308,16 +588,17 @@ impl Ppu {
 
         let line = self.ly as usize;
 
-        for x in 0..SCREEN_WIDTH {
-            self.framebuffer[line][x] = 0;
-        }
+        self.framebuffer[line].fill(blank_color(self.model));
+        self.bg_line.fill(BgPixel::default());
 
-        if self.bg_window_enabled() {
+        // On CGB, LCDC bit 0 only removes background priority over
+        // sprites; the background and window are always drawn
+        if self.bg_window_enabled() || self.model.is_cgb() {
             self.render_background(line, vram);
-        }
 
-        if self.window_enabled() && self.bg_window_enabled() {
-            self.render_window(line, vram);
+            if self.window_enabled() {
+                self.render_window(line, vram);
+            }
         }
 
         if self.sprites_enabled() {
@@ -326,32 +607,15 @@ impl Ppu {
     }
 
     fn render_background(&mut self, line: usize, vram: &[u8]) {
-        let (tile_data_base, is_signed) = self.bg_window_tile_data_area();
         let tile_map_base = self.bg_tile_map_area();
-
         let bg_y = self.scy.wrapping_add(line as u8) as usize;
-        let tile_row = bg_y / 8;
-        let tile_y_offset = bg_y % 8;
 
         for x in 0..SCREEN_WIDTH {
             let bg_x = self.scx.wrapping_add(x as u8) as usize;
-            let tile_col = bg_x / 8;
-            let tile_x_offset = bg_x % 8;
+            let (pixel, bg) = self.fetch_bg_pixel(vram, tile_map_base, bg_x, bg_y);
 
-            let tile_map_addr = tile_map_base + ((tile_row % 32) * 32) as u16 + (tile_col % 32) as u16;
-            let tile_index = vram[(tile_map_addr - 0x8000) as usize];
-
-            let tile_addr = if is_signed {
-                let offset = (tile_index as i8 as i16) * 16;
-                (0x9000u16 as i16 + offset) as u16
-            } else {
-                tile_data_base + (tile_index as u16 * 16)
-            };
-
-            let color = self.get_tile_pixel(vram, tile_addr, tile_x_offset, tile_y_offset);
-            let palette_color = self.apply_palette(color, self.bgp);
-
-            self.framebuffer[line][x] = palette_color;
+            self.framebuffer[line][x] = pixel;
+            self.bg_line[x] = bg;
         }
     }
 
@@ -360,39 +624,84 @@ impl Ppu {
             return;
         }
 
-        let window_x_start = if self.wx >= 7 { self.wx - 7 } else { 0 };
+        let window_x_start = self.wx.saturating_sub(7);
 
         if window_x_start >= 160 {
             return;
         }
 
-        let (tile_data_base, is_signed) = self.bg_window_tile_data_area();
         let tile_map_base = self.window_tile_map_area();
-
         let window_y = (line as u8).wrapping_sub(self.wy) as usize;
-        let tile_row = window_y / 8;
-        let tile_y_offset = window_y % 8;
 
         for screen_x in window_x_start as usize..SCREEN_WIDTH {
             let window_x = screen_x - window_x_start as usize;
-            let tile_col = window_x / 8;
-            let tile_x_offset = window_x % 8;
+            let (pixel, bg) = self.fetch_bg_pixel(vram, tile_map_base, window_x, window_y);
 
-            let tile_map_addr = tile_map_base + ((tile_row % 32) * 32) as u16 + (tile_col % 32) as u16;
-            let tile_index = vram[(tile_map_addr - 0x8000) as usize];
+            self.framebuffer[line][screen_x] = pixel;
+            self.bg_line[screen_x] = bg;
+        }
+    }
 
-            let tile_addr = if is_signed {
-                let offset = (tile_index as i8 as i16) * 16;
-                (0x9000u16 as i16 + offset) as u16
-            } else {
-                tile_data_base + (tile_index as u16 * 16)
-            };
+    /// Fetch one background/window pixel at (x, y) in the 256x256 tile map
+    ///
+    /// On CGB the tile map entry has an attribute byte at the same address
+    /// in VRAM bank 1:
+    /// Bit 7: BG-to-OAM priority
+    /// Bit 6: Y flip
+    /// Bit 5: X flip
+    /// Bit 3: Tile data VRAM bank
+    /// Bit 0-2: Background palette
+    ///
+    /// Returns the output pixel and the raw color/priority for sprite mixing.
+    fn fetch_bg_pixel(
+        &self,
+        vram: &[u8],
+        tile_map_base: u16,
+        x: usize,
+        y: usize,
+    ) -> (u16, BgPixel) {
+        let (tile_data_base, is_signed) = self.bg_window_tile_data_area();
+
+        let tile_map_addr = tile_map_base + ((y / 8 % 32) * 32 + (x / 8 % 32)) as u16;
+        let map_offset = (tile_map_addr - 0x8000) as usize;
+        let tile_index = vram[map_offset];
+        let attributes = if self.model.is_cgb() {
+            vram[0x2000 + map_offset]
+        } else {
+            0
+        };
 
-            let color = self.get_tile_pixel(vram, tile_addr, tile_x_offset, tile_y_offset);
-            let palette_color = self.apply_palette(color, self.bgp);
+        let tile_addr = if is_signed {
+            let offset = (tile_index as i8 as i16) * 16;
+            (0x9000u16 as i16 + offset) as u16
+        } else {
+            tile_data_base + (tile_index as u16 * 16)
+        };
 
-            self.framebuffer[line][screen_x] = palette_color;
+        let mut tile_x_offset = x % 8;
+        let mut tile_y_offset = y % 8;
+        if attributes & 0x20 != 0 {
+            tile_x_offset = 7 - tile_x_offset;
+        }
+        if attributes & 0x40 != 0 {
+            tile_y_offset = 7 - tile_y_offset;
         }
+
+        let bank = ((attributes >> 3) & 0x01) as usize;
+        let color = self.get_tile_pixel(vram, bank, tile_addr, tile_x_offset, tile_y_offset);
+
+        let pixel = if self.model.is_cgb() {
+            cgb_color(&self.bg_palette_ram, attributes & 0x07, color)
+        } else {
+            self.apply_palette(color, self.bgp) as u16
+        };
+
+        let bg = BgPixel {
+            color,
+            priority: attributes & 0x80 != 0,
+        };
+
+        (pixel, bg)
     }
 
     pub fn scan_oam(&mut self, oam: &[u8]) {
@@ -401,8 +710,7 @@ impl Ppu {
         let sprite_height = self.sprite_size();
         let line = self.ly as i16;
 
-        let mut candidates = Vec::with_capacity(40);
-
+        // Only the first 10 sprites (in OAM order) on the line are selected
         for i in 0..40 {
             let oam_addr = i * 4;
             let y = oam[oam_addr] as i16 - 16;
@@ -411,19 +719,23 @@ impl Ppu {
             let attributes = oam[oam_addr + 3];
 
             if line >= y && line < y + sprite_height as i16 {
-                candidates.push((i, SpriteData {
+                self.sprite_buffer.push(SpriteData {
                     y,
                     x,
                     tile_index,
                     attributes,
-                }));
+                });
+
+                if self.sprite_buffer.len() == 10 {
+                    break;
+                }
             }
         }
 
-        candidates.sort_by_key(|(oam_index, sprite)| (sprite.x, *oam_index));
-
-        for (_, sprite) in candidates.into_iter().take(10) {
-            self.sprite_buffer.push(sprite);
+        // DMG: smaller X has priority, then OAM order (stable sort).
+        // CGB: priority is given by OAM order only.
+        if !self.model.is_cgb() {
+            self.sprite_buffer.sort_by_key(|sprite| sprite.x);
         }
     }
 
@@ -433,7 +745,12 @@ impl Ppu {
         }
 
         let sprite_height = self.sprite_size();
+        let cgb = self.model.is_cgb();
+
+        // On CGB, clearing LCDC bit 0 puts sprites above everything
+        let bg_can_cover = !cgb || self.bg_window_enabled();
 
+        // Draw lowest priority first so higher priority sprites end up on top
         for sprite in self.sprite_buffer.iter().rev() {
             if sprite.x < -7 || sprite.x >= 160 {
                 continue;
@@ -467,36 +784,55 @@ impl Ppu {
                 self.obp0
             };
 
+            // CGB: bit 3 selects the tile VRAM bank, bits 0-2 the palette
+            let bank = if cgb {
+                ((sprite.attributes >> 3) & 0x01) as usize
+            } else {
+                0
+            };
+
             let x_flip = sprite.attributes & 0x20 != 0;
             let bg_priority = sprite.attributes & 0x80 != 0;
 
             for pixel_x in 0..8 {
                 let screen_x = sprite.x + pixel_x;
-                if screen_x < 0 || screen_x >= 160 {
+                if !(0..160).contains(&screen_x) {
                     continue;
                 }
 
-                let tile_x_offset = if x_flip { 7 - pixel_x as usize } else { pixel_x as usize };
-                let color = self.get_tile_pixel(vram, tile_addr, tile_x_offset, tile_y_offset);
+                let tile_x_offset = if x_flip {
+                    7 - pixel_x as usize
+                } else {
+                    pixel_x as usize
+                };
+                let color =
+                    self.get_tile_pixel(vram, bank, tile_addr, tile_x_offset, tile_y_offset);
 
                 if color == 0 {
                     continue;
                 }
 
-                if bg_priority && self.framebuffer[line][screen_x as usize] != 0 {
+                // Background colors 1-3 cover the sprite when either the
+                // sprite or (CGB) the tile map entry requests it
+                let bg = self.bg_line[screen_x as usize];
+                if bg_can_cover && bg.color != 0 && (bg_priority || bg.priority) {
                     continue;
                 }
 
-                let palette_color = self.apply_palette(color, palette);
-                self.framebuffer[line][screen_x as usize] = palette_color;
+                let pixel = if cgb {
+                    cgb_color(&self.obj_palette_ram, sprite.attributes & 0x07, color)
+                } else {
+                    self.apply_palette(color, palette) as u16
+                };
+                self.framebuffer[line][screen_x as usize] = pixel;
             }
         }
     }
 
-    fn get_tile_pixel(&self, vram: &[u8], tile_addr: u16, x: usize, y: usize) -> u8 {
-        let row_addr = tile_addr + (y as u16 * 2);
-        let low_byte = vram[(row_addr - 0x8000) as usize];
-        let high_byte = vram[(row_addr - 0x8000 + 1) as usize];
+    fn get_tile_pixel(&self, vram: &[u8], bank: usize, tile_addr: u16, x: usize, y: usize) -> u8 {
+        let row_offset = bank * 0x2000 + (tile_addr - 0x8000) as usize + y * 2;
+        let low_byte = vram[row_offset];
+        let high_byte = vram[row_offset + 1];
 
         let bit_pos = 7 - x;
         let low_bit = (low_byte >> bit_pos) & 1;
@@ -516,6 +852,34 @@ impl Ppu {
     }
 }
 
+/// Framebuffer value shown for a blank (white) pixel
+fn blank_color(model: Model) -> u16 {
+    match model {
+        Model::Dmg | Model::Sgb => 0,
+        Model::Cgb => 0x7FFF,
+    }
+}
+
+/// Look up a 15-bit color in CGB palette memory
+fn cgb_color(palette_ram: &[u8; 64], palette: u8, color: u8) -> u16 {
+    let index = (palette as usize * 4 + color as usize) * 2;
+    u16::from_le_bytes([palette_ram[index], palette_ram[index + 1]]) & 0x7FFF
+}
+
+/// Write to BCPD/OCPD through the matching index register
+///
+/// Bit 7 of the index register enables auto-increment after each write,
+/// which also happens when the write itself is blocked.
+fn write_palette_data(spec: &mut u8, palette_ram: &mut [u8; 64], value: u8, locked: bool) {
+    if !locked {
+        palette_ram[(*spec & 0x3F) as usize] = value;
+    }
+
+    if *spec & 0x80 != 0 {
+        *spec = 0x80 | (spec.wrapping_add(1) & 0x3F);
+    }
+}
+
 impl Default for Ppu {
     fn default() -> Self {
         Self::new()
@@ -650,4 +1014,419 @@ mod tests {
         // LYC flag should be set when LY == LYC
         assert_eq!(ppu.read_stat() & 0x04, 0x04);
     }
+
+    /// Step until the PPU reaches the given line and dot
+    fn step_to(ppu: &mut Ppu, ly: u8, dot: u16) {
+        while ppu.read_ly() != ly || ppu.dots != dot {
+            ppu.step(1);
+        }
+    }
+
+    #[test]
+    fn test_lyc_compared_at_dot_4() {
+        let mut ppu = Ppu::new();
+        ppu.write_lyc(5);
+
+        step_to(&mut ppu, 5, 0);
+        assert_eq!(ppu.read_stat() & 0x04, 0);
+
+        ppu.step(3);
+        assert_eq!(ppu.read_stat() & 0x04, 0);
+
+        ppu.step(1);
+        assert_eq!(ppu.read_stat() & 0x04, 0x04);
+
+        // Flag clears again at the start of the next line
+        step_to(&mut ppu, 6, 0);
+        assert_eq!(ppu.read_stat() & 0x04, 0);
+    }
+
+    #[test]
+    fn test_stat_lyc_interrupt_fires_once() {
+        let mut ppu = Ppu::new();
+        ppu.write_lyc(5);
+        ppu.write_stat(0x40);
+        ppu.stat_interrupt = false;
+
+        step_to(&mut ppu, 5, 3);
+        assert!(!ppu.stat_interrupt);
+
+        ppu.step(1);
+        assert!(ppu.stat_interrupt);
+        ppu.stat_interrupt = false;
+
+        // The line stays high for the rest of the line: no repeated request
+        step_to(&mut ppu, 5, 455);
+        assert!(!ppu.stat_interrupt);
+    }
+
+    #[test]
+    fn test_stat_blocking_mode2_then_lyc() {
+        let mut ppu = Ppu::new();
+        ppu.write_lyc(5);
+        ppu.write_stat(0x60); // LYC + mode 2 sources
+
+        step_to(&mut ppu, 5, 0);
+        ppu.stat_interrupt = false;
+
+        // Mode 2 already holds the line high, so the LYC match is blocked
+        ppu.step(LYC_COMPARE_DOT as u64);
+        assert_eq!(ppu.read_stat() & 0x04, 0x04);
+        assert!(!ppu.stat_interrupt);
+    }
+
+    #[test]
+    fn test_stat_blocking_hblank_then_vblank() {
+        let mut ppu = Ppu::new();
+        ppu.write_stat(0x18); // HBlank + VBlank sources
+
+        step_to(&mut ppu, 143, 252);
+        assert_eq!(ppu.mode(), Mode::HBlank);
+        ppu.stat_interrupt = false;
+
+        // HBlank of line 143 runs straight into VBlank without the line dropping
+        step_to(&mut ppu, 144, 0);
+        assert_eq!(ppu.mode(), Mode::VBlank);
+        assert!(!ppu.stat_interrupt);
+    }
+
+    #[test]
+    fn test_stat_hblank_interrupt_every_line() {
+        let mut ppu = Ppu::new();
+        ppu.write_stat(0x08);
+
+        for line in 1..4 {
+            step_to(&mut ppu, line, 251);
+            ppu.stat_interrupt = false;
+            ppu.step(1);
+            assert_eq!(ppu.mode(), Mode::HBlank);
+            assert!(ppu.stat_interrupt, "no HBlank interrupt on line {}", line);
+        }
+    }
+
+    #[test]
+    fn test_stat_oam_source_fires_at_line_144() {
+        let mut ppu = Ppu::new();
+        ppu.write_stat(0x20); // Only the mode 2 source
+
+        step_to(&mut ppu, 143, 455);
+        ppu.stat_interrupt = false;
+
+        ppu.step(1);
+        assert_eq!(ppu.read_ly(), 144);
+        assert_eq!(ppu.mode(), Mode::VBlank);
+        assert!(ppu.stat_interrupt);
+
+        // No further mode 2 requests during the rest of VBlank
+        ppu.stat_interrupt = false;
+        step_to(&mut ppu, 153, 455);
+        assert!(!ppu.stat_interrupt);
+    }
+
+    #[test]
+    fn test_stat_write_bug_in_hblank() {
+        let mut ppu = Ppu::new();
+        ppu.write_lyc(100);
+        assert_eq!(ppu.mode(), Mode::HBlank);
+        ppu.stat_interrupt = false;
+
+        // Writing zero still raises the line for one cycle during HBlank
+        ppu.write_stat(0x00);
+        assert!(ppu.stat_interrupt);
+    }
+
+    #[test]
+    fn test_stat_write_bug_not_in_pixel_transfer() {
+        let mut ppu = Ppu::new();
+        ppu.write_lyc(100);
+
+        step_to(&mut ppu, 1, 100);
+        assert_eq!(ppu.mode(), Mode::PixelTransfer);
+        ppu.stat_interrupt = false;
+
+        ppu.write_stat(0x00);
+        assert!(!ppu.stat_interrupt);
+    }
+
+    #[test]
+    fn test_lcd_off_resets_to_mode_0() {
+        let mut ppu = Ppu::new();
+        step_to(&mut ppu, 10, 100);
+
+        ppu.write_lcdc(0x11);
+        assert_eq!(ppu.read_ly(), 0);
+        assert_eq!(ppu.mode(), Mode::HBlank);
+        assert_eq!(ppu.read_stat() & 0x03, 0);
+
+        // PPU does not advance while the LCD is off
+        ppu.step(456 * 3);
+        assert_eq!(ppu.read_ly(), 0);
+    }
+
+    #[test]
+    fn test_lcd_off_keeps_frame_time() {
+        let mut ppu = Ppu::new();
+        let to_vblank = SCREEN_HEIGHT as u64 * DOTS_PER_LINE as u64 - ppu.dots as u64;
+        ppu.write_lcdc(0x11);
+        ppu.frame_ready = false;
+
+        // Switched off on line 0: the next frame is due when line 144 would
+        // have started
+        ppu.step(to_vblank - 1);
+        assert!(!ppu.frame_ready);
+
+        ppu.step(1);
+        assert!(ppu.frame_ready);
+        assert!(!ppu.vblank_interrupt);
+    }
+
+    #[test]
+    fn test_lcd_off_in_vblank_keeps_frame_time() {
+        let mut ppu = Ppu::new();
+        step_to(&mut ppu, 144, 0);
+        assert!(ppu.frame_ready);
+        ppu.frame_ready = false;
+
+        // Switch off 100 dots into VBlank: the next frame is a whole frame
+        // after the last one
+        ppu.step(100);
+        ppu.write_lcdc(0x11);
+        ppu.step(DOTS_PER_FRAME - 101);
+        assert!(!ppu.frame_ready);
+
+        ppu.step(1);
+        assert!(ppu.frame_ready);
+    }
+
+    #[test]
+    fn test_lcd_on_first_line_timing() {
+        let mut ppu = Ppu::new();
+        ppu.write_lcdc(0x11);
+        ppu.write_lcdc(0x91);
+
+        // First line starts in mode 0, one M-cycle in, and skips OAM search
+        assert_eq!(ppu.mode(), Mode::HBlank);
+        ppu.step(75);
+        assert_eq!(ppu.mode(), Mode::HBlank);
+        ppu.step(1);
+        assert_eq!(ppu.mode(), Mode::PixelTransfer);
+        ppu.step(172);
+        assert_eq!(ppu.mode(), Mode::HBlank);
+
+        // Following lines are regular
+        ppu.step(204);
+        assert_eq!(ppu.read_ly(), 1);
+        assert_eq!(ppu.mode(), Mode::OamSearch);
+    }
+
+    #[test]
+    fn test_lcd_on_first_frame_not_presented() {
+        let mut ppu = Ppu::new();
+        ppu.write_lcdc(0x11);
+        ppu.write_lcdc(0x91);
+        assert!(!ppu.frame_presented());
+
+        // Lines are not rendered during the first frame
+        step_to(&mut ppu, 0, 252);
+        assert!(!ppu.should_render_scanline);
+
+        step_to(&mut ppu, 144, 0);
+        assert!(ppu.vblank_interrupt);
+        assert!(!ppu.frame_ready);
+        assert!(ppu.frame_presented());
+
+        // The second frame is displayed normally
+        step_to(&mut ppu, 0, 252);
+        assert!(ppu.should_render_scanline);
+        step_to(&mut ppu, 144, 0);
+        assert!(ppu.frame_ready);
+    }
+
+    #[test]
+    fn test_lcd_off_blanks_framebuffer() {
+        let mut ppu = Ppu::new();
+        ppu.framebuffer[10][10] = 3;
+
+        ppu.write_lcdc(0x11);
+        assert_eq!(ppu.framebuffer()[10][10], 0);
+    }
+
+    /// Write a 15-bit color into CGB palette memory through BCPS/BCPD or OCPS/OCPD
+    fn write_cgb_color(ppu: &mut Ppu, obj: bool, palette: u8, color: u8, value: u16) {
+        let index = 0x80 | (palette * 8 + color * 2);
+        let [low, high] = value.to_le_bytes();
+        if obj {
+            ppu.write_ocps(index);
+            ppu.write_ocpd(low);
+            ppu.write_ocpd(high);
+        } else {
+            ppu.write_bcps(index);
+            ppu.write_bcpd(low);
+            ppu.write_bcpd(high);
+        }
+    }
+
+    /// Fill row 0 of a tile with the given color index in the given VRAM bank
+    fn fill_tile_row(vram: &mut [u8], bank: usize, tile: usize, color: u8) {
+        let offset = bank * 0x2000 + tile * 16;
+        vram[offset] = if color & 0x01 != 0 { 0xFF } else { 0x00 };
+        vram[offset + 1] = if color & 0x02 != 0 { 0xFF } else { 0x00 };
+    }
+
+    #[test]
+    fn test_cgb_palette_auto_increment() {
+        let mut ppu = Ppu::with_model(Model::Cgb);
+
+        ppu.write_bcps(0x80 | 0x3E);
+        ppu.write_bcpd(0x12);
+        ppu.write_bcpd(0x34);
+        assert_eq!(ppu.read_bcps(), 0xC0); // Wrapped to index 0
+        assert_eq!(ppu.read_bcpd(), 0xFF); // Initial white
+
+        ppu.write_bcps(0x3E);
+        assert_eq!(ppu.read_bcpd(), 0x12);
+        ppu.write_bcps(0x3F);
+        assert_eq!(ppu.read_bcpd(), 0x34);
+
+        // Without auto-increment the index stays put
+        ppu.write_ocps(0x05);
+        ppu.write_ocpd(0xAA);
+        ppu.write_ocpd(0xBB);
+        assert_eq!(ppu.read_ocps(), 0x45);
+        assert_eq!(ppu.read_ocpd(), 0xBB);
+    }
+
+    #[test]
+    fn test_cgb_palette_locked_during_pixel_transfer() {
+        let mut ppu = Ppu::with_model(Model::Cgb);
+        step_to(&mut ppu, 1, 100);
+        assert_eq!(ppu.mode(), Mode::PixelTransfer);
+
+        ppu.write_bcps(0x80);
+        ppu.write_bcpd(0x00);
+        assert_eq!(ppu.read_bcpd(), 0xFF);
+        // Index still increments on a blocked write
+        assert_eq!(ppu.read_bcps(), 0xC1);
+
+        step_to(&mut ppu, 1, 252);
+        ppu.write_bcps(0x00);
+        assert_eq!(ppu.read_bcpd(), 0xFF);
+    }
+
+    #[test]
+    fn test_dmg_has_no_cgb_palettes() {
+        let mut ppu = Ppu::new();
+        ppu.write_bcps(0x80);
+        ppu.write_bcpd(0x00);
+        assert_eq!(ppu.read_bcps(), 0xFF);
+        assert_eq!(ppu.read_bcpd(), 0xFF);
+        assert_eq!(ppu.read_ocps(), 0xFF);
+        assert_eq!(ppu.read_ocpd(), 0xFF);
+    }
+
+    #[test]
+    fn test_cgb_background_attributes() {
+        let mut ppu = Ppu::with_model(Model::Cgb);
+        let mut vram = vec![0; 0x4000];
+        let oam = vec![0; 0xA0];
+
+        // Tile map entry 0 uses tile 1 from VRAM bank 1 with palette 2
+        vram[0x1800] = 0x01;
+        vram[0x3800] = 0x0A;
+        fill_tile_row(&mut vram, 1, 1, 1);
+        write_cgb_color(&mut ppu, false, 2, 1, 0x001F);
+
+        // Tile map entry 1 uses tile 2 with X flip, only its leftmost pixel set
+        vram[0x1801] = 0x02;
+        vram[0x3801] = 0x20;
+        vram[2 * 16] = 0x80;
+        write_cgb_color(&mut ppu, false, 0, 1, 0x03E0);
+
+        ppu.render_scanline(&vram, &oam);
+
+        let line = ppu.framebuffer()[0];
+        assert!(line[0..8].iter().all(|&pixel| pixel == 0x001F));
+        assert_eq!(line[8], 0x7FFF);
+        assert_eq!(line[15], 0x03E0);
+    }
+
+    #[test]
+    fn test_sprite_priority_dmg_by_x_cgb_by_oam_index() {
+        for model in [Model::Dmg, Model::Sgb, Model::Cgb] {
+            let mut ppu = Ppu::with_model(model);
+            ppu.write_lcdc(0x93); // Sprites enabled
+            ppu.write_obp0(0xC0); // Color 3 -> shade 3
+            ppu.write_obp1(0x40); // Color 3 -> shade 1
+            write_cgb_color(&mut ppu, true, 0, 3, 0x001F);
+            write_cgb_color(&mut ppu, true, 1, 3, 0x7C00);
+
+            let mut vram = vec![0; 0x4000];
+            let mut oam = vec![0; 0xA0];
+            fill_tile_row(&mut vram, 0, 2, 3);
+
+            // Sprite 0 at x=10 (palette 0), sprite 1 at x=5 (palette 1)
+            oam[0..4].copy_from_slice(&[16, 18, 2, 0x00]);
+            oam[4..8].copy_from_slice(&[16, 13, 2, 0x11]);
+
+            ppu.scan_oam(&oam);
+            ppu.render_scanline(&vram, &oam);
+
+            let pixel = ppu.framebuffer()[0][10];
+            match model {
+                Model::Dmg | Model::Sgb => assert_eq!(pixel, 1, "smaller X wins on DMG"),
+                Model::Cgb => assert_eq!(pixel, 0x001F, "lower OAM index wins on CGB"),
+            }
+        }
+    }
+
+    #[test]
+    fn test_cgb_bg_priority_and_master_priority() {
+        let mut ppu = Ppu::with_model(Model::Cgb);
+        ppu.write_lcdc(0x93);
+        write_cgb_color(&mut ppu, false, 0, 1, 0x0001);
+        write_cgb_color(&mut ppu, true, 0, 3, 0x001F);
+
+        let mut vram = vec![0; 0x4000];
+        let mut oam = vec![0; 0xA0];
+
+        // Background tile 0 is color 1 with the BG-to-OAM priority bit set
+        fill_tile_row(&mut vram, 0, 0, 1);
+        vram[0x3800] = 0x80;
+        fill_tile_row(&mut vram, 0, 2, 3);
+        oam[0..4].copy_from_slice(&[16, 8, 2, 0x00]);
+
+        ppu.scan_oam(&oam);
+        ppu.render_scanline(&vram, &oam);
+        assert_eq!(ppu.framebuffer()[0][0], 0x0001);
+
+        // Clearing LCDC bit 0 gives sprites priority but keeps the background
+        ppu.write_lcdc(0x92);
+        ppu.render_scanline(&vram, &oam);
+        assert_eq!(ppu.framebuffer()[0][0], 0x001F);
+        assert_eq!(ppu.framebuffer()[0][8], 0x0001);
+    }
+
+    #[test]
+    fn test_cgb_stat_write_has_no_bug() {
+        let mut ppu = Ppu::with_model(Model::Cgb);
+        ppu.write_lyc(100);
+        ppu.stat_interrupt = false;
+
+        ppu.write_stat(0x00);
+        assert!(!ppu.stat_interrupt);
+    }
+
+    #[test]
+    fn test_framebuffer_rgba_uses_palette() {
+        let mut ppu = Ppu::new();
+        let mut rgba = vec![0; video::RGBA_FRAME_SIZE];
+
+        ppu.framebuffer_rgba(&mut rgba);
+        let [r, g, b] = Palette::CLASSIC_GREEN.colors[0];
+        assert_eq!(rgba[0..4], [r, g, b, 0xFF]);
+
+        ppu.set_palette(Palette::GRAYSCALE);
+        ppu.framebuffer_rgba(&mut rgba);
+        assert_eq!(rgba[0..4], [0xFF, 0xFF, 0xFF, 0xFF]);
+    }
 }
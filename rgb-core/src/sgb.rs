@@ -0,0 +1,675 @@
+/// Super Game Boy support
+///
+/// The SGB runs DMG hardware inside a SNES. Games talk to the SNES side by
+/// bit-banging command packets through P1 (0xFF00):
+/// - P14 and P15 low: reset pulse, starts a packet
+/// - P14 low: 0 bit, P15 low: 1 bit (both lines go high between pulses)
+/// - 16 bytes are sent LSB first, followed by a 0 stop bit
+///
+/// The first byte of a command holds the command code (bits 3-7) and the
+/// number of packets it spans (bits 0-2). Bulk data (palettes, border tiles)
+/// is sent by displaying it on screen for a frame (VRAM transfer).
+///
+/// The output is 256x224: the 160x144 game screen centered in the border.
+use std::collections::VecDeque;
+
+use crate::ppu::{Framebuffer, SCREEN_HEIGHT, SCREEN_WIDTH};
+use crate::video::ColorCorrection;
+
+/// Output size including the border
+pub const SGB_WIDTH: usize = 256;
+pub const SGB_HEIGHT: usize = 224;
+
+/// Size in bytes of an RGBA frame with the border
+pub const SGB_RGBA_FRAME_SIZE: usize = SGB_WIDTH * SGB_HEIGHT * 4;
+
+/// Position of the game screen within the border
+const SCREEN_X: usize = 48;
+const SCREEN_Y: usize = 40;
+
+/// Attribute map size (one palette number per 8x8 tile of the game screen)
+const ATTR_WIDTH: usize = SCREEN_WIDTH / 8;
+const ATTR_HEIGHT: usize = SCREEN_HEIGHT / 8;
+
+/// Bytes sent with each VRAM transfer
+const TRANSFER_SIZE: usize = 0x1000;
+
+/// Border tile map size (32x32 entries, of which 32x28 are visible)
+const BORDER_MAP_SIZE: usize = 32 * 32;
+
+pub const PACKET_SIZE: usize = 16;
+
+/// A single 16-byte command packet
+pub type Packet = [u8; PACKET_SIZE];
+
+// Command codes
+const PAL01: u8 = 0x00;
+const PAL23: u8 = 0x01;
+const PAL03: u8 = 0x02;
+const PAL12: u8 = 0x03;
+const ATTR_BLK: u8 = 0x04;
+const ATTR_LIN: u8 = 0x05;
+const ATTR_DIV: u8 = 0x06;
+const ATTR_CHR: u8 = 0x07;
+const PAL_SET: u8 = 0x0A;
+const PAL_TRN: u8 = 0x0B;
+const MLT_REQ: u8 = 0x11;
+const CHR_TRN: u8 = 0x13;
+const PCT_TRN: u8 = 0x14;
+
+/// SGB palette 1-A, shown until the game sets its own colors
+const DEFAULT_PALETTE: [u16; 4] = [0x67BF, 0x265B, 0x10B5, 0x2866];
+
+/// Decodes command packets from writes to P1
+#[derive(Debug, Default)]
+pub struct PacketReceiver {
+    packet: Packet,
+    /// Bits received in the current packet
+    bits: usize,
+    /// A reset pulse started a packet that is not finished yet
+    receiving: bool,
+    /// Both lines went high since the last pulse
+    ready_for_bit: bool,
+    /// Complete packets waiting to be handled
+    packets: VecDeque<Packet>,
+}
+
+impl PacketReceiver {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Feed a value written to P1 (only bits 4-5 matter)
+    pub fn write(&mut self, value: u8) {
+        match value & 0x30 {
+            0x30 => self.ready_for_bit = true,
+            0x00 => {
+                self.packet = [0; PACKET_SIZE];
+                self.bits = 0;
+                self.receiving = true;
+                self.ready_for_bit = false;
+            }
+            lines => {
+                if !self.receiving || !self.ready_for_bit {
+                    return;
+                }
+                self.ready_for_bit = false;
+
+                // P15 low sends a 1, P14 low sends a 0
+                let bit = lines == 0x10;
+
+                if self.bits == PACKET_SIZE * 8 {
+                    // Stop bit: a 1 here aborts the packet
+                    if !bit {
+                        self.packets.push_back(self.packet);
+                    }
+                    self.receiving = false;
+                } else {
+                    if bit {
+                        self.packet[self.bits / 8] |= 1 << (self.bits % 8);
+                    }
+                    self.bits += 1;
+                }
+            }
+        }
+    }
+
+    /// Take the next complete packet
+    pub fn take_packet(&mut self) -> Option<Packet> {
+        self.packets.pop_front()
+    }
+}
+
+/// Data expected by the next VRAM transfer
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Transfer {
+    /// PAL_TRN: 512 system palettes
+    Palettes,
+    /// CHR_TRN: border tiles 0x00-0x7F (0) or 0x80-0xFF (1)
+    BorderTiles(usize),
+    /// PCT_TRN: border tile map and palettes
+    BorderMap,
+}
+
+/// SNES-side state: palettes, attributes and border
+pub struct Sgb {
+    /// Palettes 0-3 used for the game screen (color 0 is shared by all four)
+    palettes: [[u16; 4]; 4],
+    /// System palettes uploaded with PAL_TRN and selected with PAL_SET
+    system_palettes: Box<[[u16; 4]; 512]>,
+    /// Palette number for each 8x8 tile of the game screen
+    attributes: [[u8; ATTR_WIDTH]; ATTR_HEIGHT],
+    /// Border tiles in SNES 4bpp format (32 bytes each)
+    border_tiles: Box<[u8; 256 * 32]>,
+    /// Border tile map: bits 0-7 tile, bits 10-12 palette (4-7),
+    /// bit 14 X flip, bit 15 Y flip
+    border_map: Box<[u16; BORDER_MAP_SIZE]>,
+    /// Border palettes 4-7, 16 colors each (color 0 is transparent)
+    border_colors: [u16; 64],
+    /// Packets of the command being received
+    command: Vec<u8>,
+    /// VRAM transfer that will read the next frame
+    pending_transfer: Option<Transfer>,
+    /// Number of controllers requested with MLT_REQ (1, 2 or 4)
+    players: u8,
+}
+
+impl Sgb {
+    pub fn new() -> Self {
+        Self {
+            palettes: [DEFAULT_PALETTE; 4],
+            system_palettes: Box::new([[0; 4]; 512]),
+            attributes: [[0; ATTR_WIDTH]; ATTR_HEIGHT],
+            border_tiles: Box::new([0; 256 * 32]),
+            border_map: Box::new([0; BORDER_MAP_SIZE]),
+            border_colors: [0; 64],
+            command: Vec::new(),
+            pending_transfer: None,
+            players: 1,
+        }
+    }
+
+    /// Number of controllers the game asked for
+    pub fn players(&self) -> u8 {
+        self.players
+    }
+
+    /// Handle a packet received through P1
+    ///
+    /// Commands spanning several packets run once the last one arrives.
+    pub fn handle_packet(&mut self, packet: &Packet) {
+        self.command.extend_from_slice(packet);
+
+        let length = (self.command[0] & 0x07).max(1) as usize;
+        if self.command.len() < length * PACKET_SIZE {
+            return;
+        }
+
+        let command = std::mem::take(&mut self.command);
+        self.execute(&command);
+    }
+
+    fn execute(&mut self, data: &[u8]) {
+        match data[0] >> 3 {
+            PAL01 => self.set_palette_pair(0, 1, data),
+            PAL23 => self.set_palette_pair(2, 3, data),
+            PAL03 => self.set_palette_pair(0, 3, data),
+            PAL12 => self.set_palette_pair(1, 2, data),
+            ATTR_BLK => self.attr_blk(data),
+            ATTR_LIN => self.attr_lin(data),
+            ATTR_DIV => self.attr_div(data),
+            ATTR_CHR => self.attr_chr(data),
+            PAL_SET => self.pal_set(data),
+            PAL_TRN => self.pending_transfer = Some(Transfer::Palettes),
+            MLT_REQ => {
+                self.players = match data[1] & 0x03 {
+                    0x01 => 2,
+                    0x03 => 4,
+                    _ => 1,
+                };
+            }
+            CHR_TRN => {
+                self.pending_transfer = Some(Transfer::BorderTiles((data[1] & 0x01) as usize))
+            }
+            PCT_TRN => self.pending_transfer = Some(Transfer::BorderMap),
+            _ => {} // Unsupported commands are ignored
+        }
+    }
+
+    /// PAL01/PAL23/PAL03/PAL12: shared color 0, then colors 1-3 of each palette
+    fn set_palette_pair(&mut self, first: usize, second: usize, data: &[u8]) {
+        self.set_shared_color(read_color(data, 1));
+        for i in 0..3 {
+            self.palettes[first][i + 1] = read_color(data, 3 + i * 2);
+            self.palettes[second][i + 1] = read_color(data, 9 + i * 2);
+        }
+    }
+
+    fn set_shared_color(&mut self, color: u16) {
+        for palette in &mut self.palettes {
+            palette[0] = color;
+        }
+    }
+
+    /// ATTR_BLK: assign palettes inside, on and outside of rectangles
+    fn attr_blk(&mut self, data: &[u8]) {
+        let count = (data[1] & 0x1F) as usize;
+
+        for set in data[2..].chunks_exact(6).take(count) {
+            let control = set[0] & 0x07;
+            let inside = set[1] & 0x03;
+            let outside = (set[1] >> 4) & 0x03;
+            // With only the inside or the outside selected, the border
+            // takes that palette too
+            let border = match control {
+                0x01 => Some(inside),
+                0x04 => Some(outside),
+                _ if control & 0x02 != 0 => Some((set[1] >> 2) & 0x03),
+                _ => None,
+            };
+
+            let (x1, y1) = (set[2] as usize & 0x1F, set[3] as usize & 0x1F);
+            let (x2, y2) = (set[4] as usize & 0x1F, set[5] as usize & 0x1F);
+
+            for (y, row) in self.attributes.iter_mut().enumerate() {
+                for (x, attribute) in row.iter_mut().enumerate() {
+                    let within = (x1..=x2).contains(&x) && (y1..=y2).contains(&y);
+                    let on_edge = within && (x == x1 || x == x2 || y == y1 || y == y2);
+
+                    let palette = if !within {
+                        (control & 0x04 != 0).then_some(outside)
+                    } else if on_edge {
+                        border
+                    } else {
+                        (control & 0x01 != 0).then_some(inside)
+                    };
+
+                    if let Some(palette) = palette {
+                        *attribute = palette;
+                    }
+                }
+            }
+        }
+    }
+
+    /// ATTR_LIN: assign a palette to whole rows or columns
+    fn attr_lin(&mut self, data: &[u8]) {
+        let count = data[1] as usize;
+
+        for &line in data[2..].iter().take(count) {
+            let number = (line & 0x1F) as usize;
+            let palette = (line >> 5) & 0x03;
+
+            if line & 0x80 != 0 {
+                if let Some(row) = self.attributes.get_mut(number) {
+                    row.fill(palette);
+                }
+            } else if number < ATTR_WIDTH {
+                for row in &mut self.attributes {
+                    row[number] = palette;
+                }
+            }
+        }
+    }
+
+    /// ATTR_DIV: split the screen in two along a row or column
+    fn attr_div(&mut self, data: &[u8]) {
+        let control = data[1];
+        let after = control & 0x03;
+        let before = (control >> 2) & 0x03;
+        let on_line = (control >> 4) & 0x03;
+        let horizontal = control & 0x40 != 0;
+        let line = (data[2] & 0x1F) as usize;
+
+        for (y, row) in self.attributes.iter_mut().enumerate() {
+            for (x, attribute) in row.iter_mut().enumerate() {
+                let position = if horizontal { y } else { x };
+                *attribute = match position.cmp(&line) {
+                    std::cmp::Ordering::Less => before,
+                    std::cmp::Ordering::Equal => on_line,
+                    std::cmp::Ordering::Greater => after,
+                };
+            }
+        }
+    }
+
+    /// ATTR_CHR: assign palettes tile by tile (4 tiles per byte, MSB first)
+    fn attr_chr(&mut self, data: &[u8]) {
+        let (mut x, mut y) = (data[1] as usize, data[2] as usize);
+        let count = u16::from_le_bytes([data[3], data[4]]) as usize;
+        let vertical = data[5] & 0x01 != 0;
+
+        for i in 0..count.min(ATTR_WIDTH * ATTR_HEIGHT) {
+            let Some(&byte) = data.get(6 + i / 4) else {
+                break;
+            };
+
+            if x < ATTR_WIDTH && y < ATTR_HEIGHT {
+                self.attributes[y][x] = (byte >> (6 - (i % 4) * 2)) & 0x03;
+            }
+
+            if vertical {
+                y += 1;
+                if y == ATTR_HEIGHT {
+                    y = 0;
+                    x += 1;
+                }
+            } else {
+                x += 1;
+                if x == ATTR_WIDTH {
+                    x = 0;
+                    y += 1;
+                }
+            }
+        }
+    }
+
+    /// PAL_SET: copy four system palettes into palettes 0-3
+    fn pal_set(&mut self, data: &[u8]) {
+        for (i, palette) in self.palettes.iter_mut().enumerate() {
+            let index = u16::from_le_bytes([data[1 + i * 2], data[2 + i * 2]]) & 0x01FF;
+            *palette = self.system_palettes[index as usize];
+        }
+
+        // Color 0 of the first palette is used for all four
+        self.set_shared_color(self.palettes[0][0]);
+    }
+
+    /// Called when the PPU finishes a frame, completes a pending VRAM transfer
+    pub fn frame_complete(&mut self, framebuffer: &Framebuffer) {
+        let Some(transfer) = self.pending_transfer.take() else {
+            return;
+        };
+        let data = screen_data(framebuffer);
+
+        match transfer {
+            Transfer::Palettes => {
+                for (palette, colors) in self.system_palettes.iter_mut().zip(data.chunks_exact(8)) {
+                    for (i, color) in palette.iter_mut().enumerate() {
+                        *color = read_color(colors, i * 2);
+                    }
+                }
+            }
+            Transfer::BorderTiles(half) => {
+                let start = half * TRANSFER_SIZE;
+                self.border_tiles[start..start + TRANSFER_SIZE].copy_from_slice(&data);
+            }
+            Transfer::BorderMap => {
+                for (i, entry) in self.border_map.iter_mut().enumerate() {
+                    *entry = u16::from_le_bytes([data[i * 2], data[i * 2 + 1]]);
+                }
+                for (i, color) in self.border_colors.iter_mut().enumerate() {
+                    *color = read_color(&data, 0x800 + i * 2);
+                }
+            }
+        }
+    }
+
+    /// Render the game screen inside the border as 256x224 RGBA
+    ///
+    /// `out` must hold at least `SGB_RGBA_FRAME_SIZE` bytes.
+    pub fn render_rgba(&self, framebuffer: &Framebuffer, out: &mut [u8]) {
+        assert!(
+            out.len() >= SGB_RGBA_FRAME_SIZE,
+            "RGBA buffer too small: {} bytes (need {})",
+            out.len(),
+            SGB_RGBA_FRAME_SIZE
+        );
+
+        for (i, rgba) in out
+            .chunks_exact_mut(4)
+            .take(SGB_WIDTH * SGB_HEIGHT)
+            .enumerate()
+        {
+            let color = self.pixel(framebuffer, i % SGB_WIDTH, i / SGB_WIDTH);
+            // The SNES outputs its 15-bit colors to a TV unchanged
+            let [r, g, b] = ColorCorrection::None.apply(color);
+            rgba.copy_from_slice(&[r, g, b, 0xFF]);
+        }
+    }
+
+    /// Color of an output pixel: opaque border pixels are drawn over the
+    /// game screen, everything else shows the screen or color 0
+    fn pixel(&self, framebuffer: &Framebuffer, x: usize, y: usize) -> u16 {
+        if let Some(color) = self.border_pixel(x, y) {
+            return color;
+        }
+
+        let screen_x = x.wrapping_sub(SCREEN_X);
+        let screen_y = y.wrapping_sub(SCREEN_Y);
+        if screen_x >= SCREEN_WIDTH || screen_y >= SCREEN_HEIGHT {
+            return self.palettes[0][0];
+        }
+
+        let shade = (framebuffer[screen_y][screen_x] & 0x03) as usize;
+        let palette = self.attributes[screen_y / 8][screen_x / 8] as usize;
+        self.palettes[palette][shade]
+    }
+
+    fn border_pixel(&self, x: usize, y: usize) -> Option<u16> {
+        let entry = self.border_map[(y / 8) * 32 + x / 8];
+        let tile = &self.border_tiles[(entry & 0xFF) as usize * 32..][..32];
+        let palette = ((entry >> 10) & 0x03) as usize;
+
+        let column = if entry & 0x4000 != 0 {
+            x % 8
+        } else {
+            7 - x % 8
+        };
+        let row = if entry & 0x8000 != 0 {
+            7 - y % 8
+        } else {
+            y % 8
+        };
+
+        // Bitplanes 0/1 are interleaved in the first 16 bytes, 2/3 in the rest
+        let index = (0..4).fold(0, |index, plane| {
+            let byte = tile[(plane / 2) * 16 + row * 2 + plane % 2];
+            index | (((byte >> column) & 0x01) as usize) << plane
+        });
+
+        (index != 0).then(|| self.border_colors[palette * 16 + index])
+    }
+}
+
+impl Default for Sgb {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Read a 15-bit color stored little-endian at `offset`
+fn read_color(data: &[u8], offset: usize) -> u16 {
+    u16::from_le_bytes([data[offset], data[offset + 1]]) & 0x7FFF
+}
+
+/// Read the 4 KiB of VRAM transfer data displayed on screen
+///
+/// The data is shown as 256 tiles laid out left to right, top to bottom.
+/// It is rebuilt from the displayed shades, which equal the tile color
+/// indices since games set BGP to 0xE4 for the transfer.
+fn screen_data(framebuffer: &Framebuffer) -> Vec<u8> {
+    let mut data = vec![0; TRANSFER_SIZE];
+
+    for (tile, bytes) in data.chunks_exact_mut(16).enumerate() {
+        let tile_x = (tile % ATTR_WIDTH) * 8;
+        let tile_y = (tile / ATTR_WIDTH) * 8;
+
+        for (row, pair) in bytes.chunks_exact_mut(2).enumerate() {
+            for (column, &shade) in framebuffer[tile_y + row][tile_x..tile_x + 8]
+                .iter()
+                .enumerate()
+            {
+                pair[0] |= ((shade & 0x01) as u8) << (7 - column);
+                pair[1] |= (((shade >> 1) & 0x01) as u8) << (7 - column);
+            }
+        }
+    }
+
+    data
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Bit-bang a packet into the receiver the way games do
+    fn send(receiver: &mut PacketReceiver, packet: &Packet) {
+        receiver.write(0x00);
+        receiver.write(0x30);
+        for i in 0..PACKET_SIZE * 8 {
+            let bit = packet[i / 8] & (1 << (i % 8)) != 0;
+            receiver.write(if bit { 0x10 } else { 0x20 });
+            receiver.write(0x30);
+        }
+        receiver.write(0x20);
+        receiver.write(0x30);
+    }
+
+    fn packet(bytes: &[u8]) -> Packet {
+        let mut packet = [0; PACKET_SIZE];
+        packet[..bytes.len()].copy_from_slice(bytes);
+        packet
+    }
+
+    fn blank_screen() -> Framebuffer {
+        [[0; SCREEN_WIDTH]; SCREEN_HEIGHT]
+    }
+
+    #[test]
+    fn test_packet_receiver_decodes_bits_lsb_first() {
+        let mut receiver = PacketReceiver::new();
+        let sent = packet(&[0x89, 0x01, 0xA5, 0xFF]);
+
+        send(&mut receiver, &sent);
+
+        assert_eq!(receiver.take_packet(), Some(sent));
+        assert_eq!(receiver.take_packet(), None);
+    }
+
+    #[test]
+    fn test_packet_receiver_ignores_joypad_polling() {
+        let mut receiver = PacketReceiver::new();
+        for _ in 0..200 {
+            receiver.write(0x20);
+            receiver.write(0x10);
+            receiver.write(0x30);
+        }
+        assert_eq!(receiver.take_packet(), None);
+    }
+
+    #[test]
+    fn test_packet_receiver_rejects_bad_stop_bit() {
+        let mut receiver = PacketReceiver::new();
+        receiver.write(0x00);
+        receiver.write(0x30);
+        for _ in 0..PACKET_SIZE * 8 {
+            receiver.write(0x20);
+            receiver.write(0x30);
+        }
+        receiver.write(0x10);
+        assert_eq!(receiver.take_packet(), None);
+    }
+
+    #[test]
+    fn test_pal01_shares_color_zero() {
+        let mut sgb = Sgb::new();
+        sgb.handle_packet(&packet(&[
+            0x01, 0x1F, 0x00, // Color 0: red
+            0x01, 0x00, 0x02, 0x00, 0x03, 0x00, // Palette 0
+            0x11, 0x00, 0x12, 0x00, 0x13, 0x00, // Palette 1
+        ]));
+
+        assert_eq!(sgb.palettes[0], [0x001F, 0x0001, 0x0002, 0x0003]);
+        assert_eq!(sgb.palettes[1], [0x001F, 0x0011, 0x0012, 0x0013]);
+        assert_eq!(sgb.palettes[3][0], 0x001F);
+        assert_eq!(sgb.palettes[3][1], DEFAULT_PALETTE[1]);
+    }
+
+    #[test]
+    fn test_attr_blk_inside_border_outside() {
+        let mut sgb = Sgb::new();
+        // One data set: inside=1, border=2, outside=3, rectangle (2,2)-(5,5)
+        sgb.handle_packet(&packet(&[0x21, 0x01, 0x07, 0x39, 2, 2, 5, 5]));
+
+        assert_eq!(sgb.attributes[3][3], 1);
+        assert_eq!(sgb.attributes[2][4], 2);
+        assert_eq!(sgb.attributes[5][5], 2);
+        assert_eq!(sgb.attributes[0][0], 3);
+        assert_eq!(sgb.attributes[17][19], 3);
+    }
+
+    #[test]
+    fn test_attr_blk_inside_only_includes_border() {
+        let mut sgb = Sgb::new();
+        sgb.handle_packet(&packet(&[0x21, 0x01, 0x01, 0x02, 2, 2, 5, 5]));
+
+        assert_eq!(sgb.attributes[2][2], 2);
+        assert_eq!(sgb.attributes[3][3], 2);
+        assert_eq!(sgb.attributes[0][0], 0);
+    }
+
+    #[test]
+    fn test_attr_lin_and_div() {
+        let mut sgb = Sgb::new();
+        // Split at row 9: above=1, line=2, below=3
+        sgb.handle_packet(&packet(&[0x31, 0x67, 9]));
+        assert_eq!(sgb.attributes[0][0], 1);
+        assert_eq!(sgb.attributes[9][10], 2);
+        assert_eq!(sgb.attributes[17][0], 3);
+
+        // Column 4 -> palette 0, row 0 -> palette 2
+        sgb.handle_packet(&packet(&[0x29, 0x02, 0x04, 0xC0]));
+        assert_eq!(sgb.attributes[12][4], 0);
+        assert_eq!(sgb.attributes[0][7], 2);
+    }
+
+    #[test]
+    fn test_attr_chr_spans_packets_and_wraps_rows() {
+        let mut sgb = Sgb::new();
+        // Start at (18, 0), 24 tiles left to right, 2 packets
+        let mut first = packet(&[0x3A, 18, 0, 24, 0, 0]);
+        first[6..16].fill(0xE4); // Palettes 3, 2, 1, 0
+        sgb.handle_packet(&first);
+        assert_eq!(sgb.attributes[0][18], 0, "waits for the second packet");
+
+        sgb.handle_packet(&packet(&[0xE4; 16]));
+        assert_eq!(sgb.attributes[0][18], 3);
+        assert_eq!(sgb.attributes[0][19], 2);
+        assert_eq!(sgb.attributes[1][0], 1);
+        assert_eq!(sgb.attributes[1][1], 0);
+    }
+
+    #[test]
+    fn test_pal_trn_and_pal_set() {
+        let mut sgb = Sgb::new();
+        sgb.handle_packet(&packet(&[0x59]));
+
+        // System palette 1 color 1 = 0x7FFF: bytes 10-11 of the data are
+        // row 5 of tile 0, both planes set
+        let mut screen = blank_screen();
+        screen[5][..8].fill(3);
+        sgb.frame_complete(&screen);
+
+        sgb.handle_packet(&packet(&[0x51, 1, 0, 0, 0, 0, 0, 0, 0]));
+        assert_eq!(sgb.palettes[0][1], 0x7FFF);
+        assert_eq!(sgb.palettes[1][1], 0x0000);
+    }
+
+    #[test]
+    fn test_mlt_req_sets_players() {
+        let mut sgb = Sgb::new();
+        sgb.handle_packet(&packet(&[0x89, 0x01]));
+        assert_eq!(sgb.players(), 2);
+        sgb.handle_packet(&packet(&[0x89, 0x03]));
+        assert_eq!(sgb.players(), 4);
+        sgb.handle_packet(&packet(&[0x89, 0x00]));
+        assert_eq!(sgb.players(), 1);
+    }
+
+    #[test]
+    fn test_render_border_and_screen() {
+        let mut sgb = Sgb::new();
+        sgb.set_shared_color(0x0000);
+        sgb.palettes[0][3] = 0x001F;
+
+        // Border tile 1: color 1 everywhere, using border palette 4
+        sgb.border_tiles[32..48].copy_from_slice(&[0xFF, 0x00].repeat(8));
+        sgb.border_colors[1] = 0x7C00;
+        sgb.border_map[0] = 0x1001;
+
+        let mut screen = blank_screen();
+        screen[0][0] = 3;
+
+        let mut out = vec![0; SGB_RGBA_FRAME_SIZE];
+        sgb.render_rgba(&screen, &mut out);
+
+        let pixel = |x: usize, y: usize| {
+            let i = (y * SGB_WIDTH + x) * 4;
+            [out[i], out[i + 1], out[i + 2]]
+        };
+        assert_eq!(pixel(0, 0), [0x00, 0x00, 0xFF], "border tile");
+        assert_eq!(pixel(8, 0), [0x00, 0x00, 0x00], "transparent border");
+        assert_eq!(pixel(SCREEN_X, SCREEN_Y), [0xFF, 0x00, 0x00], "game screen");
+    }
+}
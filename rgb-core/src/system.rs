@@ -1,8 +1,36 @@
 use crate::cartridge::Cartridge;
+use crate::decode;
+use crate::hdma::{self, Hdma, HdmaStart};
 use crate::joypad::Joypad;
 use crate::memory::{FlatMemory, Memory};
 use crate::mmu::Mmu;
-use crate::ppu::Ppu;
+use crate::model::Model;
+use crate::oam_bug::{self, OamCorruption};
+use crate::ppu::{Ppu, SCREEN_HEIGHT, SCREEN_WIDTH};
+use crate::sgb::{SGB_HEIGHT, SGB_WIDTH, Sgb};
+use crate::timer::Timer;
+
+/// Why `run_frame` returned
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StopReason {
+    /// The PPU entered VBlank
+    VBlank,
+    /// A frame's worth of dots passed without VBlank (LCD off, or
+    /// switched on during the frame)
+    CycleBudget,
+    /// The CPU executed STOP and waits for joypad input
+    Stopped,
+}
+
+/// Outcome of `GameBoy::run_frame`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FrameResult {
+    /// A new frame is ready to be displayed (blank while the LCD is off)
+    pub frame_ready: bool,
+    /// CPU cycles executed
+    pub cycles: u64,
+    pub stop_reason: StopReason,
+}
 
 /// Game Boy emulator
 ///
@@ -12,6 +40,9 @@ use crate::ppu::Ppu;
 ///
 /// This replaces the old `State` struct for new code.
 pub struct GameBoy<M: Memory = Mmu> {
+    // Hardware model (DMG, SGB or CGB)
+    pub model: Model,
+
     // CPU Registers
     pub a: u8,
     pub f: u8,
@@ -27,67 +58,222 @@ pub struct GameBoy<M: Memory = Mmu> {
     // CPU State
     pub ime: bool,       // Interrupt Master Enable flag
     pub halt: bool,      // CPU is halted
+    pub stopped: bool,   // CPU is in STOP mode (woken by joypad input)
     pub halt_bug: bool,  // HALT bug triggered (PC not incremented after HALT)
     pub ei_delay: bool,  // EI takes effect after next instruction
     pub di_delay: bool,  // DI takes effect after next instruction
     pub cycles: u64,     // Total CPU cycles executed
     pub last_opcode: u8, // Last executed opcode (for delayed interrupt handling)
 
-    // Timer State
-    pub div_counter: u64, // Internal counter for DIV register (increments every cycle)
-    pub tima_counter: u64, // Internal counter for TIMA register
+    // Clocks (CGB double speed runs the CPU and timers twice as fast as the PPU)
+    pub double_speed: bool,       // CGB double-speed mode active
+    pub speed_switch_armed: bool, // KEY1 bit 0: STOP performs a speed switch
+    pub dot_cycles: u64,          // Total PPU dots (4 MHz clock, unaffected by double speed)
+    ticked_cycles: u64,           // CPU cycles of the current step already run by `tick`
+
+    // Timer (DIV, TIMA, TMA, TAC)
+    pub timer: Timer,
 
     // PPU (Picture Processing Unit)
     pub ppu: Ppu,
+    pub skip_rendering: bool, // Frame skipping: don't draw scanlines (timing is unaffected)
+
+    // CGB VRAM DMA
+    pub hdma: Hdma,
 
     // Joypad
     pub joypad: Joypad,
 
+    // Super Game Boy (palettes, attributes and border), None on other models
+    pub sgb: Option<Sgb>,
+
     // Memory (generic over Memory trait)
     pub mmu: M,
 }
 
 impl GameBoy<Mmu> {
     /// Create a new Game Boy with the given cartridge
+    ///
+    /// The hardware model (DMG, SGB or CGB) is selected from the cartridge header.
     pub fn with_cartridge(cartridge: Cartridge) -> Self {
+        let model = Model::for_header(&cartridge.header);
+        Self::with_model(cartridge, model)
+    }
+
+    /// Create a new Game Boy emulating a specific hardware model
+    ///
+    /// Lets CGB-compatible cartridges run on DMG or SGB hardware.
+    pub fn with_model(cartridge: Cartridge, model: Model) -> Self {
+        let mut gb = Self::power_on(cartridge, model);
+
+        // Initialize CPU registers to post-boot values
+        gb.a = 0x01;
+        gb.f = 0xB0;
+        gb.set_bc(0x0013);
+        gb.set_de(0x00D8);
+        gb.set_hl(0x014D);
+        gb.sp = 0xFFFE;
+        gb.pc = 0x0100;
+
+        // SGB boot ROM leaves different values in the CPU registers
+        if gb.model.is_sgb() {
+            gb.f = 0x00;
+            gb.set_bc(0x0014);
+            gb.set_de(0x0000);
+            gb.set_hl(0xC060);
+        }
+
+        // CGB boot ROM leaves different values in the CPU registers
+        if gb.model.is_cgb() {
+            gb.a = 0x11;
+            gb.f = 0x80;
+            gb.set_bc(0x0000);
+            gb.set_de(0xFF56);
+            gb.set_hl(0x000D);
+        }
+
+        // Initialize I/O registers to post-boot values
+        gb.init_io_registers();
+
+        // The DMG boot ROM hands over with DIV reading 0xAB. The SGB and
+        // CGB boot ROMs have no fixed running time (the SGB waits on the
+        // SNES, the CGB one depends on the cartridge header), so there is
+        // no single post-boot DIV for them and it is left at 0.
+        if gb.model == Model::Dmg {
+            gb.timer.set_divider(0xABCC);
+        }
+
+        gb
+    }
+
+    /// Create a new Game Boy that runs a boot ROM before the cartridge
+    ///
+    /// The boot ROM (256 bytes for DMG/SGB, 2304 bytes for CGB) is mapped
+    /// over the cartridge until it writes to 0xFF50. Execution starts at
+    /// 0x0000 with zeroed registers and the LCD off; the boot ROM scrolls the
+    /// logo, verifies the header and jumps to 0x0100 itself.
+    ///
+    /// A CGB boot ROM selects CGB hardware for any cartridge, DMG-only ones
+    /// included; otherwise the model comes from the cartridge header.
+    pub fn with_boot_rom(cartridge: Cartridge, boot_rom: Vec<u8>) -> Result<Self, String> {
+        let model = if boot_rom.len() == crate::mmu::CGB_BOOT_ROM_SIZE {
+            Model::Cgb
+        } else {
+            Model::for_header(&cartridge.header)
+        };
+        Self::with_boot_rom_model(cartridge, model, boot_rom)
+    }
+
+    /// Create a new Game Boy emulating a specific hardware model that runs
+    /// a boot ROM before the cartridge
+    ///
+    /// The boot ROM must match the model, see `with_boot_rom`.
+    pub fn with_boot_rom_model(
+        cartridge: Cartridge,
+        model: Model,
+        boot_rom: Vec<u8>,
+    ) -> Result<Self, String> {
+        use crate::io::{BGP, LCDC, OBP0, OBP1};
+
+        let mut gb = Self::power_on(cartridge, model);
+        gb.mmu.load_boot_rom(boot_rom)?;
+
+        for register in [LCDC, BGP, OBP0, OBP1] {
+            gb.write(register, 0x00);
+        }
+
+        Ok(gb)
+    }
+
+    /// Soft reset: restart the cartridge from the post-boot state
+    ///
+    /// Cartridge RAM (battery saves) is kept; everything else is set up
+    /// again as `with_model` does, without running a boot ROM. Display and
+    /// input settings and the buttons held are kept.
+    pub fn reset(&mut self) {
+        self.restart(true);
+    }
+
+    /// Hard reset: like `reset`, but cartridge RAM is cleared too
+    pub fn hard_reset(&mut self) {
+        self.restart(false);
+    }
+
+    fn restart(&mut self, keep_cartridge_ram: bool) {
+        let mut gb = Self::with_model(self.mmu.cartridge.clone(), self.model);
+        if keep_cartridge_ram {
+            gb.mmu
+                .external_ram_mut()
+                .copy_from_slice(self.mmu.external_ram());
+        }
+
+        gb.ppu.set_palette(self.ppu.palette());
+        gb.ppu.set_color_correction(self.ppu.color_correction());
+        gb.joypad
+            .set_opposing_directions(self.joypad.opposing_directions());
+        gb.joypad.set_state(self.joypad.state());
+        gb.skip_rendering = self.skip_rendering;
+
+        *self = gb;
+    }
+
+    /// Create a Game Boy in its power-on state: all registers cleared and
+    /// PC at 0x0000
+    fn power_on(cartridge: Cartridge, model: Model) -> Self {
         let mut gb = GameBoy {
-            // Initialize CPU registers to post-boot values
-            a: 0x01,
-            f: 0xB0,
+            model,
+
+            // CPU registers
+            a: 0x00,
+            f: 0x00,
             b: 0x00,
-            c: 0x13,
+            c: 0x00,
             d: 0x00,
-            e: 0xD8,
-            h: 0x01,
-            l: 0x4D,
-            sp: 0xFFFE,
-            pc: 0x0100,
+            e: 0x00,
+            h: 0x00,
+            l: 0x00,
+            sp: 0x0000,
+            pc: 0x0000,
 
             // CPU state
             ime: false,
             halt: false,
+            stopped: false,
             halt_bug: false,
             ei_delay: false,
             di_delay: false,
             cycles: 0,
             last_opcode: 0,
 
-            // Timer state
-            div_counter: 0,
-            tima_counter: 0,
+            // Clocks
+            double_speed: false,
+            speed_switch_armed: false,
+            dot_cycles: 0,
+            ticked_cycles: 0,
+
+            // Timer
+            timer: Timer::new(),
 
             // PPU
-            ppu: Ppu::new(),
+            ppu: Ppu::with_model(model),
+            skip_rendering: false,
+
+            // CGB VRAM DMA
+            hdma: Hdma::new(),
 
             // Joypad
             joypad: Joypad::new(),
 
+            // Super Game Boy
+            sgb: model.is_sgb().then(Sgb::new),
+
             // MMU with cartridge
-            mmu: Mmu::new(cartridge),
+            mmu: Mmu::with_model(cartridge, model),
         };
 
-        // Initialize I/O registers to post-boot values
-        gb.init_io_registers();
+        if model.is_sgb() {
+            gb.joypad.enable_sgb();
+        }
 
         gb
     }
@@ -97,7 +283,6 @@ impl GameBoy<Mmu> {
         use crate::io::*;
 
         self.write(P1, 0xFF);
-        self.write(DIV, 0xAF);
         self.write(TIMA, 0x00);
         self.write(TMA, 0x00);
         self.write(TAC, 0x00);
@@ -129,20 +314,10 @@ impl GameBoy<Mmu> {
         self.write(WY, 0x00);
         self.write(WX, 0x00);
         self.write(IE, 0x00);
-    }
-
-    /// Handle PPU rendering (OAM scan and scanline rendering)
-    fn handle_ppu_rendering(&mut self) {
-        // Perform OAM scan if requested
-        if self.ppu.should_scan_oam {
-            self.ppu.should_scan_oam = false;
-            self.ppu.scan_oam(self.mmu.oam());
-        }
 
-        // Perform scanline rendering if requested
-        if self.ppu.should_render_scanline {
-            self.ppu.should_render_scanline = false;
-            self.ppu.render_scanline(self.mmu.vram(), self.mmu.oam());
+        if self.model.is_cgb() {
+            self.write(VBK, 0x00);
+            self.write(SVBK, 0x01);
         }
     }
 
@@ -171,20 +346,99 @@ impl GameBoy<Mmu> {
     ///
     /// This executes one CPU instruction and updates all subsystems (PPU, timers, etc.)
     pub fn step_with_ppu(&mut self) {
-        let cycles_before = self.cycles;
-        crate::instructions::execute(self);
-        let cycles_consumed = self.cycles - cycles_before;
-
-        // Update timers/PPU based on cycles consumed by the instruction or interrupt servicing
-        update_timers(self, cycles_consumed);
-        self.ppu.step(cycles_consumed);
+        self.handle_joypad_interrupt();
+        self.execute_instruction();
+        self.handle_hblank_dma();
 
         // Handle PPU rendering requests
         self.handle_ppu_rendering();
 
+        // SGB VRAM transfers read the frame that was just completed
+        if self.ppu.vblank_interrupt
+            && let Some(sgb) = &mut self.sgb
+        {
+            sgb.frame_complete(self.ppu.framebuffer());
+        }
+
         // Handle PPU interrupts
         self.handle_ppu_interrupts();
     }
+
+    /// Run until the PPU enters VBlank
+    ///
+    /// While the LCD is off no VBlank happens, so this stops after one
+    /// frame's worth of dots instead (`DOTS_PER_FRAME`, independent of
+    /// CGB double speed). Also returns early when the CPU enters STOP.
+    pub fn run_frame(&mut self) -> FrameResult {
+        self.run_frame_with(|_| {})
+    }
+
+    /// `run_frame`, calling `before_step` before each instruction
+    pub fn run_frame_with<F>(&mut self, mut before_step: F) -> FrameResult
+    where
+        F: FnMut(&Self),
+    {
+        use crate::ppu::{DOTS_PER_FRAME, Mode};
+
+        let start_cycles = self.cycles;
+        let budget = self.dot_cycles + DOTS_PER_FRAME;
+        self.ppu.frame_ready = false;
+
+        let stop_reason = loop {
+            let was_vblank = self.ppu.mode() == Mode::VBlank;
+            let was_stopped = self.stopped;
+
+            before_step(self);
+            self.step_with_ppu();
+
+            if self.ppu.is_lcd_enabled() && !was_vblank && self.ppu.mode() == Mode::VBlank {
+                break StopReason::VBlank;
+            }
+            if self.stopped && !was_stopped {
+                break StopReason::Stopped;
+            }
+            if self.dot_cycles >= budget {
+                break StopReason::CycleBudget;
+            }
+        };
+
+        FrameResult {
+            frame_ready: std::mem::take(&mut self.ppu.frame_ready),
+            cycles: self.cycles - start_cycles,
+            stop_reason,
+        }
+    }
+
+    /// Run the emulator frame by frame
+    ///
+    /// # Arguments
+    /// * `max_frames` - Maximum number of frames to run (None = run forever)
+    /// * `callback` - Called after each frame, returns false to stop
+    ///
+    /// # Returns
+    /// Number of frames run
+    pub fn run<F>(&mut self, max_frames: Option<u64>, mut callback: F) -> u64
+    where
+        F: FnMut(&mut Self, &FrameResult) -> bool,
+    {
+        let mut frames = 0u64;
+
+        while max_frames.is_none_or(|max| frames < max) {
+            let result = self.run_frame();
+            frames += 1;
+
+            if !callback(self, &result) {
+                break;
+            }
+        }
+
+        frames
+    }
+
+    /// Run the emulator for a number of frames without callbacks
+    pub fn run_simple(&mut self, max_frames: u64) -> u64 {
+        self.run(Some(max_frames), |_, _| true)
+    }
 }
 
 // Generic implementation for all Memory types
@@ -192,6 +446,8 @@ impl<M: Memory> GameBoy<M> {
     /// Create a GameBoy with custom memory (for testing)
     pub fn with_memory(memory: M) -> Self {
         GameBoy {
+            model: Model::Dmg,
+
             // Initialize CPU registers to post-boot values
             a: 0x01,
             f: 0xB0,
@@ -207,22 +463,35 @@ impl<M: Memory> GameBoy<M> {
             // CPU state
             ime: false,
             halt: false,
+            stopped: false,
             halt_bug: false,
             ei_delay: false,
             di_delay: false,
             cycles: 0,
             last_opcode: 0,
 
-            // Timer state
-            div_counter: 0,
-            tima_counter: 0,
+            // Clocks
+            double_speed: false,
+            speed_switch_armed: false,
+            dot_cycles: 0,
+            ticked_cycles: 0,
+
+            // Timer
+            timer: Timer::new(),
 
             // PPU
             ppu: Ppu::new(),
+            skip_rendering: false,
+
+            // CGB VRAM DMA
+            hdma: Hdma::new(),
 
             // Joypad
             joypad: Joypad::new(),
 
+            // Super Game Boy
+            sgb: None,
+
             mmu: memory,
         }
     }
@@ -238,6 +507,18 @@ impl<M: Memory> GameBoy<M> {
             return self.joypad.read();
         }
 
+        if addr == KEY1 {
+            return self.read_key1();
+        }
+
+        // VRAM DMA registers: only HDMA5 is readable
+        match addr {
+            HDMA1..=HDMA4 => return 0xFF,
+            HDMA5 if self.model.is_cgb() => return self.hdma.read_hdma5(),
+            HDMA5 => return 0xFF,
+            _ => {}
+        }
+
         // Intercept PPU register reads
         match addr {
             LCDC => return self.ppu.read_lcdc(),
@@ -251,23 +532,35 @@ impl<M: Memory> GameBoy<M> {
             OBP1 => return self.ppu.read_obp1(),
             WY => return self.ppu.read_wy(),
             WX => return self.ppu.read_wx(),
+            BCPS => return self.ppu.read_bcps(),
+            BCPD => return self.ppu.read_bcpd(),
+            OCPS => return self.ppu.read_ocps(),
+            OCPD => return self.ppu.read_ocpd(),
+            _ => {}
+        }
+
+        // Intercept timer register reads
+        match addr {
+            DIV => return self.timer.read_div(),
+            TIMA => return self.timer.read_tima(),
+            TMA => return self.timer.read_tma(),
+            TAC => return self.timer.read_tac(),
             _ => {}
         }
 
         // VRAM access restrictions (blocked during Mode 3 - Pixel Transfer)
-        if (0x8000..=0x9FFF).contains(&addr) {
-            if self.ppu.is_lcd_enabled() && self.ppu.mode() == Mode::PixelTransfer {
-                return 0xFF; // Return 0xFF when VRAM is blocked
-            }
+        if (0x8000..=0x9FFF).contains(&addr)
+            && self.ppu.is_lcd_enabled()
+            && self.ppu.mode() == Mode::PixelTransfer
+        {
+            return 0xFF; // Return 0xFF when VRAM is blocked
         }
 
         // OAM access restrictions (blocked during Mode 2 - OAM Search and Mode 3 - Pixel Transfer)
-        if (0xFE00..=0xFE9F).contains(&addr) {
-            if self.ppu.is_lcd_enabled() {
-                let mode = self.ppu.mode();
-                if mode == Mode::OamSearch || mode == Mode::PixelTransfer {
-                    return 0xFF; // Return 0xFF when OAM is blocked
-                }
+        if (0xFE00..=0xFE9F).contains(&addr) && self.ppu.is_lcd_enabled() {
+            let mode = self.ppu.mode();
+            if mode == Mode::OamSearch || mode == Mode::PixelTransfer {
+                return 0xFF; // Return 0xFF when OAM is blocked
             }
         }
 
@@ -283,9 +576,40 @@ impl<M: Memory> GameBoy<M> {
         // Handle joypad register writes
         if addr == P1 {
             self.joypad.write(value);
+            self.handle_sgb_packets();
+            return;
+        }
+
+        if addr == KEY1 {
+            self.write_key1(value);
             return;
         }
 
+        // VRAM DMA registers
+        match addr {
+            HDMA1 => {
+                self.hdma.write_hdma1(value);
+                return;
+            }
+            HDMA2 => {
+                self.hdma.write_hdma2(value);
+                return;
+            }
+            HDMA3 => {
+                self.hdma.write_hdma3(value);
+                return;
+            }
+            HDMA4 => {
+                self.hdma.write_hdma4(value);
+                return;
+            }
+            HDMA5 => {
+                self.write_hdma5(value);
+                return;
+            }
+            _ => {}
+        }
+
         // Handle PPU register writes
         match addr {
             LCDC => {
@@ -329,29 +653,59 @@ impl<M: Memory> GameBoy<M> {
                 self.ppu.write_wx(value);
                 return;
             }
+            BCPS => {
+                self.ppu.write_bcps(value);
+                return;
+            }
+            BCPD => {
+                self.ppu.write_bcpd(value);
+                return;
+            }
+            OCPS => {
+                self.ppu.write_ocps(value);
+                return;
+            }
+            OCPD => {
+                self.ppu.write_ocpd(value);
+                return;
+            }
+            _ => {}
+        }
+
+        // Handle timer register writes
+        match addr {
             DIV => {
-                // Writing any value to DIV resets it to 0x00 and resets the internal counter
-                self.mmu.write(addr, 0x00);
-                self.div_counter = 0;
+                self.timer.write_div(value);
+                return;
+            }
+            TIMA => {
+                self.timer.write_tima(value);
+                return;
+            }
+            TMA => {
+                self.timer.write_tma(value);
+                return;
+            }
+            TAC => {
+                self.timer.write_tac(value);
                 return;
             }
             _ => {}
         }
 
         // VRAM access restrictions (blocked during Mode 3 - Pixel Transfer)
-        if (0x8000..=0x9FFF).contains(&addr) {
-            if self.ppu.is_lcd_enabled() && self.ppu.mode() == Mode::PixelTransfer {
-                return; // Ignore writes when VRAM is blocked
-            }
+        if (0x8000..=0x9FFF).contains(&addr)
+            && self.ppu.is_lcd_enabled()
+            && self.ppu.mode() == Mode::PixelTransfer
+        {
+            return; // Ignore writes when VRAM is blocked
         }
 
         // OAM access restrictions (blocked during Mode 2 - OAM Search and Mode 3 - Pixel Transfer)
-        if (0xFE00..=0xFE9F).contains(&addr) {
-            if self.ppu.is_lcd_enabled() {
-                let mode = self.ppu.mode();
-                if mode == Mode::OamSearch || mode == Mode::PixelTransfer {
-                    return; // Ignore writes when OAM is blocked
-                }
+        if (0xFE00..=0xFE9F).contains(&addr) && self.ppu.is_lcd_enabled() {
+            let mode = self.ppu.mode();
+            if mode == Mode::OamSearch || mode == Mode::PixelTransfer {
+                return; // Ignore writes when OAM is blocked
             }
         }
 
@@ -406,6 +760,28 @@ impl<M: Memory> GameBoy<M> {
         self.pc
     }
 
+    /// Disassemble the instruction at PC along with the register state,
+    /// one line per instruction for execution traces
+    pub fn trace_line(&self) -> String {
+        let disassembly = decode::disassemble(self.pc, |addr| self.read(addr));
+        let bytes: Vec<String> = disassembly
+            .bytes
+            .iter()
+            .map(|b| format!("{b:02X}"))
+            .collect();
+        format!(
+            "{:04X}  {:<8}  {:<18}  AF:{:04X} BC:{:04X} DE:{:04X} HL:{:04X} SP:{:04X}",
+            self.pc,
+            bytes.join(" "),
+            disassembly.to_string(),
+            self.af(),
+            self.bc(),
+            self.de(),
+            self.hl(),
+            self.sp,
+        )
+    }
+
     // Register pair setters
     pub fn set_af(&mut self, value: u16) {
         self.a = (value >> 8) as u8;
@@ -495,63 +871,292 @@ impl<M: Memory> GameBoy<M> {
     /// This executes one CPU instruction and updates all subsystems (PPU, timers, etc.)
     /// For testing with generic memory that doesn't support PPU rendering
     pub fn step(&mut self) {
+        self.handle_joypad_interrupt();
+        self.execute_instruction();
+        self.handle_hblank_dma();
+    }
+
+    /// Execute one instruction (or interrupt dispatch) and run timers and
+    /// the PPU for the cycles it took
+    ///
+    /// Memory accesses made by the instruction already advanced the system
+    /// through `tick`; only the remaining internal cycles are run here.
+    fn execute_instruction(&mut self) {
         let cycles_before = self.cycles;
+        self.ticked_cycles = 0;
         crate::instructions::execute(self);
         let cycles_consumed = self.cycles - cycles_before;
 
-        // Update timers/PPU based on cycles consumed by the instruction or interrupt servicing
-        update_timers(self, cycles_consumed);
-        self.ppu.step(cycles_consumed);
+        debug_assert!(
+            self.ticked_cycles <= cycles_consumed,
+            "instruction ticked {} cycles but took {}",
+            self.ticked_cycles,
+            cycles_consumed
+        );
+        self.advance_clock(cycles_consumed.saturating_sub(self.ticked_cycles));
+        self.ticked_cycles = 0;
     }
 
-    /// Run the emulator for a specified number of instructions
+    /// Run timers and the PPU for one M-cycle in the middle of an instruction
     ///
-    /// This executes instructions in batches, optionally calling a callback
-    /// after each batch for tasks like rendering, input handling, etc.
+    /// Called by the CPU before each memory access so that reads and writes
+    /// of I/O registers see the state at the M-cycle they happen on.
+    pub fn tick(&mut self) {
+        self.advance_clock(4);
+        self.ticked_cycles += 4;
+    }
+
+    /// Run one M-cycle while the CPU is in STOP mode
     ///
-    /// # Arguments
-    /// * `max_instructions` - Maximum number of instructions to execute (None = run forever)
-    /// * `batch_size` - Number of instructions to execute before yielding
-    /// * `callback` - Optional function called after each batch, returns false to stop
+    /// STOP halts the system clock, so DIV and TIMA don't count; the PPU
+    /// keeps going so frontends keep their frame timing.
+    pub fn tick_stopped(&mut self) {
+        self.cycles += 4;
+        self.ticked_cycles += 4;
+        let dots = self.advance_dot_clock(4);
+        self.ppu.step(dots);
+    }
+
+    /// Read a byte as part of an instruction, taking one M-cycle
+    #[inline]
+    pub fn cpu_read(&mut self, addr: u16) -> u8 {
+        self.tick();
+        self.trigger_oam_bug(addr, OamCorruption::Read);
+        self.read(addr)
+    }
+
+    /// Read a byte while the register holding the address is incremented
+    /// or decremented (LD A,(HL+), LD A,(HL-) and POP), taking one M-cycle
+    #[inline]
+    pub fn cpu_read_idu(&mut self, addr: u16) -> u8 {
+        self.tick();
+        self.trigger_oam_bug(addr, OamCorruption::ReadIdu);
+        self.read(addr)
+    }
+
+    /// Write a byte as part of an instruction, taking one M-cycle
+    #[inline]
+    pub fn cpu_write(&mut self, addr: u16, value: u8) {
+        self.tick();
+        self.trigger_oam_bug(addr, OamCorruption::Write);
+        self.write(addr, value)
+    }
+
+    /// Corrupt OAM if `addr` is in 0xFE00-0xFEFF while the PPU is reading
+    /// OAM (DMG and SGB only)
     ///
-    /// # Returns
-    /// Total number of instructions executed
-    pub fn run<F>(&mut self, max_instructions: Option<u64>, batch_size: u64, mut callback: F) -> u64
-    where
-        F: FnMut(&mut Self) -> bool,
-    {
-        let mut total_executed = 0u64;
-
-        loop {
-            // Execute a batch of instructions
-            for _ in 0..batch_size {
-                self.step();
-                total_executed += 1;
-
-                // Check if we've hit the max
-                if let Some(max) = max_instructions {
-                    if total_executed >= max {
-                        return total_executed;
-                    }
-                }
+    /// Called for CPU reads and writes, and for 16-bit increments and
+    /// decrements, which put the register on the address bus.
+    pub fn trigger_oam_bug(&mut self, addr: u16, kind: OamCorruption) {
+        if self.model.is_cgb() || !(0xFE00..=0xFEFF).contains(&addr) {
+            return;
+        }
+        let Some(row) = self.ppu.oam_row() else {
+            return;
+        };
+
+        oam_bug::corrupt(self.mmu.oam_mut(), row, kind);
+    }
+
+    /// Read a 16-bit word (little-endian) as part of an instruction
+    #[inline]
+    pub fn cpu_read_word(&mut self, addr: u16) -> u16 {
+        let low = self.cpu_read(addr) as u16;
+        let high = self.cpu_read(addr.wrapping_add(1)) as u16;
+        (high << 8) | low
+    }
+
+    /// Write a 16-bit word (little-endian) as part of an instruction
+    #[inline]
+    pub fn cpu_write_word(&mut self, addr: u16, data: u16) {
+        self.cpu_write(addr, data as u8);
+        self.cpu_write(addr.wrapping_add(1), (data >> 8) as u8);
+    }
+
+    /// Run timers and the PPU for a number of CPU cycles
+    fn advance_clock(&mut self, cycles: u64) {
+        update_timers(self, cycles);
+        let dots = self.advance_dot_clock(cycles);
+        self.ppu.step(dots);
+    }
+
+    /// Request the joypad interrupt (IF bit 4) after a P1 input line went low
+    ///
+    /// Checked before each instruction so button presses made by the frontend
+    /// between steps are seen right away.
+    fn handle_joypad_interrupt(&mut self) {
+        use crate::io::IF;
+
+        if self.joypad.interrupt {
+            self.joypad.interrupt = false;
+            let if_flags = self.mmu.read(IF);
+            self.mmu.write(IF, if_flags | 0x10);
+        }
+    }
+
+    /// Convert CPU cycles to PPU dots and advance the dot clock
+    ///
+    /// In CGB double-speed mode the CPU (and timers) run at 8 MHz while
+    /// the PPU keeps running at 4 MHz, so it sees half as many cycles.
+    fn advance_dot_clock(&mut self, cpu_cycles: u64) -> u64 {
+        let dots = if self.double_speed {
+            cpu_cycles / 2
+        } else {
+            cpu_cycles
+        };
+        self.dot_cycles += dots;
+        dots
+    }
+
+    /// Handle PPU rendering (OAM scan and scanline rendering)
+    pub(crate) fn handle_ppu_rendering(&mut self) {
+        // Skipped frames keep their timing and interrupts, only the pixels
+        // are dropped. SGB VRAM transfers read the drawn frame, so the SGB
+        // always renders.
+        let skip = self.skip_rendering && self.sgb.is_none();
+        let video_memory = self.mmu.video_memory().filter(|_| !skip);
+
+        // Perform OAM scan if requested
+        if self.ppu.should_scan_oam {
+            self.ppu.should_scan_oam = false;
+            if let Some((_, oam)) = video_memory {
+                self.ppu.scan_oam(oam);
             }
+        }
 
-            // Call the callback after each batch
-            // If it returns false, stop execution
-            if !callback(self) {
-                break;
+        // Perform scanline rendering if requested
+        if self.ppu.should_render_scanline {
+            self.ppu.should_render_scanline = false;
+            if let Some((vram, oam)) = video_memory {
+                self.ppu.render_scanline(vram, oam);
+            }
+        }
+    }
+
+    /// Halt the CPU for `cycles` CPU cycles (VRAM DMA, speed switch) while
+    /// the rest of the system keeps running
+    ///
+    /// The PPU only reports one scanline event per step, so the time is
+    /// run one M-cycle at a time: every line crossed is scanned and
+    /// rendered, and gets its HBlank DMA block if one is armed.
+    pub(crate) fn stall(&mut self, cycles: u64) {
+        for _ in 0..cycles / 4 {
+            self.cycles += 4;
+            self.tick();
+            self.handle_ppu_rendering();
+            self.handle_hblank_dma();
+        }
+    }
+
+    /// Copy one 16-byte block of a VRAM DMA transfer
+    ///
+    /// Returns the number of CPU cycles the CPU is halted for.
+    fn transfer_hdma_block(&mut self) -> u64 {
+        let Some((source, dest)) = self.hdma.next_block() else {
+            return 0;
+        };
+
+        for offset in 0..0x10 {
+            let value = self.mmu.read(source.wrapping_add(offset));
+            self.mmu.write(dest + offset, value);
+        }
+
+        // Same duration in real time, so twice the CPU cycles in double speed
+        if self.double_speed {
+            hdma::BLOCK_CYCLES * 2
+        } else {
+            hdma::BLOCK_CYCLES
+        }
+    }
+
+    /// Write HDMA5: run a general purpose transfer at once, or arm an
+    /// HBlank transfer
+    fn write_hdma5(&mut self, value: u8) {
+        if !self.model.is_cgb() {
+            return;
+        }
+
+        if self.hdma.write_hdma5(value) == HdmaStart::GeneralPurpose {
+            // The CPU is halted until the whole transfer is done. Copy until
+            // the blocks run out: a 128-block transfer already reads 0xFF.
+            let mut stall = 0;
+            loop {
+                let cycles = self.transfer_hdma_block();
+                if cycles == 0 {
+                    break;
+                }
+                stall += cycles;
             }
+            self.stall(stall);
+        }
+    }
+
+    /// Transfer one HBlank DMA block when the PPU has just entered HBlank
+    fn handle_hblank_dma(&mut self) {
+        if !self.ppu.entered_hblank {
+            return;
+        }
+        self.ppu.entered_hblank = false;
+
+        if !self.hdma.is_hblank_active() {
+            return;
+        }
+
+        // The CPU is halted while the block is copied; keep the rest of
+        // the system running for that time
+        let stall = self.transfer_hdma_block();
+        self.stall(stall);
+    }
+
+    /// Read KEY1 (CGB speed switch)
+    ///
+    /// Bit 7: Current speed (1 = double speed)
+    /// Bit 0: Speed switch armed
+    fn read_key1(&self) -> u8 {
+        if !self.model.is_cgb() {
+            return 0xFF;
+        }
+        0x7E | ((self.double_speed as u8) << 7) | (self.speed_switch_armed as u8)
+    }
+
+    /// Write KEY1: only the "armed" bit is writable
+    fn write_key1(&mut self, value: u8) {
+        if self.model.is_cgb() {
+            self.speed_switch_armed = value & 0x01 != 0;
+        }
+    }
+
+    /// Pass SGB command packets sent through P1 on to the SNES side
+    fn handle_sgb_packets(&mut self) {
+        let Some(sgb) = &mut self.sgb else {
+            return;
+        };
+
+        while let Some(packet) = self.joypad.take_sgb_packet() {
+            sgb.handle_packet(&packet);
+            self.joypad.set_players(sgb.players());
         }
+    }
 
-        total_executed
+    /// Size of the frames produced by `framebuffer_rgba`
+    ///
+    /// 160x144, or 256x224 on a Super Game Boy (screen inside the border).
+    pub fn screen_size(&self) -> (usize, usize) {
+        if self.sgb.is_some() {
+            (SGB_WIDTH, SGB_HEIGHT)
+        } else {
+            (SCREEN_WIDTH, SCREEN_HEIGHT)
+        }
     }
 
-    /// Run the emulator for a specified number of instructions without callbacks
+    /// Convert the current frame to RGBA for display
     ///
-    /// This is a simpler version of `run()` for cases where you just want to
-    /// execute instructions without any periodic callbacks.
-    pub fn run_simple(&mut self, max_instructions: u64) -> u64 {
-        self.run(Some(max_instructions), max_instructions, |_| true)
+    /// `out` must hold `width * height * 4` bytes of `screen_size()`.
+    pub fn framebuffer_rgba(&self, out: &mut [u8]) {
+        match &self.sgb {
+            Some(sgb) => sgb.render_rgba(self.ppu.framebuffer(), out),
+            None => self.ppu.framebuffer_rgba(out),
+        }
     }
 }
 
@@ -591,80 +1196,24 @@ impl GameBoy<FlatMemory> {
 
 /// Update timers based on cycles executed
 ///
-/// Game Boy timers:
-/// - DIV (0xFF04): Divider register, increments at 16384 Hz (every 256 cycles)
-/// - TIMA (0xFF05): Timer counter, increments at frequency set by TAC
-/// - TMA (0xFF06): Timer modulo, loaded into TIMA when it overflows
-/// - TAC (0xFF07): Timer control (bit 2 = enable, bits 0-1 = clock select)
+/// Advances the timer (see `timer::Timer`) and requests the timer
+/// interrupt (IF bit 2) when TIMA was reloaded after an overflow.
 pub fn update_timers<M: Memory>(state: &mut GameBoy<M>, cycles: u64) {
-    use crate::io::{DIV, IF, TAC, TIMA, TMA};
-
-    // Update DIV register (increments every 256 cycles = 16384 Hz)
-    state.div_counter += cycles;
-    if state.div_counter >= 256 {
-        let div_increments = state.div_counter / 256;
-        state.div_counter %= 256;
-        let current_div = state.read(DIV);
-        // Write directly to MMU to avoid triggering the DIV reset handler
-        state
-            .mmu
-            .write(DIV, current_div.wrapping_add(div_increments as u8));
-    }
-
-    // Check if timer is enabled (bit 2 of TAC)
-    let tac = state.read(TAC);
-    let timer_enabled = (tac & 0x04) != 0;
-
-    if timer_enabled {
-        // Clock select (bits 0-1 of TAC):
-        // 00: 4096 Hz   (1024 cycles per increment)
-        // 01: 262144 Hz (16 cycles per increment)
-        // 10: 65536 Hz  (64 cycles per increment)
-        // 11: 16384 Hz  (256 cycles per increment)
-        let clock_select = tac & 0x03;
-        let cycles_per_increment = match clock_select {
-            0 => 1024,
-            1 => 16,
-            2 => 64,
-            3 => 256,
-            _ => unreachable!(),
-        };
+    use crate::io::IF;
 
-        // Add cycles to counter
-        state.tima_counter += cycles;
+    state.timer.step(cycles);
 
-        // Check if we need to increment TIMA
-        if state.tima_counter >= cycles_per_increment {
-            // Calculate how many increments and keep the remainder
-            let increments = state.tima_counter / cycles_per_increment;
-            state.tima_counter %= cycles_per_increment;
-
-            // Read current TIMA value
-            let tima = state.read(TIMA);
-            let tma = state.read(TMA);
-
-            // Check if overflow will occur
-            let will_overflow = (tima as u64 + increments) > 0xFF;
-
-            if will_overflow {
-                // If we overflow, reload from TMA
-                // The actual hardware reloads TMA after overflow, not the wrapped value
-                state.write(TIMA, tma);
-
-                // Set timer interrupt flag
-                let if_flags = state.read(IF);
-                state.write(IF, if_flags | 0x04);
-            } else {
-                // No overflow, just update TIMA
-                state.write(TIMA, tima.wrapping_add(increments as u8));
-            }
-        }
+    if state.timer.interrupt {
+        state.timer.interrupt = false;
+        let if_flags = state.mmu.read(IF);
+        state.mmu.write(IF, if_flags | 0x04);
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::video::Palette;
 
     #[test]
     fn test_new_initializes_correct_values() {
@@ -677,6 +1226,17 @@ mod tests {
         assert_eq!(state.pc(), 0x0100);
     }
 
+    #[test]
+    fn test_trace_line() {
+        let mut state = GameBoy::<FlatMemory>::new();
+        state.write(0x0100, 0x3E);
+        state.write(0x0101, 0x42);
+        assert_eq!(
+            state.trace_line(),
+            "0100  3E 42     LD A,$42            AF:01B0 BC:0013 DE:00D8 HL:014D SP:FFFE"
+        );
+    }
+
     #[test]
     fn test_register_pairs_getter_setter() {
         let mut state = GameBoy::<FlatMemory>::new();
@@ -830,9 +1390,14 @@ mod tests {
         state.write(TAC, 0x04); // Timer enabled, 4096 Hz
         state.write(IF, 0x00);
 
-        // Run 1024 cycles - should overflow and reload from TMA
+        // Run 1024 cycles - should overflow, reading 0 for one M-cycle
         update_timers(&mut state, 1024);
-        assert_eq!(state.read(TIMA), 0x10); // Reloaded from TMA
+        assert_eq!(state.read(TIMA), 0x00);
+        assert_eq!(state.read(IF) & 0x04, 0x00);
+
+        // Then reload from TMA
+        update_timers(&mut state, 4);
+        assert_eq!(state.read(TIMA), 0x10);
 
         // Check timer interrupt flag is set (bit 2)
         assert_eq!(state.read(IF) & 0x04, 0x04);
@@ -850,4 +1415,562 @@ mod tests {
         update_timers(&mut state, 64);
         assert_eq!(state.read(TIMA), 0x04);
     }
+
+    #[test]
+    fn test_cgb_cartridge_post_boot_values() {
+        use crate::io::{SVBK, VBK};
+
+        let mut rom = vec![0; 32 * 1024];
+        rom[0x0143] = 0xC0; // CGB only
+
+        let mut checksum: u8 = 0;
+        for &byte in &rom[0x0134..=0x014C] {
+            checksum = checksum.wrapping_sub(byte).wrapping_sub(1);
+        }
+        rom[0x014D] = checksum;
+
+        let cartridge = Cartridge::from_bytes(rom).unwrap();
+        let gameboy = GameBoy::with_cartridge(cartridge);
+
+        assert_eq!(gameboy.model, Model::Cgb);
+        assert_eq!(gameboy.ppu.model(), Model::Cgb);
+        assert_eq!(gameboy.af(), 0x1180);
+        assert_eq!(gameboy.bc(), 0x0000);
+        assert_eq!(gameboy.de(), 0xFF56);
+        assert_eq!(gameboy.hl(), 0x000D);
+        assert_eq!(gameboy.read(VBK), 0xFE);
+        assert_eq!(gameboy.read(SVBK), 0xF9);
+    }
+
+    #[test]
+    fn test_dmg_cartridge_post_boot_values() {
+        let gameboy = GameBoy::<Mmu>::default();
+        assert_eq!(gameboy.model, Model::Dmg);
+        assert_eq!(gameboy.af(), 0x01B0);
+        assert_eq!(gameboy.read(crate::io::DIV), 0xAB);
+    }
+
+    #[test]
+    fn test_boot_rom_selects_model() {
+        use crate::mmu::{CGB_BOOT_ROM_SIZE, DMG_BOOT_ROM_SIZE};
+
+        // The default cartridge is DMG-only
+        let cartridge = GameBoy::<Mmu>::default().mmu.cartridge;
+
+        let gameboy =
+            GameBoy::with_boot_rom(cartridge.clone(), vec![0; DMG_BOOT_ROM_SIZE]).unwrap();
+        assert_eq!(gameboy.model, Model::Dmg);
+
+        let gameboy =
+            GameBoy::with_boot_rom(cartridge.clone(), vec![0; CGB_BOOT_ROM_SIZE]).unwrap();
+        assert_eq!(gameboy.model, Model::Cgb);
+
+        let gameboy =
+            GameBoy::with_boot_rom_model(cartridge.clone(), Model::Sgb, vec![0; DMG_BOOT_ROM_SIZE])
+                .unwrap();
+        assert_eq!(gameboy.model, Model::Sgb);
+
+        assert!(
+            GameBoy::with_boot_rom_model(cartridge, Model::Cgb, vec![0; DMG_BOOT_ROM_SIZE])
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn test_boot_rom_hands_over_to_cartridge() {
+        let cartridge = GameBoy::<Mmu>::default().mmu.cartridge;
+
+        // Jump to the end of the boot ROM, which unmaps itself right
+        // before 0x0100 like the real one
+        let mut boot_rom = vec![0x00; crate::mmu::DMG_BOOT_ROM_SIZE];
+        boot_rom[0x00..0x03].copy_from_slice(&[0xC3, 0xFC, 0x00]); // JP $00FC
+        boot_rom[0xFC..0x100].copy_from_slice(&[0x3E, 0x01, 0xE0, 0x50]); // LD A,1; LDH (BOOT),A
+
+        let mut gameboy = GameBoy::with_boot_rom(cartridge, boot_rom).unwrap();
+        assert_eq!(gameboy.pc(), 0x0000);
+        assert_eq!(gameboy.af(), 0x0000);
+        assert_eq!(gameboy.sp(), 0x0000);
+        assert!(!gameboy.ppu.is_lcd_enabled());
+        assert_eq!(gameboy.read(0x0000), 0xC3);
+
+        for _ in 0..3 {
+            gameboy.step_with_ppu();
+        }
+
+        assert_eq!(gameboy.pc(), 0x0100);
+        assert!(!gameboy.mmu.is_boot_rom_mapped());
+        assert_eq!(gameboy.read(0x0000), 0x00);
+    }
+
+    #[test]
+    fn test_sgb_cartridge_multiplayer_request() {
+        use crate::io::P1;
+
+        let mut rom = vec![0; 32 * 1024];
+        rom[0x0146] = 0x03; // SGB functions
+        rom[0x014B] = 0x33; // Use new licensee code
+
+        let mut checksum: u8 = 0;
+        for &byte in &rom[0x0134..=0x014C] {
+            checksum = checksum.wrapping_sub(byte).wrapping_sub(1);
+        }
+        rom[0x014D] = checksum;
+
+        let cartridge = Cartridge::from_bytes(rom).unwrap();
+        let mut gameboy = GameBoy::with_cartridge(cartridge);
+
+        assert_eq!(gameboy.model, Model::Sgb);
+        assert_eq!(gameboy.af(), 0x0100);
+        assert_eq!(gameboy.hl(), 0xC060);
+        assert_eq!(gameboy.screen_size(), (SGB_WIDTH, SGB_HEIGHT));
+
+        // Bit-bang MLT_REQ (2 players) through P1
+        let packet = [0x89, 0x01];
+        gameboy.write(P1, 0x00);
+        gameboy.write(P1, 0x30);
+        for i in 0..128 {
+            let bit = packet
+                .get(i / 8)
+                .is_some_and(|byte| byte & (1 << (i % 8)) != 0);
+            gameboy.write(P1, if bit { 0x10 } else { 0x20 });
+            gameboy.write(P1, 0x30);
+        }
+        gameboy.write(P1, 0x20);
+        gameboy.write(P1, 0x30);
+
+        // Controller IDs read while no row is selected; each release of
+        // P15 selects the next controller
+        assert_eq!(gameboy.read(P1) & 0x0F, 0x0F);
+        gameboy.write(P1, 0x10);
+        gameboy.write(P1, 0x30);
+        assert_eq!(gameboy.read(P1) & 0x0F, 0x0E);
+        gameboy.write(P1, 0x10);
+        gameboy.write(P1, 0x30);
+        assert_eq!(gameboy.read(P1) & 0x0F, 0x0F);
+    }
+
+    #[test]
+    fn test_joypad_interrupt_wakes_halt() {
+        use crate::io::{IE, IF, P1};
+        use crate::joypad::Button;
+
+        let mut state = GameBoy::<FlatMemory>::new();
+        state.write(IE, 0x10);
+        state.write(IF, 0x00);
+        state.write(P1, 0x20); // Select directions
+        state.ime = true;
+        state.halt = true;
+        state.pc = 0xC000;
+
+        state.step();
+        assert!(state.halt);
+
+        // Buttons in the unselected row do not trigger the interrupt
+        state.joypad.press(Button::A);
+        state.step();
+        assert!(state.halt);
+
+        state.joypad.press(Button::Right);
+        state.step();
+        assert!(!state.halt);
+        assert_eq!(state.pc, 0x0060);
+        assert_eq!(state.read(IF) & 0x10, 0x00);
+    }
+
+    #[test]
+    fn test_p1_select_write_requests_joypad_interrupt() {
+        use crate::io::{IF, P1};
+        use crate::joypad::Button;
+
+        let mut state = GameBoy::<FlatMemory>::new();
+        state.write(IF, 0x00);
+        state.write(P1, 0x30);
+        state.joypad.press(Button::Start);
+
+        // LD A,$10; LDH (P1),A; NOP
+        for (i, byte) in [0x3E, 0x10, 0xE0, 0x00, 0x00].into_iter().enumerate() {
+            state.write(0xC000 + i as u16, byte);
+        }
+        state.pc = 0xC000;
+
+        state.step();
+        state.step();
+        state.step();
+        assert_eq!(state.read(IF) & 0x10, 0x10);
+    }
+
+    #[test]
+    fn test_stop_waits_for_joypad_input() {
+        use crate::io::P1;
+        use crate::joypad::Button;
+
+        let mut state = GameBoy::<FlatMemory>::new();
+        state.write(P1, 0x10); // Select actions
+        state.write(0xC000, 0x10); // STOP
+        state.write(0xC001, 0x00);
+        state.write(0xC002, 0x00); // NOP
+        state.pc = 0xC000;
+
+        state.step();
+        assert!(state.stopped);
+        assert_eq!(state.pc, 0xC002);
+
+        state.step();
+        assert!(state.stopped);
+        assert_eq!(state.pc, 0xC002);
+
+        state.joypad.press(Button::A);
+        state.step();
+        assert!(!state.stopped);
+        assert_eq!(state.pc, 0xC003);
+    }
+
+    #[test]
+    fn test_stop_resets_div() {
+        use crate::io::DIV;
+
+        let mut state = GameBoy::<FlatMemory>::new();
+        state.timer.set_divider(0xABCC);
+        state.write(0xC000, 0x10); // STOP
+        state.write(0xC001, 0x00);
+        state.pc = 0xC000;
+
+        state.step();
+        assert!(state.stopped);
+        assert_eq!(state.read(DIV), 0x00);
+    }
+
+    #[test]
+    fn test_stop_halts_timers() {
+        use crate::io::{DIV, TAC, TIMA};
+
+        let mut state = GameBoy::<FlatMemory>::new();
+        state.write(TAC, 0x05); // Timer on, every 16 cycles
+        state.write(0xC000, 0x10); // STOP
+        state.write(0xC001, 0x00);
+        state.pc = 0xC000;
+        state.step();
+        let tima = state.read(TIMA);
+
+        let dots_before = state.dot_cycles;
+        for _ in 0..1000 {
+            state.step();
+        }
+        assert!(state.stopped);
+        assert_eq!(state.read(DIV), 0x00);
+        assert_eq!(state.read(TIMA), tima);
+        assert_eq!(state.dot_cycles - dots_before, 4000);
+    }
+
+    #[test]
+    fn test_key1_speed_switch() {
+        use crate::io::KEY1;
+        let mut state = GameBoy::<FlatMemory>::new();
+        state.model = Model::Cgb;
+
+        assert_eq!(state.read(KEY1), 0x7E);
+        state.write(KEY1, 0x01);
+        assert_eq!(state.read(KEY1), 0x7F);
+
+        // STOP performs the armed switch
+        state.pc = 0xC000;
+        state.write(0xC000, 0x10);
+        state.write(0xC001, 0x00);
+        state.step();
+
+        assert!(state.double_speed);
+        assert_eq!(state.read(KEY1), 0xFE);
+        assert_eq!(state.pc, 0xC002);
+
+        // Switching back works the same way
+        state.write(KEY1, 0x01);
+        state.pc = 0xC000;
+        state.step();
+        assert!(!state.double_speed);
+        assert_eq!(state.read(KEY1), 0x7E);
+    }
+
+    #[test]
+    fn test_key1_ignored_on_dmg() {
+        use crate::io::KEY1;
+        let mut state = GameBoy::<FlatMemory>::new();
+
+        state.write(KEY1, 0x01);
+        assert_eq!(state.read(KEY1), 0xFF);
+
+        state.pc = 0xC000;
+        state.write(0xC000, 0x10);
+        state.step();
+        assert!(!state.double_speed);
+    }
+
+    #[test]
+    fn test_double_speed_halves_ppu_clock() {
+        use crate::io::{TAC, TIMA};
+        let mut state = GameBoy::<FlatMemory>::new();
+        state.model = Model::Cgb;
+        state.double_speed = true;
+        state.write(TAC, 0x05); // 16 CPU cycles per increment
+        state.write(TIMA, 0x00);
+
+        // 64 NOPs = 256 CPU cycles
+        state.pc = 0xC000;
+        let dots_before = state.dot_cycles;
+        for _ in 0..64 {
+            state.step();
+        }
+
+        assert_eq!(state.dot_cycles - dots_before, 128);
+        assert_eq!(state.read(TIMA), 16);
+    }
+
+    #[test]
+    fn test_memory_reads_see_mid_instruction_timer() {
+        use crate::io::{TAC, TIMA};
+        let mut state = GameBoy::<FlatMemory>::new();
+        state.write(TAC, 0x05); // Divider bit 3
+        state.write(TIMA, 0x00);
+        state.timer.set_divider(0x0008);
+
+        // LDH A,(TIMA): the read happens on the third M-cycle, after bit 3 fell
+        state.pc = 0xC000;
+        state.write(0xC000, 0xF0);
+        state.write(0xC001, 0x05);
+        state.step();
+
+        assert_eq!(state.a, 0x01);
+        assert_eq!(state.cycles, 12);
+        assert_eq!(state.timer.divider(), 0x0014);
+    }
+
+    /// CGB whose background palette 0 is about to turn black, stopped at
+    /// the start of line 20 with the previous (white) frame still shown
+    fn cgb_at_line_20() -> GameBoy<Mmu> {
+        use crate::io::{BCPD, BCPS, LY};
+        use crate::ppu::Mode;
+
+        let cartridge = GameBoy::<Mmu>::default().mmu.cartridge;
+        let mut gameboy = GameBoy::with_model(cartridge, Model::Cgb);
+        gameboy.run_frame();
+        gameboy.run_frame();
+        assert!(gameboy.ppu.framebuffer()[20].iter().all(|&c| c == 0x7FFF));
+
+        while gameboy.read(LY) != 20 || gameboy.ppu.mode() != Mode::OamSearch {
+            gameboy.tick();
+        }
+        gameboy.write(BCPS, 0x80);
+        for _ in 0..8 {
+            gameboy.write(BCPD, 0x00);
+        }
+        gameboy
+    }
+
+    #[test]
+    fn test_general_purpose_dma_renders_lines_it_stalls_over() {
+        use crate::io::{HDMA1, HDMA2, HDMA3, HDMA4, HDMA5, LY};
+
+        let mut gameboy = cgb_at_line_20();
+        // 128 blocks from WRAM to 0x8800: 4096 cycles, about 9 lines
+        gameboy.write(HDMA1, 0xC0);
+        gameboy.write(HDMA2, 0x00);
+        gameboy.write(HDMA3, 0x08);
+        gameboy.write(HDMA4, 0x00);
+        gameboy.write(HDMA5, 0x7F);
+
+        assert_eq!(gameboy.read(LY), 28);
+        for line in 20..28 {
+            let framebuffer = gameboy.ppu.framebuffer();
+            assert!(framebuffer[line].iter().all(|&c| c == 0), "line {}", line);
+        }
+    }
+
+    #[test]
+    fn test_speed_switch_renders_lines_it_pauses_over() {
+        use crate::io::{KEY1, LY};
+
+        let mut gameboy = cgb_at_line_20();
+        // 2050 M-cycles at double speed plus the STOP fetch: 4104 dots,
+        // exactly 9 lines
+        gameboy.write(KEY1, 0x01);
+        gameboy.write(0xC000, 0x10);
+        gameboy.write(0xC001, 0x00);
+        gameboy.pc = 0xC000;
+        gameboy.step_with_ppu();
+
+        assert!(gameboy.double_speed);
+        assert_eq!(gameboy.read(LY), 29);
+        for line in 20..29 {
+            let framebuffer = gameboy.ppu.framebuffer();
+            assert!(framebuffer[line].iter().all(|&c| c == 0), "line {}", line);
+        }
+    }
+
+    #[test]
+    fn test_oam_bug_during_oam_search() {
+        let numbered: Vec<u8> = (0..0xA0).collect();
+
+        for model in [Model::Dmg, Model::Cgb] {
+            let cartridge = GameBoy::<Mmu>::default().mmu.cartridge;
+            let mut gameboy = GameBoy::with_model(cartridge, model);
+            for (addr, &byte) in (0xFE00..).zip(&numbered) {
+                gameboy.mmu.write(addr, byte);
+            }
+
+            // Outside OAM search nothing happens
+            while gameboy.ppu.oam_row().is_some() {
+                gameboy.tick();
+            }
+            gameboy.trigger_oam_bug(0xFE10, OamCorruption::Write);
+            assert_eq!(gameboy.mmu.oam(), &numbered[..]);
+
+            while gameboy.ppu.oam_row() != Some(5) {
+                gameboy.tick();
+            }
+            gameboy.trigger_oam_bug(0xFDFF, OamCorruption::Write);
+            assert_eq!(gameboy.mmu.oam(), &numbered[..]);
+
+            let mut expected = numbered.clone();
+            if model == Model::Dmg {
+                oam_bug::corrupt(&mut expected, 5, OamCorruption::Write);
+            }
+            gameboy.trigger_oam_bug(0xFEFF, OamCorruption::Write);
+            assert_eq!(gameboy.mmu.oam(), &expected[..], "{:?}", model);
+        }
+    }
+
+    #[test]
+    fn test_run_frame_stops_at_vblank() {
+        use crate::ppu::{DOTS_PER_FRAME, Mode};
+
+        let mut gameboy = GameBoy::<Mmu>::default();
+        let first = gameboy.run_frame();
+        assert_eq!(first.stop_reason, StopReason::VBlank);
+        assert_eq!(gameboy.ppu.mode(), Mode::VBlank);
+
+        // From one VBlank to the next is exactly one frame, give or take
+        // the instruction that crosses it
+        let start = gameboy.dot_cycles;
+        let result = gameboy.run_frame();
+        assert_eq!(result.stop_reason, StopReason::VBlank);
+        assert!(result.frame_ready);
+        assert_eq!(result.cycles, gameboy.dot_cycles - start);
+        assert!(result.cycles.abs_diff(DOTS_PER_FRAME) < 24);
+    }
+
+    #[test]
+    fn test_run_frame_with_lcd_off() {
+        use crate::io::LCDC;
+        use crate::ppu::DOTS_PER_FRAME;
+
+        let mut gameboy = GameBoy::<Mmu>::default();
+        gameboy.write(LCDC, 0x00);
+
+        let result = gameboy.run_frame();
+        assert_eq!(result.stop_reason, StopReason::CycleBudget);
+        assert!(result.cycles >= DOTS_PER_FRAME);
+        assert!(gameboy.run_frame().frame_ready);
+    }
+
+    #[test]
+    fn test_run_frame_returns_on_stop() {
+        let mut gameboy = GameBoy::<Mmu>::default();
+        gameboy.write(0xC000, 0x10); // STOP
+        gameboy.pc = 0xC000;
+
+        let result = gameboy.run_frame();
+        assert_eq!(result.stop_reason, StopReason::Stopped);
+        assert_eq!(result.cycles, 4);
+
+        // The system keeps its frame timing while stopped
+        assert_eq!(gameboy.run_frame().stop_reason, StopReason::VBlank);
+        assert!(gameboy.stopped);
+    }
+
+    #[test]
+    fn test_run_counts_frames() {
+        let mut gameboy = GameBoy::<Mmu>::default();
+        assert_eq!(gameboy.run_simple(3), 3);
+
+        let mut seen = 0;
+        let frames = gameboy.run(None, |_, result| {
+            assert_eq!(result.stop_reason, StopReason::VBlank);
+            seen += 1;
+            seen < 5
+        });
+        assert_eq!(frames, 5);
+    }
+
+    #[test]
+    fn test_skip_rendering_keeps_timing() {
+        use crate::io::BGP;
+
+        let mut drawn = GameBoy::<Mmu>::default();
+        let mut skipped = GameBoy::<Mmu> {
+            skip_rendering: true,
+            ..Default::default()
+        };
+        for gameboy in [&mut drawn, &mut skipped] {
+            gameboy.write(BGP, 0xFF);
+            gameboy.run_simple(3);
+        }
+
+        assert_eq!(skipped.dot_cycles, drawn.dot_cycles);
+        assert_eq!(skipped.mmu.read(0xFF0F), drawn.mmu.read(0xFF0F));
+        assert!(skipped.ppu.framebuffer() != drawn.ppu.framebuffer());
+
+        skipped.skip_rendering = false;
+        skipped.run_frame();
+        drawn.run_frame();
+        assert!(skipped.ppu.framebuffer() == drawn.ppu.framebuffer());
+    }
+
+    /// Game Boy with an MBC1 cartridge with 8 KiB of RAM, with 0x42 written
+    /// to the start of it and some other state changed
+    fn gameboy_with_cartridge_ram() -> GameBoy<Mmu> {
+        let mut rom = vec![0; 32 * 1024];
+        rom[0x0147] = 0x03; // MBC1+RAM+BATTERY
+        rom[0x0149] = 0x02; // 8 KiB RAM
+        let mut checksum: u8 = 0;
+        for &byte in &rom[0x0134..=0x014C] {
+            checksum = checksum.wrapping_sub(byte).wrapping_sub(1);
+        }
+        rom[0x014D] = checksum;
+        let cartridge = Cartridge::from_bytes(rom).unwrap();
+
+        let mut gameboy = GameBoy::with_cartridge(cartridge);
+        gameboy.write(0x0000, 0x0A); // Enable RAM
+        gameboy.write(0xA000, 0x42);
+        gameboy.write(0xC000, 0x99);
+        gameboy.ppu.set_palette(Palette::builtin("pocket").unwrap());
+        gameboy.run_simple(2);
+        gameboy.pc = 0x1234;
+        gameboy
+    }
+
+    #[test]
+    fn test_reset_keeps_cartridge_ram() {
+        let mut gameboy = gameboy_with_cartridge_ram();
+        gameboy.reset();
+
+        assert_eq!(gameboy.pc(), 0x0100);
+        assert_eq!(gameboy.af(), 0x01B0);
+        assert_eq!(gameboy.dot_cycles, 0);
+        assert_eq!(gameboy.read(0xC000), 0x00);
+        assert_eq!(gameboy.ppu.palette(), Palette::builtin("pocket").unwrap());
+
+        // RAM is disabled again after the reset
+        assert_eq!(gameboy.read(0xA000), 0xFF);
+        gameboy.write(0x0000, 0x0A);
+        assert_eq!(gameboy.read(0xA000), 0x42);
+    }
+
+    #[test]
+    fn test_hard_reset_clears_cartridge_ram() {
+        let mut gameboy = gameboy_with_cartridge_ram();
+        gameboy.hard_reset();
+
+        assert_eq!(gameboy.pc(), 0x0100);
+        gameboy.write(0x0000, 0x0A);
+        assert_eq!(gameboy.read(0xA000), 0x00);
+    }
 }
@@ -0,0 +1,271 @@
+/// Timer (DIV, TIMA, TMA, TAC)
+///
+/// DIV is the upper byte of a 16-bit divider that counts CPU cycles. TIMA
+/// is not clocked on its own: it increments on a falling edge of one
+/// divider bit (selected by TAC) ANDed with the timer enable bit. Because of
+/// this, resetting DIV or changing TAC can also increment TIMA.
+///
+/// When TIMA overflows it reads 0x00 for one M-cycle before TMA is loaded
+/// and the interrupt is requested. Writing TIMA during that cycle cancels
+/// the reload; writes to TIMA on the reload cycle itself are ignored, and
+/// writes to TMA on that cycle go straight through to TIMA.
+///
+/// Registers:
+/// DIV (0xFF04): Bits 8-15 of the divider, any write resets the divider
+/// TIMA (0xFF05): Timer counter
+/// TMA (0xFF06): Value loaded into TIMA on overflow
+/// TAC (0xFF07): Bit 2 = enable, Bit 0-1 = clock select
+pub struct Timer {
+    divider: u16,
+    tima: u8,
+    tma: u8,
+    tac: u8,
+    reload: Reload,
+    /// CPU cycles not yet making up a full M-cycle
+    leftover_cycles: u64,
+    /// Set when TIMA is reloaded after an overflow; the system requests
+    /// the timer interrupt and clears it
+    pub interrupt: bool,
+}
+
+/// Progress of a TIMA overflow
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Reload {
+    Idle,
+    /// TIMA overflowed and reads 0x00; TMA is loaded on the next M-cycle
+    Pending,
+    /// TMA was loaded into TIMA during this M-cycle
+    Reloading,
+}
+
+/// CPU cycles per M-cycle
+const CYCLES_PER_TICK: u64 = 4;
+
+/// Divider bit that clocks TIMA for each TAC clock select
+///
+/// 00: 4096 Hz (every 1024 cycles), 01: 262144 Hz (every 16 cycles),
+/// 10: 65536 Hz (every 64 cycles), 11: 16384 Hz (every 256 cycles)
+const TAC_DIVIDER_BITS: [u16; 4] = [1 << 9, 1 << 3, 1 << 5, 1 << 7];
+
+impl Timer {
+    pub fn new() -> Self {
+        Self {
+            divider: 0,
+            tima: 0,
+            tma: 0,
+            tac: 0,
+            reload: Reload::Idle,
+            leftover_cycles: 0,
+            interrupt: false,
+        }
+    }
+
+    /// Full 16-bit divider
+    pub fn divider(&self) -> u16 {
+        self.divider
+    }
+
+    /// Set the full 16-bit divider (e.g. to its post-boot value)
+    pub fn set_divider(&mut self, divider: u16) {
+        self.divider = divider;
+    }
+
+    /// Advance the timer by a number of CPU cycles
+    pub fn step(&mut self, cycles: u64) {
+        self.leftover_cycles += cycles;
+        while self.leftover_cycles >= CYCLES_PER_TICK {
+            self.leftover_cycles -= CYCLES_PER_TICK;
+            self.tick();
+        }
+    }
+
+    /// Advance the timer by one M-cycle
+    pub fn tick(&mut self) {
+        match self.reload {
+            Reload::Pending => {
+                self.tima = self.tma;
+                self.interrupt = true;
+                self.reload = Reload::Reloading;
+            }
+            Reload::Reloading => self.reload = Reload::Idle,
+            Reload::Idle => {}
+        }
+
+        let input = self.input();
+        self.divider = self.divider.wrapping_add(CYCLES_PER_TICK as u16);
+        self.detect_falling_edge(input);
+    }
+
+    pub fn read_div(&self) -> u8 {
+        (self.divider >> 8) as u8
+    }
+
+    pub fn read_tima(&self) -> u8 {
+        self.tima
+    }
+
+    pub fn read_tma(&self) -> u8 {
+        self.tma
+    }
+
+    pub fn read_tac(&self) -> u8 {
+        0xF8 | self.tac
+    }
+
+    /// Write DIV: any value resets the whole divider
+    pub fn write_div(&mut self, _value: u8) {
+        let input = self.input();
+        self.divider = 0;
+        self.detect_falling_edge(input);
+    }
+
+    pub fn write_tima(&mut self, value: u8) {
+        match self.reload {
+            // Cancels the pending reload and interrupt
+            Reload::Pending => {
+                self.tima = value;
+                self.reload = Reload::Idle;
+            }
+            // TMA is being loaded this cycle and wins
+            Reload::Reloading => {}
+            Reload::Idle => self.tima = value,
+        }
+    }
+
+    pub fn write_tma(&mut self, value: u8) {
+        self.tma = value;
+        if self.reload == Reload::Reloading {
+            self.tima = value;
+        }
+    }
+
+    pub fn write_tac(&mut self, value: u8) {
+        let input = self.input();
+        self.tac = value & 0x07;
+        self.detect_falling_edge(input);
+    }
+
+    /// Timer input: the selected divider bit ANDed with the enable bit
+    fn input(&self) -> bool {
+        self.tac & 0x04 != 0 && self.divider & TAC_DIVIDER_BITS[(self.tac & 0x03) as usize] != 0
+    }
+
+    /// Increment TIMA if the timer input went from high to low
+    fn detect_falling_edge(&mut self, previous: bool) {
+        if !previous || self.input() {
+            return;
+        }
+
+        let (tima, overflow) = self.tima.overflowing_add(1);
+        self.tima = tima;
+        if overflow {
+            self.reload = Reload::Pending;
+        }
+    }
+}
+
+impl Default for Timer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_div_counts_every_256_cycles() {
+        let mut timer = Timer::new();
+        timer.step(255);
+        assert_eq!(timer.read_div(), 0x00);
+        timer.step(1);
+        assert_eq!(timer.read_div(), 0x01);
+
+        timer.write_div(0x42);
+        assert_eq!(timer.read_div(), 0x00);
+        assert_eq!(timer.divider(), 0x0000);
+    }
+
+    #[test]
+    fn test_overflow_reloads_after_one_m_cycle() {
+        let mut timer = Timer::new();
+        timer.write_tima(0xFF);
+        timer.write_tma(0x80);
+        timer.write_tac(0x05); // 16 cycles per increment
+
+        timer.step(16);
+        assert_eq!(timer.read_tima(), 0x00, "reads 0 during the delay");
+        assert!(!timer.interrupt);
+
+        timer.step(4);
+        assert_eq!(timer.read_tima(), 0x80);
+        assert!(timer.interrupt);
+    }
+
+    #[test]
+    fn test_tima_write_cancels_pending_reload() {
+        let mut timer = Timer::new();
+        timer.write_tima(0xFF);
+        timer.write_tma(0x80);
+        timer.write_tac(0x05);
+
+        timer.step(16);
+        timer.write_tima(0x12);
+        timer.step(4);
+        assert_eq!(timer.read_tima(), 0x12);
+        assert!(!timer.interrupt);
+    }
+
+    #[test]
+    fn test_writes_on_reload_cycle() {
+        let mut timer = Timer::new();
+        timer.write_tima(0xFF);
+        timer.write_tma(0x80);
+        timer.write_tac(0x05);
+        timer.step(20);
+
+        // TIMA writes are ignored, TMA writes reach TIMA
+        timer.write_tima(0x12);
+        assert_eq!(timer.read_tima(), 0x80);
+        timer.write_tma(0x34);
+        assert_eq!(timer.read_tima(), 0x34);
+
+        // Back to normal on the next cycle
+        timer.step(4);
+        timer.write_tima(0x56);
+        assert_eq!(timer.read_tima(), 0x56);
+    }
+
+    #[test]
+    fn test_div_reset_ticks_tima_on_falling_edge() {
+        let mut timer = Timer::new();
+        timer.write_tac(0x04); // Divider bit 9
+        timer.set_divider(0x0200);
+
+        timer.write_div(0);
+        assert_eq!(timer.read_tima(), 0x01);
+
+        // Bit 9 already low: no increment
+        timer.write_div(0);
+        assert_eq!(timer.read_tima(), 0x01);
+    }
+
+    #[test]
+    fn test_tac_change_ticks_tima_on_falling_edge() {
+        let mut timer = Timer::new();
+        timer.set_divider(0x0008); // Bit 3 high, bit 5 low
+        timer.write_tac(0x05);
+        assert_eq!(timer.read_tima(), 0x00);
+
+        // Selecting a bit that is low
+        timer.write_tac(0x06);
+        assert_eq!(timer.read_tima(), 0x01);
+
+        // Disabling the timer while the selected bit is high
+        timer.write_tac(0x05);
+        timer.write_tac(0x01);
+        assert_eq!(timer.read_tima(), 0x02);
+        assert_eq!(timer.read_tac(), 0xF9);
+    }
+}
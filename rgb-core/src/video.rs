@@ -0,0 +1,263 @@
+/// Video output: converting the PPU framebuffer to displayable colors
+///
+/// DMG frames hold 2-bit shades which are mapped through a `Palette`.
+/// CGB frames hold raw 15-bit colors which look oversaturated when shown
+/// as-is on a modern display, so they go through a `ColorCorrection` curve.
+use crate::model::Model;
+use crate::ppu::{Framebuffer, SCREEN_HEIGHT, SCREEN_WIDTH};
+
+pub mod filters;
+
+/// An 8-bit per channel RGB color
+pub type Rgb = [u8; 3];
+
+/// Size in bytes of an RGBA frame (160x144 pixels, 4 bytes each)
+pub const RGBA_FRAME_SIZE: usize = SCREEN_WIDTH * SCREEN_HEIGHT * 4;
+
+/// Colors used for the four DMG shades (0 = lightest, 3 = darkest)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Palette {
+    pub colors: [Rgb; 4],
+}
+
+impl Palette {
+    /// Original DMG green LCD
+    pub const CLASSIC_GREEN: Palette = Palette {
+        colors: [
+            [0x9B, 0xBC, 0x0F],
+            [0x8B, 0xAC, 0x0F],
+            [0x30, 0x62, 0x30],
+            [0x0F, 0x38, 0x0F],
+        ],
+    };
+
+    /// Neutral gray levels
+    pub const GRAYSCALE: Palette = Palette {
+        colors: [
+            [0xFF, 0xFF, 0xFF],
+            [0xAA, 0xAA, 0xAA],
+            [0x55, 0x55, 0x55],
+            [0x00, 0x00, 0x00],
+        ],
+    };
+
+    /// Game Boy Pocket (greenish gray LCD)
+    pub const POCKET: Palette = Palette {
+        colors: [
+            [0xC4, 0xCF, 0xA1],
+            [0x8B, 0x95, 0x6D],
+            [0x4D, 0x53, 0x3C],
+            [0x1F, 0x1F, 0x1F],
+        ],
+    };
+
+    /// Game Boy Light (blue-green backlit LCD)
+    pub const LIGHT: Palette = Palette {
+        colors: [
+            [0x00, 0xB5, 0x81],
+            [0x00, 0x9A, 0x71],
+            [0x00, 0x69, 0x4A],
+            [0x00, 0x4F, 0x3B],
+        ],
+    };
+
+    /// All built-in palettes with their names
+    pub const BUILTIN: [(&'static str, Palette); 4] = [
+        ("classic", Palette::CLASSIC_GREEN),
+        ("grayscale", Palette::GRAYSCALE),
+        ("pocket", Palette::POCKET),
+        ("light", Palette::LIGHT),
+    ];
+
+    /// Create a user-defined palette from lightest to darkest color
+    pub fn new(colors: [Rgb; 4]) -> Self {
+        Palette { colors }
+    }
+
+    /// Create a user-defined palette from 0xRRGGBB values
+    pub fn from_hex(colors: [u32; 4]) -> Self {
+        Palette {
+            colors: colors.map(|color| {
+                let [_, r, g, b] = color.to_be_bytes();
+                [r, g, b]
+            }),
+        }
+    }
+
+    /// Look up a built-in palette by name (case-insensitive)
+    pub fn builtin(name: &str) -> Option<Palette> {
+        Self::BUILTIN
+            .iter()
+            .find(|(builtin, _)| builtin.eq_ignore_ascii_case(name))
+            .map(|(_, palette)| *palette)
+    }
+
+    /// Color for a 2-bit shade
+    pub fn color(&self, shade: u16) -> Rgb {
+        self.colors[(shade & 0x03) as usize]
+    }
+}
+
+impl Default for Palette {
+    fn default() -> Self {
+        Palette::CLASSIC_GREEN
+    }
+}
+
+/// How CGB 15-bit colors are turned into 8-bit RGB
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ColorCorrection {
+    /// Scale each 5-bit channel linearly (raw, oversaturated colors)
+    None,
+    /// Per-channel brightness curve of the CGB LCD (SameBoy-style)
+    Curves,
+    /// Channel mixing that mimics the washed-out CGB LCD (Gambatte-style)
+    #[default]
+    LcdMix,
+}
+
+impl ColorCorrection {
+    /// All color correction modes with their names
+    pub const ALL: [(&'static str, ColorCorrection); 3] = [
+        ("none", ColorCorrection::None),
+        ("curves", ColorCorrection::Curves),
+        ("lcd", ColorCorrection::LcdMix),
+    ];
+
+    /// Look up a color correction mode by name (case-insensitive)
+    pub fn from_name(name: &str) -> Option<ColorCorrection> {
+        Self::ALL
+            .iter()
+            .find(|(mode, _)| mode.eq_ignore_ascii_case(name))
+            .map(|(_, correction)| *correction)
+    }
+
+    /// Convert a 15-bit CGB color (bits 0-4 red, 5-9 green, 10-14 blue)
+    pub fn apply(&self, color: u16) -> Rgb {
+        let r = (color & 0x1F) as u32;
+        let g = ((color >> 5) & 0x1F) as u32;
+        let b = ((color >> 10) & 0x1F) as u32;
+
+        match self {
+            ColorCorrection::None => {
+                let expand = |c: u32| ((c << 3) | (c >> 2)) as u8;
+                [expand(r), expand(g), expand(b)]
+            }
+            ColorCorrection::Curves => [
+                CHANNEL_CURVE[r as usize],
+                CHANNEL_CURVE[g as usize],
+                CHANNEL_CURVE[b as usize],
+            ],
+            ColorCorrection::LcdMix => [
+                ((r * 13 + g * 2 + b) >> 1) as u8,
+                ((g * 3 + b) << 1) as u8,
+                ((r * 3 + g * 2 + b * 11) >> 1) as u8,
+            ],
+        }
+    }
+}
+
+/// Brightness response of a CGB LCD channel for each 5-bit level
+const CHANNEL_CURVE: [u8; 32] = [
+    0, 6, 12, 20, 28, 36, 45, 56, 66, 76, 88, 100, 113, 125, 137, 149, 161, 172, 182, 192, 202,
+    210, 218, 225, 232, 238, 243, 247, 250, 252, 254, 255,
+];
+
+/// Convert a framebuffer to RGBA
+///
+/// `out` must hold at least `RGBA_FRAME_SIZE` bytes. DMG shades go through
+/// `palette`, CGB colors through `correction`.
+pub fn framebuffer_to_rgba(
+    framebuffer: &Framebuffer,
+    model: Model,
+    palette: &Palette,
+    correction: ColorCorrection,
+    out: &mut [u8],
+) {
+    assert!(
+        out.len() >= RGBA_FRAME_SIZE,
+        "RGBA buffer too small: {} bytes (need {})",
+        out.len(),
+        RGBA_FRAME_SIZE
+    );
+
+    let pixels = framebuffer.iter().flat_map(|row| row.iter());
+    for (pixel, rgba) in pixels.zip(out.chunks_exact_mut(4)) {
+        let [r, g, b] = match model {
+            Model::Dmg | Model::Sgb => palette.color(*pixel),
+            Model::Cgb => correction.apply(*pixel),
+        };
+        rgba.copy_from_slice(&[r, g, b, 0xFF]);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_builtin_palette_lookup() {
+        assert_eq!(Palette::builtin("Pocket"), Some(Palette::POCKET));
+        assert_eq!(Palette::builtin("grayscale"), Some(Palette::GRAYSCALE));
+        assert_eq!(Palette::builtin("sepia"), None);
+        assert_eq!(Palette::default(), Palette::CLASSIC_GREEN);
+    }
+
+    #[test]
+    fn test_user_palette_from_hex() {
+        let palette = Palette::from_hex([0xFFEEDD, 0xAABBCC, 0x112233, 0x000000]);
+        assert_eq!(palette.color(0), [0xFF, 0xEE, 0xDD]);
+        assert_eq!(palette.color(2), [0x11, 0x22, 0x33]);
+    }
+
+    #[test]
+    fn test_color_correction_extremes() {
+        for (_, correction) in ColorCorrection::ALL {
+            assert_eq!(correction.apply(0x0000), [0, 0, 0]);
+        }
+
+        assert_eq!(ColorCorrection::None.apply(0x7FFF), [0xFF, 0xFF, 0xFF]);
+        assert_eq!(ColorCorrection::Curves.apply(0x7FFF), [0xFF, 0xFF, 0xFF]);
+        assert_eq!(ColorCorrection::LcdMix.apply(0x7FFF), [248, 248, 248]);
+    }
+
+    #[test]
+    fn test_color_correction_desaturates_pure_red() {
+        let raw = ColorCorrection::None.apply(0x001F);
+        assert_eq!(raw, [0xFF, 0x00, 0x00]);
+
+        // The CGB LCD bleeds red into the blue channel
+        let mixed = ColorCorrection::LcdMix.apply(0x001F);
+        assert!(mixed[0] < raw[0]);
+        assert!(mixed[2] > 0);
+    }
+
+    #[test]
+    fn test_framebuffer_to_rgba() {
+        let mut framebuffer = [[0u16; SCREEN_WIDTH]; SCREEN_HEIGHT];
+        framebuffer[0][1] = 3;
+        framebuffer[143][159] = 0x001F;
+
+        let mut out = vec![0; RGBA_FRAME_SIZE];
+        let palette = Palette::GRAYSCALE;
+
+        framebuffer_to_rgba(
+            &framebuffer,
+            Model::Dmg,
+            &palette,
+            ColorCorrection::None,
+            &mut out,
+        );
+        assert_eq!(out[0..4], [0xFF, 0xFF, 0xFF, 0xFF]);
+        assert_eq!(out[4..8], [0x00, 0x00, 0x00, 0xFF]);
+
+        framebuffer_to_rgba(
+            &framebuffer,
+            Model::Cgb,
+            &palette,
+            ColorCorrection::None,
+            &mut out,
+        );
+        assert_eq!(out[RGBA_FRAME_SIZE - 4..], [0xFF, 0x00, 0x00, 0xFF]);
+    }
+}
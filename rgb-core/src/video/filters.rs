@@ -0,0 +1,358 @@
+/// An RGBA pixel
+type Pixel = [u8; 4];
+
+/// Upscaling filter for RGBA frames
+///
+/// Upscales a `width` x `height` RGBA frame, such as the output of
+/// `GameBoy::framebuffer_rgba`, into a frame `scale()` times bigger. Edge
+/// pixels are repeated when a filter looks past the border. `Ghosting`
+/// blends consecutive frames and runs on the unscaled frame before this.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Filter {
+    /// Nearest neighbour, no upscaling
+    #[default]
+    None,
+    /// EPX / Scale2x: 2x, extends diagonal edges without new colors
+    Scale2x,
+    /// AdvMAME3x / Scale3x: 3x variant of Scale2x
+    Scale3x,
+    /// HQ2x-style: 2x, interpolates along edges found by color difference
+    Hq2x,
+    /// 3x with dark gaps between pixels, like the DMG dot-matrix LCD
+    LcdGrid,
+}
+
+impl Filter {
+    /// All filters with their names
+    pub const ALL: [(&'static str, Filter); 5] = [
+        ("none", Filter::None),
+        ("scale2x", Filter::Scale2x),
+        ("scale3x", Filter::Scale3x),
+        ("hq2x", Filter::Hq2x),
+        ("lcd", Filter::LcdGrid),
+    ];
+
+    /// Look up a filter by name (case-insensitive)
+    pub fn from_name(name: &str) -> Option<Filter> {
+        Self::ALL
+            .iter()
+            .find(|(filter, _)| filter.eq_ignore_ascii_case(name))
+            .map(|(_, filter)| *filter)
+    }
+
+    /// Output size as a multiple of the input size
+    pub fn scale(self) -> usize {
+        match self {
+            Filter::None => 1,
+            Filter::Scale2x | Filter::Hq2x => 2,
+            Filter::Scale3x | Filter::LcdGrid => 3,
+        }
+    }
+
+    /// Filter a `width` x `height` RGBA frame into `out`
+    ///
+    /// `out` is resized to `width * scale() * height * scale() * 4` bytes,
+    /// so a buffer kept between frames is only allocated once.
+    pub fn apply(self, src: &[u8], width: usize, height: usize, out: &mut Vec<u8>) {
+        assert_eq!(src.len(), width * height * 4, "RGBA frame size mismatch");
+
+        let scale = self.scale();
+        out.resize(src.len() * scale * scale, 0);
+        match self {
+            Filter::None => out.copy_from_slice(src),
+            Filter::Scale2x => for_each_block(src, width, height, out, scale2x),
+            Filter::Scale3x => for_each_block(src, width, height, out, scale3x),
+            Filter::Hq2x => for_each_block(src, width, height, out, hq2x),
+            Filter::LcdGrid => for_each_block(src, width, height, out, lcd_grid),
+        }
+    }
+}
+
+/// Run `kernel` on the 3x3 neighbourhood (A B C / D E F / G H I) of every
+/// source pixel and write the N x N block it returns for the center one
+fn for_each_block<const N: usize>(
+    src: &[u8],
+    width: usize,
+    height: usize,
+    out: &mut [u8],
+    kernel: impl Fn(&[Pixel; 9]) -> [[Pixel; N]; N],
+) {
+    let pixel = |x: usize, y: usize| -> Pixel {
+        let offset = (y * width + x) * 4;
+        src[offset..offset + 4].try_into().unwrap()
+    };
+    let out_width = width * N;
+
+    for y in 0..height {
+        let rows = [y.saturating_sub(1), y, (y + 1).min(height - 1)];
+        for x in 0..width {
+            let columns = [x.saturating_sub(1), x, (x + 1).min(width - 1)];
+            let neighbours =
+                [0, 1, 2, 3, 4, 5, 6, 7, 8].map(|i| pixel(columns[i % 3], rows[i / 3]));
+
+            for (dy, block_row) in kernel(&neighbours).iter().enumerate() {
+                let offset = ((y * N + dy) * out_width + x * N) * 4;
+                for (dx, color) in block_row.iter().enumerate() {
+                    out[offset + dx * 4..offset + dx * 4 + 4].copy_from_slice(color);
+                }
+            }
+        }
+    }
+}
+
+fn scale2x(&[_, b, _, d, e, f, _, h, _]: &[Pixel; 9]) -> [[Pixel; 2]; 2] {
+    if b == h || d == f {
+        return [[e; 2]; 2];
+    }
+    [
+        [if d == b { d } else { e }, if b == f { f } else { e }],
+        [if d == h { d } else { e }, if h == f { f } else { e }],
+    ]
+}
+
+fn scale3x(&[a, b, c, d, e, f, g, h, i]: &[Pixel; 9]) -> [[Pixel; 3]; 3] {
+    if b == h || d == f {
+        return [[e; 3]; 3];
+    }
+    let pick = |condition: bool, color: Pixel| if condition { color } else { e };
+    [
+        [
+            pick(d == b, d),
+            pick((d == b && e != c) || (b == f && e != a), b),
+            pick(b == f, f),
+        ],
+        [
+            pick((d == b && e != g) || (d == h && e != a), d),
+            e,
+            pick((b == f && e != i) || (h == f && e != c), f),
+        ],
+        [
+            pick(d == h, d),
+            pick((d == h && e != i) || (h == f && e != g), h),
+            pick(h == f, f),
+        ],
+    ]
+}
+
+/// Like Scale2x, but neighbours are compared by perceived difference (the
+/// YUV thresholds of HQx) and edges are blended rather than copied
+fn hq2x(&[a, b, c, d, e, f, g, h, i]: &[Pixel; 9]) -> [[Pixel; 2]; 2] {
+    // Corner between orthogonal neighbours `p` and `q`, across diagonal `r`
+    let corner = |p: Pixel, q: Pixel, r: Pixel| {
+        if !similar(p, q) || similar(e, p) {
+            e
+        } else if similar(e, r) {
+            // A shallow edge passes through the corner
+            blend(e, blend(p, q, 1, 1), 3, 1)
+        } else {
+            blend(e, blend(p, q, 1, 1), 1, 1)
+        }
+    };
+    [
+        [corner(b, d, a), corner(b, f, c)],
+        [corner(h, d, g), corner(h, f, i)],
+    ]
+}
+
+/// Pixel repeated 3x3 with its right column and bottom row darkened
+fn lcd_grid(&[_, _, _, _, e, _, _, _, _]: &[Pixel; 9]) -> [[Pixel; 3]; 3] {
+    let gap = blend(e, [0, 0, 0, e[3]], 3, 1);
+    [[e, e, gap], [e, e, gap], [gap, gap, gap]]
+}
+
+/// Weighted average of two colors, alpha taken from `x`
+fn blend(x: Pixel, y: Pixel, x_weight: u32, y_weight: u32) -> Pixel {
+    let total = x_weight + y_weight;
+    let mix = |i: usize| ((x[i] as u32 * x_weight + y[i] as u32 * y_weight) / total) as u8;
+    [mix(0), mix(1), mix(2), x[3]]
+}
+
+/// Whether two colors look alike (HQx YUV thresholds)
+fn similar(x: Pixel, y: Pixel) -> bool {
+    let yuv = |p: Pixel| {
+        let [r, g, b] = [p[0] as i32, p[1] as i32, p[2] as i32];
+        [
+            (299 * r + 587 * g + 114 * b) / 1000,
+            (-169 * r - 331 * g + 500 * b) / 1000,
+            (500 * r - 419 * g - 81 * b) / 1000,
+        ]
+    };
+    let ([y1, u1, v1], [y2, u2, v2]) = (yuv(x), yuv(y));
+    (y1 - y2).abs() <= 48 && (u1 - u2).abs() <= 7 && (v1 - v2).abs() <= 6
+}
+
+/// DMG LCD ghosting: each frame is blended with the one before it
+///
+/// The DMG LCD is slow to change, so games that flicker objects on
+/// alternate frames show them as transparent. Without the blend they
+/// flicker at 30 Hz.
+#[derive(Debug, Clone)]
+pub struct Ghosting {
+    /// Weight of the previous frame (0 = off, 1 = previous frame only)
+    strength: f32,
+    previous: Vec<u8>,
+}
+
+impl Ghosting {
+    /// Weight of the previous frame that gives flickering objects 50% opacity
+    pub const DEFAULT_STRENGTH: f32 = 0.5;
+
+    pub fn new(strength: f32) -> Self {
+        Ghosting {
+            strength: strength.clamp(0.0, 1.0),
+            previous: Vec::new(),
+        }
+    }
+
+    /// Blend `frame` (RGBA) with the previous frame passed in, in place
+    ///
+    /// The first frame, or one of a different size, is left as is.
+    pub fn apply(&mut self, frame: &mut [u8]) {
+        if self.previous.len() != frame.len() {
+            self.previous = frame.to_vec();
+            return;
+        }
+
+        let weight = (self.strength * 256.0) as u32;
+        for (i, (current, previous)) in frame.iter_mut().zip(&mut self.previous).enumerate() {
+            let value = *current;
+            if i % 4 != 3 {
+                *current = ((value as u32 * (256 - weight) + *previous as u32 * weight) >> 8) as u8;
+            }
+            *previous = value;
+        }
+    }
+
+    /// Forget the previous frame, e.g. after loading another ROM
+    pub fn clear(&mut self) {
+        self.previous.clear();
+    }
+}
+
+impl Default for Ghosting {
+    fn default() -> Self {
+        Self::new(Self::DEFAULT_STRENGTH)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const WHITE: Pixel = [0xFF, 0xFF, 0xFF, 0xFF];
+    const BLACK: Pixel = [0x00, 0x00, 0x00, 0xFF];
+
+    /// RGBA frame from rows of pixels
+    fn frame(rows: &[&[Pixel]]) -> Vec<u8> {
+        rows.iter()
+            .flat_map(|row| row.iter().flatten())
+            .copied()
+            .collect()
+    }
+
+    fn pixel_at(frame: &[u8], width: usize, x: usize, y: usize) -> Pixel {
+        let offset = (y * width + x) * 4;
+        frame[offset..offset + 4].try_into().unwrap()
+    }
+
+    #[test]
+    fn test_filter_lookup() {
+        assert_eq!(Filter::from_name("Scale2x"), Some(Filter::Scale2x));
+        assert_eq!(Filter::from_name("lcd"), Some(Filter::LcdGrid));
+        assert_eq!(Filter::from_name("bilinear"), None);
+        assert_eq!(Filter::default(), Filter::None);
+    }
+
+    #[test]
+    fn test_flat_frames_stay_flat() {
+        let row: &[Pixel] = &[WHITE; 4];
+        let src = frame(&[row; 3]);
+        let mut out = Vec::new();
+        for (_, filter) in Filter::ALL {
+            filter.apply(&src, 4, 3, &mut out);
+            let scale = filter.scale();
+            assert_eq!(out.len(), src.len() * scale * scale, "{:?}", filter);
+            if filter != Filter::LcdGrid {
+                assert!(out.chunks_exact(4).all(|p| p == WHITE), "{:?}", filter);
+            }
+        }
+    }
+
+    /// A black 2x2 block in the bottom-right of a white 3x3 frame
+    fn black_corner() -> Vec<u8> {
+        frame(&[
+            &[WHITE, WHITE, WHITE],
+            &[WHITE, BLACK, BLACK],
+            &[WHITE, BLACK, BLACK],
+        ])
+    }
+
+    #[test]
+    fn test_scale2x_rounds_corners() {
+        let mut out = Vec::new();
+        Filter::Scale2x.apply(&black_corner(), 3, 3, &mut out);
+
+        // Only the outer corner of the black block is cut off
+        assert_eq!(pixel_at(&out, 6, 2, 2), WHITE);
+        assert_eq!(pixel_at(&out, 6, 3, 2), BLACK);
+        assert_eq!(pixel_at(&out, 6, 2, 3), BLACK);
+        assert_eq!(pixel_at(&out, 6, 3, 3), BLACK);
+    }
+
+    #[test]
+    fn test_scale3x_rounds_corners() {
+        let mut out = Vec::new();
+        Filter::Scale3x.apply(&black_corner(), 3, 3, &mut out);
+
+        // The corner is cut along a diagonal three subpixels long
+        assert_eq!(pixel_at(&out, 9, 3, 3), WHITE);
+        assert_eq!(pixel_at(&out, 9, 4, 3), WHITE);
+        assert_eq!(pixel_at(&out, 9, 3, 4), WHITE);
+        assert_eq!(pixel_at(&out, 9, 5, 3), BLACK);
+        assert_eq!(pixel_at(&out, 9, 4, 4), BLACK);
+    }
+
+    #[test]
+    fn test_hq2x_blends_edges() {
+        let mut out = Vec::new();
+        Filter::Hq2x.apply(&black_corner(), 3, 3, &mut out);
+
+        let corner = pixel_at(&out, 6, 2, 2);
+        assert!(corner != BLACK && corner != WHITE, "{:?}", corner);
+        assert_eq!(pixel_at(&out, 6, 3, 3), BLACK);
+    }
+
+    #[test]
+    fn test_lcd_grid_darkens_gaps() {
+        let src = frame(&[&[WHITE]]);
+        let mut out = Vec::new();
+        Filter::LcdGrid.apply(&src, 1, 1, &mut out);
+
+        assert_eq!(pixel_at(&out, 3, 0, 0), WHITE);
+        assert_eq!(pixel_at(&out, 3, 2, 0), [0xBF, 0xBF, 0xBF, 0xFF]);
+        assert_eq!(pixel_at(&out, 3, 1, 2), [0xBF, 0xBF, 0xBF, 0xFF]);
+    }
+
+    #[test]
+    fn test_ghosting_blends_with_previous_frame() {
+        let mut ghosting = Ghosting::default();
+
+        let mut first = frame(&[&[WHITE]]);
+        ghosting.apply(&mut first);
+        assert_eq!(first, frame(&[&[WHITE]]));
+
+        // An object shown every other frame ends up half transparent
+        let mut second = frame(&[&[BLACK]]);
+        ghosting.apply(&mut second);
+        assert_eq!(second, frame(&[&[[0x7F, 0x7F, 0x7F, 0xFF]]]));
+
+        let mut third = frame(&[&[WHITE]]);
+        ghosting.apply(&mut third);
+        assert_eq!(third, frame(&[&[[0x7F, 0x7F, 0x7F, 0xFF]]]));
+
+        ghosting.clear();
+        let mut fresh = frame(&[&[BLACK]]);
+        ghosting.apply(&mut fresh);
+        assert_eq!(fresh, frame(&[&[BLACK]]));
+    }
+}
@@ -0,0 +1,78 @@
+/// ROM archive loading tests
+///
+/// The fixtures in `test-roms` hold the same 32 KiB ROM (title "ARCHIVE"):
+/// archive.zip (deflated, after a readme.txt entry) was made with Info-ZIP,
+/// archive.gb.gz with `gzip -n -9` and archive.7z (LZMA, after a
+/// readme.txt entry) with sevenz-rust.
+use rgb_core::archive::{Container, extract_rom};
+use rgb_core::cartridge::Cartridge;
+use std::fs;
+
+fn fixture(name: &str) -> Vec<u8> {
+    let path = format!("{}/../test-roms/{}", env!("CARGO_MANIFEST_DIR"), name);
+    fs::read(&path).unwrap_or_else(|e| panic!("Failed to read {}: {}", path, e))
+}
+
+fn assert_archive_rom(cartridge: Cartridge) {
+    assert_eq!(cartridge.header.title, "ARCHIVE");
+    assert_eq!(cartridge.rom.len(), 0x8000);
+}
+
+#[test]
+fn test_load_zip() {
+    let data = fixture("archive.zip");
+    assert_eq!(Container::detect(&data), Container::Zip);
+    assert_archive_rom(Cartridge::from_archive(data).unwrap());
+}
+
+#[test]
+fn test_load_gzip() {
+    let data = fixture("archive.gb.gz");
+    assert_eq!(Container::detect(&data), Container::Gzip);
+    assert_archive_rom(Cartridge::from_archive(data).unwrap());
+}
+
+#[test]
+fn test_load_plain_rom() {
+    let rom = extract_rom(fixture("archive.zip")).unwrap();
+    assert_archive_rom(Cartridge::from_archive(rom).unwrap());
+}
+
+#[test]
+fn test_corrupt_gzip() {
+    let mut data = fixture("archive.gb.gz");
+    let crc = data.len() - 8;
+    data[crc] ^= 0xFF;
+    let error = Cartridge::from_archive(data).unwrap_err();
+    assert!(error.to_string().contains("CRC mismatch"));
+}
+
+#[test]
+fn test_load_patched_zip() {
+    // IPS record writing 0xAA 0xBB at 0x0150, after the header
+    let mut patch = b"PATCH".to_vec();
+    patch.extend_from_slice(&[0x00, 0x01, 0x50, 0x00, 0x02, 0xAA, 0xBB]);
+    patch.extend_from_slice(b"EOF");
+
+    let cartridge = Cartridge::from_archive_patched(fixture("archive.zip"), Some(&patch)).unwrap();
+    assert_eq!(cartridge.rom[0x0150..0x0152], [0xAA, 0xBB]);
+    assert_archive_rom(cartridge);
+
+    let error = Cartridge::from_archive_patched(fixture("archive.zip"), Some(b"NOPE")).unwrap_err();
+    assert!(error.to_string().contains("Unknown patch format"));
+}
+
+#[cfg(feature = "archive-7z")]
+#[test]
+fn test_load_7z() {
+    let data = fixture("archive.7z");
+    assert_eq!(Container::detect(&data), Container::SevenZip);
+    assert_archive_rom(Cartridge::from_archive(data).unwrap());
+}
+
+#[cfg(not(feature = "archive-7z"))]
+#[test]
+fn test_7z_needs_feature() {
+    let error = Cartridge::from_archive(fixture("archive.7z")).unwrap_err();
+    assert!(error.to_string().contains("archive-7z"));
+}
@@ -2,40 +2,154 @@
 ///
 /// This module contains test runners for various Blargg test ROMs
 /// that validate Game Boy emulator accuracy.
+///
+/// The ROMs report their result in one of three ways, all of which are
+/// checked here:
+/// - Text written to the serial port
+/// - Text and a result code in cartridge RAM at 0xA000, marked by the
+///   signature DE B0 61 at 0xA001-0xA003
+/// - Text on the screen only (ROMs without cartridge RAM)
+///
+/// The final screen is also hashed and compared against a snapshot: a
+/// regression hash of this emulator's own output when the ROM passed, not
+/// a hardware reference.
 use rgb_core::cartridge::Cartridge;
+use rgb_core::mmu::Mmu;
+use rgb_core::model::Model;
+use rgb_core::ppu::DOTS_PER_FRAME;
 use rgb_core::system::GameBoy;
 
 const SERIAL_DATA: u16 = 0xFF01;
 const SERIAL_CONTROL: u16 = 0xFF02;
+const SCY: u16 = 0xFF42;
+const SCX: u16 = 0xFF43;
 
-/// Common test runner for Blargg test ROMs
+/// Result protocol in cartridge RAM
+const RESULT_STATUS: u16 = 0xA000;
+const RESULT_SIGNATURE: u16 = 0xA001;
+const RESULT_TEXT: u16 = 0xA004;
+const SIGNATURE: [u8; 3] = [0xDE, 0xB0, 0x61];
+/// Status written while the test is still running
+const STATUS_RUNNING: u8 = 0x80;
+
+/// Instructions between checks of the text on screen
+const SCREEN_CHECK_INTERVAL: u64 = 100_000;
+/// Frames to keep running after completion so the final text is displayed
+const SETTLE_FRAMES: u64 = 10;
+
+/// Outcome of running a Blargg test ROM
+struct BlarggResult {
+    /// Text output (serial port, cartridge RAM or screen)
+    output: String,
+    passed: bool,
+    /// Hash of the screen when the test finished
+    screen_hash: u64,
+}
+
+/// Load a ROM from the `test-roms` directory
 ///
-/// Runs a test ROM and collects serial output until the test completes.
-/// Returns the collected output string and whether the test passed.
-fn run_blargg_test(rom_name: &str, max_instructions: u64) -> (String, bool) {
+/// The hardware model is selected from the cartridge header unless given.
+fn load_rom(rom_name: &str, model: Option<Model>) -> GameBoy<Mmu> {
     let rom_path = format!(
         "{}/{}",
         concat!(env!("CARGO_MANIFEST_DIR"), "/../test-roms"),
         rom_name
     );
 
-    let cartridge =
-        Cartridge::load(&rom_path).expect(&format!("Failed to load test ROM: {}", rom_name));
-    let mut gameboy = GameBoy::with_cartridge(cartridge);
+    let cartridge = Cartridge::load(&rom_path)
+        .unwrap_or_else(|_| panic!("Failed to load test ROM: {}", rom_name));
+    match model {
+        Some(model) => GameBoy::with_model(cartridge, model),
+        None => GameBoy::with_cartridge(cartridge),
+    }
+}
+
+/// Read the result protocol in cartridge RAM
+///
+/// Returns the status code and text, or None if the ROM doesn't use it.
+fn memory_result(gameboy: &GameBoy<Mmu>) -> Option<(u8, String)> {
+    let signature = [0, 1, 2].map(|i| gameboy.read(RESULT_SIGNATURE + i));
+    if signature != SIGNATURE {
+        return None;
+    }
+
+    let text = (RESULT_TEXT..0xC000)
+        .map(|addr| gameboy.read(addr))
+        .take_while(|&byte| byte != 0)
+        .map(|byte| byte as char)
+        .collect();
+    Some((gameboy.read(RESULT_STATUS), text))
+}
+
+/// Text on screen
+///
+/// The ROMs' console uses the ASCII code as tile number in the background
+/// map, which is scrolled through SCY as lines are printed.
+fn screen_text(gameboy: &GameBoy<Mmu>) -> String {
+    let first_row = gameboy.read(SCY) as u16 / 8;
+    let first_column = gameboy.read(SCX) as u16 / 8;
+
+    (0..18)
+        .map(|row| {
+            let line: String = (0..20)
+                .map(|column| {
+                    let x = (first_column + column) % 32;
+                    let y = (first_row + row) % 32;
+                    // Bypass the PPU's VRAM access restrictions
+                    match gameboy.mmu.read(0x9800 + y * 32 + x) {
+                        tile @ 0x20..=0x7E => tile as char,
+                        _ => ' ',
+                    }
+                })
+                .collect();
+            line.trim_end().to_string()
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Hash the screen (FNV-1a over every pixel)
+fn screen_hash(gameboy: &GameBoy<Mmu>) -> u64 {
+    gameboy
+        .ppu
+        .framebuffer()
+        .iter()
+        .flatten()
+        .flat_map(|pixel| pixel.to_le_bytes())
+        .fold(0xCBF2_9CE4_8422_2325, |hash, byte| {
+            (hash ^ byte as u64).wrapping_mul(0x0100_0000_01B3)
+        })
+}
+
+/// Common test runner for Blargg test ROMs
+///
+/// Runs a test ROM until it reports a result through the serial port,
+/// cartridge RAM or the screen, or `max_instructions` have been executed.
+fn run_blargg_test(rom_name: &str, max_instructions: u64) -> BlarggResult {
+    run_blargg_test_on(rom_name, None, max_instructions)
+}
+
+/// Run a Blargg test ROM on a specific hardware model
+fn run_blargg_test_on(rom_name: &str, model: Option<Model>, max_instructions: u64) -> BlarggResult {
+    let mut gameboy = load_rom(rom_name, model);
 
+    let mut serial = String::new();
     let mut output = String::new();
+    let mut passed = false;
+    // The result in cartridge RAM is only valid once the ROM marked it as running
+    let mut memory_running = false;
 
     println!("Running {}...", rom_name);
 
     for i in 0..max_instructions {
-        gameboy.step();
+        gameboy.step_with_ppu();
 
         // Check serial port every instruction
         let serial_control = gameboy.read(SERIAL_CONTROL);
         if serial_control & 0x80 != 0 {
             let byte = gameboy.read(SERIAL_DATA);
             if byte != 0 {
-                output.push(byte as char);
+                serial.push(byte as char);
                 print!("{}", byte as char);
             }
             gameboy.write(SERIAL_CONTROL, 0);
@@ -47,7 +161,29 @@ fn run_blargg_test(rom_name: &str, max_instructions: u64) -> (String, bool) {
         }
 
         // Check for completion
-        if output.contains("Passed") || output.contains("Failed") {
+        let mut completed = false;
+        if serial.contains("Passed") || serial.contains("Failed") {
+            output = serial.clone();
+            passed = serial.contains("Passed");
+            completed = true;
+        } else if let Some((status, text)) = memory_result(&gameboy) {
+            if status == STATUS_RUNNING {
+                memory_running = true;
+            } else if memory_running {
+                output = text;
+                passed = status == 0;
+                completed = true;
+            }
+        } else if i % SCREEN_CHECK_INTERVAL == 0 {
+            let text = screen_text(&gameboy);
+            if text.contains("Passed") || text.contains("Failed") {
+                passed = text.contains("Passed");
+                output = text;
+                completed = true;
+            }
+        }
+
+        if completed {
             println!(
                 "\nTest completed after {} million instructions",
                 i / 1_000_000
@@ -56,48 +192,96 @@ fn run_blargg_test(rom_name: &str, max_instructions: u64) -> (String, bool) {
         }
     }
 
-    let passed = output.contains("Passed");
-    (output, passed)
+    if output.is_empty() {
+        // Timed out: show whatever was reported so far
+        output = if serial.is_empty() {
+            screen_text(&gameboy)
+        } else {
+            serial
+        };
+    }
+
+    // Let the final result reach the screen before hashing it
+    let target = gameboy.dot_cycles + SETTLE_FRAMES * DOTS_PER_FRAME;
+    while gameboy.dot_cycles < target {
+        gameboy.step_with_ppu();
+    }
+
+    BlarggResult {
+        output,
+        passed,
+        screen_hash: screen_hash(&gameboy),
+    }
+}
+
+/// Subtest results in the output ("01:ok", "02:01", ...)
+fn subtest_results(output: &str) -> Vec<(&str, bool)> {
+    output
+        .split_whitespace()
+        .filter_map(|token| token.split_once(':'))
+        .filter(|(number, result)| {
+            number.len() == 2 && number.bytes().all(|b| b.is_ascii_digit()) && !result.is_empty()
+        })
+        .map(|(number, result)| (number, result == "ok"))
+        .collect()
 }
 
 /// Helper function to print test results
-fn print_test_results(test_name: &str, output: &str, passed: bool) {
+fn print_test_results(test_name: &str, result: &BlarggResult) {
     println!("\n=== {} RESULTS ===", test_name.to_uppercase());
-    println!("{}", output);
+    println!("{}", result.output);
+    println!("Screen hash: 0x{:016X}", result.screen_hash);
     println!("====================\n");
 
-    if passed {
+    if result.passed {
         println!("✓ {} PASSED!", test_name);
     } else {
         println!("✗ {} FAILED", test_name);
+    }
 
-        // Parse and display individual test failures for cpu_instrs
-        if test_name.contains("CPU") {
-            for i in 1..=11 {
-                let test_str = format!("{:02}:ok", i);
-                if output.contains(&test_str) {
-                    println!("  ✓ Test {:02}: PASSED", i);
-                } else {
-                    println!("  ✗ Test {:02}: FAILED or incomplete", i);
-                }
-            }
+    // Summary of the individual tests in multi-test ROMs
+    for (number, passed) in subtest_results(&result.output) {
+        if passed {
+            println!("  ✓ Test {}: PASSED", number);
+        } else {
+            println!("  ✗ Test {}: FAILED", number);
         }
     }
 }
 
+/// Compare the final screen against its snapshot hash
+///
+/// After an intended change to the output (palettes, rendering), check the
+/// new screen and copy the hash from the failure message into the test.
+fn assert_screen(result: &BlarggResult, snapshot_hash: u64) {
+    assert_eq!(
+        result.screen_hash, snapshot_hash,
+        "Final screen differs from the snapshot (new hash 0x{:016X})",
+        result.screen_hash
+    );
+}
+
 /// Test CPU instructions - This is the main test that validates all CPU instructions work correctly
 #[test]
 fn test_cpu_instrs() {
     println!("\n=== Blargg CPU Instructions Test ===");
     println!("This tests all CPU instructions for correct behavior.\n");
 
-    let (output, passed) = run_blargg_test("cpu_instrs.gb", 500_000_000);
-    print_test_results("CPU Instructions", &output, passed);
+    // The header allows CGB; run the DMG path as well
+    for (model, screen) in [
+        (Model::Dmg, 0x4AD3_646B_AA5E_5B26),
+        (Model::Cgb, 0x9AFE_A717_5BF9_B21B),
+    ] {
+        let result = run_blargg_test_on("cpu_instrs.gb", Some(model), 500_000_000);
+        print_test_results("CPU Instructions", &result);
 
-    assert!(
-        passed,
-        "CPU instruction tests failed! See output above for details."
-    );
+        assert!(
+            result.passed,
+            "CPU instruction tests failed on {:?}! See output above for details.",
+            model
+        );
+        assert_screen(&result, screen);
+    }
 }
 
 /// Test instruction timing - validates the cycle count for each instruction
@@ -106,11 +290,127 @@ fn test_instr_timing() {
     println!("\n=== Blargg Instruction Timing Test ===");
     println!("This tests the cycle timing of all CPU instructions.\n");
 
-    let (output, passed) = run_blargg_test("instr_timing.gb", 100_000_000);
-    print_test_results("Instruction Timing", &output, passed);
+    // The header allows CGB; run the DMG path as well
+    for (model, screen) in [
+        (Model::Dmg, 0x15CB_3D74_569E_403E),
+        (Model::Cgb, 0x8549_4AAA_8788_C2AF),
+    ] {
+        let result = run_blargg_test_on("instr_timing.gb", Some(model), 100_000_000);
+        print_test_results("Instruction Timing", &result);
+
+        assert!(
+            result.passed,
+            "Instruction timing test failed on {:?}! See output above for details.",
+            model
+        );
+        assert_screen(&result, screen);
+    }
+}
+
+/// Test memory access timing - validates the M-cycle at which instructions read and write memory
+#[test]
+fn test_mem_timing() {
+    println!("\n=== Blargg Memory Timing Test ===");
+    println!("This tests when instructions access memory within their cycles.\n");
+
+    // The header allows CGB; run the DMG path as well
+    for (model, screen) in [
+        (Model::Dmg, 0xB5B0_1491_DAE4_17DD),
+        (Model::Cgb, 0xE66A_0AEF_BB33_BE51),
+    ] {
+        let result = run_blargg_test_on("mem_timing.gb", Some(model), 100_000_000);
+        print_test_results("Memory Timing", &result);
+
+        assert!(
+            result.passed,
+            "Memory timing test failed on {:?}! See output above for details.",
+            model
+        );
+        assert_screen(&result, screen);
+    }
+}
+
+/// Test memory access timing of the remaining instructions (read-modify-write, stack, etc.)
+#[test]
+#[ignore = "test-roms/mem_timing-2.gb is a saved HTML page, not the ROM"]
+fn test_mem_timing_2() {
+    println!("\n=== Blargg Memory Timing 2 Test ===");
+    println!("This tests when more instructions access memory within their cycles.\n");
+
+    let result = run_blargg_test("mem_timing-2.gb", 100_000_000);
+    print_test_results("Memory Timing 2", &result);
 
     assert!(
-        passed,
-        "Instruction timing test failed! See output above for details."
+        result.passed,
+        "Memory timing 2 test failed! See output above for details."
+    );
+}
+
+/// Test the HALT bug - the byte after HALT is read twice when IME=0 and an interrupt is pending
+#[test]
+#[ignore = "reports Failed: HALT with IME=0 does not match hardware for every IE/IF combination"]
+fn test_halt_bug() {
+    println!("\n=== Blargg HALT Bug Test ===");
+    println!("This tests HALT with interrupts disabled and pending.\n");
+
+    let result = run_blargg_test("halt_bug.gb", 100_000_000);
+    print_test_results("HALT Bug", &result);
+
+    assert!(
+        result.passed,
+        "HALT bug test failed! See output above for details."
+    );
+}
+
+/// Test interrupt timing - validates when interrupts are serviced, in normal and double speed
+// The console waits for VBlank by polling LY up to 1250 times, about one
+// frame at normal speed. After the ROM switches to double speed the wait
+// gives up half way through the frame, and the writes that follow are
+// ignored when they land in mode 3, as on hardware.
+#[test]
+#[ignore = "result is not detected: in double speed the console's VBlank wait times out and its VRAM writes during mode 3 are dropped, so \"Failed\" shows as \"iled\""]
+fn test_interrupt_time() {
+    println!("\n=== Blargg Interrupt Timing Test ===");
+    println!("This tests the cycle at which interrupts are serviced.\n");
+
+    let result = run_blargg_test("interrupt_time.gb", 100_000_000);
+    print_test_results("Interrupt Timing", &result);
+
+    assert!(
+        result.passed,
+        "Interrupt timing test failed! See output above for details."
+    );
+}
+
+/// Test OAM corruption - the DMG corrupts OAM when 16-bit registers pointing into it change during mode 2
+#[test]
+fn test_oam_bug() {
+    println!("\n=== Blargg OAM Bug Test ===");
+    println!("This tests OAM corruption caused by the CPU during OAM search.\n");
+
+    // The header allows CGB, which doesn't have the bug
+    let result = run_blargg_test_on("oam_bug.gb", Some(Model::Dmg), 200_000_000);
+    print_test_results("OAM Bug", &result);
+
+    assert!(
+        result.passed,
+        "OAM bug test failed! See output above for details."
+    );
+    assert_screen(&result, 0x46EB_DA30_A9ED_ECA5);
+}
+
+/// Test the sound hardware - registers, length counters, sweep and wave RAM
+#[test]
+#[ignore = "sound (APU) is not emulated"]
+fn test_dmg_sound() {
+    println!("\n=== Blargg DMG Sound Test ===");
+    println!("This tests the behavior of the sound registers.\n");
+
+    let result = run_blargg_test("dmg_sound.gb", 200_000_000);
+    print_test_results("DMG Sound", &result);
+
+    assert!(
+        result.passed,
+        "DMG sound test failed! See output above for details."
     );
 }
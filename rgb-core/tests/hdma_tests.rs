@@ -0,0 +1,156 @@
+/// CGB VRAM DMA (HDMA) tests
+///
+/// These drive a ROM-less Game Boy (flat memory) running small hand-written
+/// programs that program HDMA1-HDMA5 the way games do.
+use rgb_core::io::{HDMA5, LY};
+use rgb_core::memory::{FlatMemory, Memory};
+use rgb_core::model::Model;
+use rgb_core::ppu::Mode;
+use rgb_core::system::GameBoy;
+
+const PROGRAM: u16 = 0xC000;
+const SOURCE: u16 = 0xD000;
+
+/// Create a CGB with the program loaded at `PROGRAM` and 64 bytes of
+/// recognizable data at `SOURCE`
+fn setup(program: &[u8]) -> GameBoy<FlatMemory> {
+    let mut gameboy = GameBoy::<FlatMemory>::new();
+    gameboy.model = Model::Cgb;
+
+    for (i, &byte) in program.iter().enumerate() {
+        gameboy.write(PROGRAM + i as u16, byte);
+    }
+    for i in 0..0x40 {
+        gameboy.write(SOURCE + i, 0x80 | i as u8);
+    }
+
+    gameboy.pc = PROGRAM;
+    gameboy
+}
+
+/// Program HDMA1-HDMA4 with SOURCE -> 0x8000 and write `hdma5`,
+/// then spin forever
+fn transfer_program(hdma5: u8) -> Vec<u8> {
+    vec![
+        0x3E, 0xD0, // LD A,$D0
+        0xE0, 0x51, // LDH (HDMA1),A
+        0xAF, //       XOR A
+        0xE0, 0x52, // LDH (HDMA2),A
+        0xE0, 0x53, // LDH (HDMA3),A
+        0xE0, 0x54, // LDH (HDMA4),A
+        0x3E, hdma5, // LD A,hdma5
+        0xE0, 0x55, // LDH (HDMA5),A
+        0x18, 0xFE, // JR -2
+    ]
+}
+
+/// Number of instructions in `transfer_program` before the spin loop
+const SETUP_INSTRUCTIONS: usize = 8;
+
+/// Inspect VRAM directly, bypassing the PPU access restrictions
+fn vram(gameboy: &GameBoy<FlatMemory>, addr: u16) -> u8 {
+    gameboy.mmu.read(addr)
+}
+
+fn vram_matches_source(gameboy: &GameBoy<FlatMemory>, len: u16) -> bool {
+    (0..len).all(|i| vram(gameboy, 0x8000 + i) == gameboy.read(SOURCE + i))
+}
+
+/// Step until the PPU has entered the given mode
+fn step_until_mode(gameboy: &mut GameBoy<FlatMemory>, mode: Mode) {
+    while gameboy.ppu.mode() == mode {
+        gameboy.step();
+    }
+    while gameboy.ppu.mode() != mode {
+        gameboy.step();
+    }
+}
+
+#[test]
+fn test_general_purpose_dma_copies_everything_at_once() {
+    let mut gameboy = setup(&transfer_program(0x03));
+
+    for _ in 0..SETUP_INSTRUCTIONS - 1 {
+        gameboy.step();
+    }
+    let cycles_before = gameboy.cycles;
+    gameboy.step(); // LDH (HDMA5),A
+
+    // 4 blocks of 8 M-cycles on top of the 12-cycle LDH
+    assert_eq!(gameboy.cycles - cycles_before, 12 + 4 * 32);
+    assert_eq!(gameboy.read(HDMA5), 0xFF);
+    assert!(vram_matches_source(&gameboy, 0x40));
+}
+
+#[test]
+fn test_hblank_dma_copies_one_block_per_hblank() {
+    let mut gameboy = setup(&transfer_program(0x81));
+
+    // Start the transfer outside of HBlank
+    step_until_mode(&mut gameboy, Mode::OamSearch);
+    gameboy.pc = PROGRAM;
+    for _ in 0..SETUP_INSTRUCTIONS {
+        gameboy.step();
+    }
+    assert_ne!(gameboy.ppu.mode(), Mode::HBlank);
+    assert_eq!(gameboy.read(HDMA5), 0x01);
+    assert_eq!(vram(&gameboy, 0x8000), 0x00);
+
+    // First HBlank: one block
+    step_until_mode(&mut gameboy, Mode::HBlank);
+    assert_eq!(gameboy.read(HDMA5), 0x00);
+    assert!(vram_matches_source(&gameboy, 0x10));
+    assert_eq!(vram(&gameboy, 0x8010), 0x00);
+
+    // Second HBlank: transfer complete
+    let line = gameboy.read(LY);
+    step_until_mode(&mut gameboy, Mode::HBlank);
+    assert_ne!(gameboy.read(LY), line);
+    assert_eq!(gameboy.read(HDMA5), 0xFF);
+    assert!(vram_matches_source(&gameboy, 0x20));
+    assert_eq!(vram(&gameboy, 0x8020), 0x00);
+}
+
+#[test]
+fn test_hblank_dma_can_be_cancelled() {
+    let mut program = transfer_program(0x83);
+    // Replace the spin loop: wait for LY to change, then cancel
+    program.truncate(program.len() - 2);
+    program.extend_from_slice(&[
+        0xF0, 0x44, // LDH A,(LY)
+        0x47, //       LD B,A
+        0xF0, 0x44, // .wait: LDH A,(LY)
+        0xB8, //       CP B
+        0x28, 0xFB, // JR Z,.wait
+        0xAF, //       XOR A
+        0xE0, 0x55, // LDH (HDMA5),A
+        0x18, 0xFE, // JR -2
+    ]);
+
+    let mut gameboy = setup(&program);
+    step_until_mode(&mut gameboy, Mode::OamSearch);
+    gameboy.pc = PROGRAM;
+
+    // Run long enough for the cancel to happen, and a few more lines after
+    for _ in 0..2000 {
+        gameboy.step();
+    }
+
+    // One block was copied before LY changed, then the transfer stopped
+    assert_eq!(gameboy.read(HDMA5), 0x82);
+    assert!(vram_matches_source(&gameboy, 0x10));
+    assert_eq!(vram(&gameboy, 0x8010), 0x00);
+}
+
+#[test]
+fn test_hdma_registers_ignored_on_dmg() {
+    let mut gameboy = setup(&transfer_program(0x00));
+    gameboy.model = Model::Dmg;
+
+    for _ in 0..SETUP_INSTRUCTIONS {
+        gameboy.step();
+    }
+
+    assert_eq!(gameboy.read(HDMA5), 0xFF);
+    assert_eq!(vram(&gameboy, 0x8000), 0x00);
+}
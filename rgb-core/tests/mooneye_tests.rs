@@ -0,0 +1,45 @@
+/// Mooneye-gb / SameSuite test ROM suite
+///
+/// The suites are not bundled, so the test is ignored by default: set
+/// `MOONEYE_ROMS` to a local directory of ROMs (e.g. the mooneye-gb
+/// `acceptance` build) and run it with `--ignored` to run every ROM below it.
+/// `MOONEYE_TIMEOUT` overrides the timeout in frames.
+use rgb_core::harness::{self, DEFAULT_TIMEOUT_FRAMES, Outcome, TestRom};
+use std::env;
+use std::path::Path;
+use std::thread;
+
+#[test]
+#[ignore = "set MOONEYE_ROMS to a local mooneye-gb build"]
+fn test_mooneye_suite() {
+    let dir = env::var("MOONEYE_ROMS").expect("MOONEYE_ROMS must point to a directory of ROMs");
+    let dir = Path::new(&dir);
+
+    let timeout_frames = env::var("MOONEYE_TIMEOUT")
+        .ok()
+        .map(|frames| frames.parse().expect("MOONEYE_TIMEOUT must be a number"))
+        .unwrap_or(DEFAULT_TIMEOUT_FRAMES);
+
+    let roms: Vec<_> = harness::find_test_roms(dir)
+        .unwrap()
+        .into_iter()
+        .map(|path| TestRom::new(path, timeout_frames))
+        .collect();
+    assert!(!roms.is_empty(), "No ROMs found in {}", dir.display());
+
+    let jobs = thread::available_parallelism().map_or(1, |n| n.get());
+    let results = harness::run_test_roms(&roms, jobs);
+    println!("{}", harness::format_results(&results, dir));
+
+    // Accuracy is tracked through the table; only ROMs that can't run fail the test
+    let errors: Vec<_> = results
+        .iter()
+        .filter(|result| result.outcome == Outcome::Error)
+        .map(|result| format!("{}: {}", result.path.display(), result.detail))
+        .collect();
+    assert!(
+        errors.is_empty(),
+        "Failed to load ROMs:\n{}",
+        errors.join("\n")
+    );
+}
@@ -1,13 +1,64 @@
+use js_sys::Uint8ClampedArray;
 use rgb_core::{
     cartridge::Cartridge,
-    joypad::Button,
-    ppu::{Framebuffer, SCREEN_HEIGHT, SCREEN_WIDTH},
+    joypad::{Button, JoypadState, OpposingDirections},
+    ppu::{DOTS_PER_FRAME, DOTS_PER_SECOND, SCREEN_HEIGHT, SCREEN_WIDTH},
     system::GameBoy,
+    video::{
+        ColorCorrection, Palette,
+        filters::{Filter, Ghosting},
+    },
 };
 use wasm_bindgen::prelude::*;
-use wasm_bindgen::Clamped;
 use web_sys::{CanvasRenderingContext2d, HtmlCanvasElement, ImageData};
 
+/// Slowest and fastest speed multipliers
+const MIN_SPEED: f64 = 0.25;
+const MAX_SPEED: f64 = 8.0;
+/// Speed value for running as fast as possible
+const UNCAPPED: f64 = 0.0;
+/// Milliseconds of emulation per animation frame when uncapped
+const UNCAPPED_FRAME_MS: f64 = 12.0;
+/// Longest wall-clock step emulated at once, so a tab coming back from
+/// the background doesn't try to catch up on seconds of emulation
+const MAX_ELAPSED_MS: f64 = 250.0;
+/// Interval the FPS and speed readouts are averaged over
+const STATS_INTERVAL_MS: f64 = 500.0;
+
+/// Frames and emulated time measured against wall-clock time
+#[derive(Default)]
+struct Stats {
+    elapsed_ms: f64,
+    frames: u32,
+    dots: u64,
+    /// Emulated frames per second over the last interval
+    fps: f64,
+    /// Emulated time per wall-clock time over the last interval
+    speed: f64,
+}
+
+impl Stats {
+    fn record(&mut self, elapsed_ms: f64, frames: u32, dots: u64) {
+        self.elapsed_ms += elapsed_ms;
+        self.frames += frames;
+        self.dots += dots;
+
+        if self.elapsed_ms >= STATS_INTERVAL_MS {
+            let seconds = self.elapsed_ms / 1000.0;
+            self.fps = self.frames as f64 / seconds;
+            self.speed = self.dots as f64 / DOTS_PER_SECOND as f64 / seconds;
+            *self = Stats {
+                fps: self.fps,
+                speed: self.speed,
+                ..Stats::default()
+            };
+        }
+    }
+}
+
+/// Largest integer scale (enough for a Game Boy screen on a 4K display)
+const MAX_SCALE: u32 = 32;
+
 /// Set up panic hook for better error messages in the browser console
 #[wasm_bindgen(start)]
 pub fn main() {
@@ -19,8 +70,32 @@ pub fn main() {
 pub struct Emulator {
     gameboy: Option<GameBoy>,
     running: bool,
+    /// Visible canvas, `scale` times the frame size
     ctx: CanvasRenderingContext2d,
+    /// Offscreen canvas at the filtered frame size, upscaled with drawImage
+    frame_canvas: HtmlCanvasElement,
+    frame_ctx: CanvasRenderingContext2d,
+    /// Pixels put on `frame_canvas`, rebuilt when the frame size or
+    /// filter changes. The ImageData shares its storage with the array.
+    frame_pixels: Uint8ClampedArray,
+    frame_image: ImageData,
     scale: u32,
+    /// Current frame as RGBA
+    rgba: Vec<u8>,
+    width: usize,
+    height: usize,
+    filter: Filter,
+    /// Output of `filter` (unused without a filter)
+    filtered: Vec<u8>,
+    ghosting: Option<Ghosting>,
+    palette: Palette,
+    color_correction: ColorCorrection,
+    opposing_directions: OpposingDirections,
+    /// Speed multiplier (UNCAPPED = as fast as possible)
+    speed: f64,
+    /// Dots of emulation owed to wall-clock time, carried between calls
+    dot_credit: f64,
+    stats: Stats,
 }
 
 #[wasm_bindgen]
@@ -35,35 +110,56 @@ impl Emulator {
             .ok_or(format!("Canvas '{}' not found", canvas_id))?
             .dyn_into::<HtmlCanvasElement>()?;
 
-        // Set canvas size
-        canvas.set_width(SCREEN_WIDTH as u32 * scale);
-        canvas.set_height(SCREEN_HEIGHT as u32 * scale);
-
         let ctx = canvas
             .get_context("2d")?
             .ok_or("No 2d context")?
             .dyn_into::<CanvasRenderingContext2d>()?;
 
-        // Disable image smoothing for crisp pixels
-        ctx.set_image_smoothing_enabled(false);
+        let frame_canvas = document
+            .create_element("canvas")?
+            .dyn_into::<HtmlCanvasElement>()?;
+        let frame_ctx = frame_canvas
+            .get_context("2d")?
+            .ok_or("No 2d context")?
+            .dyn_into::<CanvasRenderingContext2d>()?;
+        // Sized for the first frame by resize_frame below
+        let frame_pixels = Uint8ClampedArray::new_with_length(4);
+        let frame_image = ImageData::new_with_js_u8_clamped_array_and_sh(&frame_pixels, 1, 1)?;
 
-        Ok(Emulator {
+        let mut emulator = Emulator {
             gameboy: None,
             running: false,
             ctx,
-            scale,
-        })
+            frame_canvas,
+            frame_ctx,
+            frame_pixels,
+            frame_image,
+            scale: scale.clamp(1, MAX_SCALE),
+            rgba: Vec::new(),
+            width: 0,
+            height: 0,
+            filter: Filter::default(),
+            filtered: Vec::new(),
+            ghosting: None,
+            palette: Palette::default(),
+            color_correction: ColorCorrection::default(),
+            opposing_directions: OpposingDirections::default(),
+            speed: 1.0,
+            dot_credit: 0.0,
+            stats: Stats::default(),
+        };
+        emulator.resize_frame(SCREEN_WIDTH, SCREEN_HEIGHT)?;
+        Ok(emulator)
     }
 
-    /// Load a ROM from bytes
+    /// Load a ROM from bytes, or from a zip or gzip archive holding one
     pub fn load_rom(&mut self, rom_data: &[u8]) -> Result<(), JsValue> {
-        let cartridge = Cartridge::from_bytes(rom_data.to_vec())
-            .map_err(|e| JsValue::from_str(&format!("Failed to load ROM: {}", e)))?;
-
-        self.gameboy = Some(GameBoy::with_cartridge(cartridge));
-        self.running = false;
+        self.load(rom_data, None)
+    }
 
-        Ok(())
+    /// Load a ROM (or archive) with an IPS, BPS or UPS patch applied
+    pub fn load_patched_rom(&mut self, rom_data: &[u8], patch_data: &[u8]) -> Result<(), JsValue> {
+        self.load(rom_data, Some(patch_data))
     }
 
     /// Check if a ROM is loaded
@@ -88,120 +184,293 @@ impl Emulator {
         self.running = false;
     }
 
-    pub fn reset(&mut self) -> Result<(), JsValue> {
-        if self.gameboy.is_some() {
-            self.running = false;
-            Ok(())
+    /// Restart the loaded ROM, keeping cartridge RAM unless `hard` is set
+    ///
+    /// Emulation keeps running if it was running.
+    pub fn reset(&mut self, hard: bool) -> Result<(), JsValue> {
+        let gameboy = self
+            .gameboy
+            .as_mut()
+            .ok_or_else(|| JsValue::from_str("No ROM loaded"))?;
+
+        if hard {
+            gameboy.hard_reset();
         } else {
-            Err(JsValue::from_str("No ROM loaded"))
+            gameboy.reset();
         }
+        self.dot_credit = 0.0;
+        Ok(())
     }
 
-    /// Step the emulator for one frame (approximately 70224 cycles)
-    /// Returns true when complete
-    pub fn step_frame(&mut self) -> Result<bool, JsValue> {
+    /// Select a built-in DMG palette by name (classic, grayscale, pocket, light)
+    pub fn set_palette(&mut self, name: &str) -> Result<(), JsValue> {
+        let palette = Palette::builtin(name)
+            .ok_or_else(|| JsValue::from_str(&format!("Unknown palette '{}'", name)))?;
+        self.apply_palette(palette);
+        Ok(())
+    }
+
+    /// Use a custom DMG palette given as four 0xRRGGBB colors, lightest first
+    pub fn set_custom_palette(&mut self, c0: u32, c1: u32, c2: u32, c3: u32) {
+        self.apply_palette(Palette::from_hex([c0, c1, c2, c3]));
+    }
+
+    /// Select the CGB color correction by name (none, curves, lcd)
+    pub fn set_color_correction(&mut self, name: &str) -> Result<(), JsValue> {
+        let correction = ColorCorrection::from_name(name)
+            .ok_or_else(|| JsValue::from_str(&format!("Unknown color correction '{}'", name)))?;
+        self.color_correction = correction;
         if let Some(ref mut gameboy) = self.gameboy {
-            // Run for one full frame (154 scanlines * 456 dots per scanline)
-            const CYCLES_PER_FRAME: u64 = 70224;
-            let start_cycles = gameboy.cycles;
+            gameboy.ppu.set_color_correction(correction);
+        }
+        Ok(())
+    }
 
-            // Execute instructions until we've completed a full frame
-            while gameboy.cycles - start_cycles < CYCLES_PER_FRAME {
-                gameboy.step_with_ppu();
-            }
+    /// Set the speed multiplier (0.25 to 8), or 0 to run uncapped
+    pub fn set_speed(&mut self, speed: f64) -> Result<(), JsValue> {
+        if speed != UNCAPPED && !(MIN_SPEED..=MAX_SPEED).contains(&speed) {
+            return Err(JsValue::from_str(&format!("Unsupported speed {}", speed)));
+        }
+        self.speed = speed;
+        self.dot_credit = 0.0;
+        Ok(())
+    }
 
-            Ok(true)
+    /// Emulate `elapsed_ms` of wall-clock time at the current speed
+    ///
+    /// Frames run at the Game Boy's own rate (about 59.73 fps) whatever
+    /// the display refresh rate is; time that doesn't make up a whole frame
+    /// is carried to the next call. Every frame but the last is skipped
+    /// (not drawn). Returns true when a new frame is ready to be rendered.
+    pub fn run_for(&mut self, elapsed_ms: f64) -> Result<bool, JsValue> {
+        let gameboy = self
+            .gameboy
+            .as_mut()
+            .ok_or_else(|| JsValue::from_str("No ROM loaded"))?;
+
+        let elapsed_ms = elapsed_ms.clamp(0.0, MAX_ELAPSED_MS);
+        let start_dots = gameboy.dot_cycles;
+        let mut frames = 0;
+        let mut frame_ready = false;
+
+        if self.speed == UNCAPPED {
+            let start = js_sys::Date::now();
+            gameboy.skip_rendering = true;
+            while js_sys::Date::now() - start < UNCAPPED_FRAME_MS {
+                gameboy.run_frame();
+                frames += 1;
+            }
+            gameboy.skip_rendering = false;
+            frame_ready = gameboy.run_frame().frame_ready;
+            frames += 1;
         } else {
-            Err(JsValue::from_str("No ROM loaded"))
+            let frame_dots = DOTS_PER_FRAME as f64;
+            self.dot_credit += elapsed_ms / 1000.0 * DOTS_PER_SECOND as f64 * self.speed;
+            while self.dot_credit >= frame_dots {
+                // Only the last frame due is drawn
+                gameboy.skip_rendering = self.dot_credit >= 2.0 * frame_dots;
+                let before = gameboy.dot_cycles;
+                frame_ready = gameboy.run_frame().frame_ready;
+                self.dot_credit -= (gameboy.dot_cycles - before) as f64;
+                frames += 1;
+            }
+            gameboy.skip_rendering = false;
         }
+
+        self.stats
+            .record(elapsed_ms, frames, gameboy.dot_cycles - start_dots);
+        Ok(frame_ready)
+    }
+
+    /// Emulated frames per second, averaged over the last half second
+    pub fn fps(&self) -> f64 {
+        self.stats.fps
+    }
+
+    /// Emulation speed relative to real hardware (1.0 = full speed)
+    pub fn emulation_speed(&self) -> f64 {
+        self.stats.speed
     }
 
     /// Render the screen to the canvas
     pub fn render(&mut self) -> Result<(), JsValue> {
         if let Some(ref gameboy) = self.gameboy {
-            let framebuffer = gameboy.ppu.framebuffer();
-            self.render_framebuffer(framebuffer)?;
+            gameboy.framebuffer_rgba(&mut self.rgba);
+            if let Some(ghosting) = &mut self.ghosting {
+                ghosting.apply(&mut self.rgba);
+            }
+            self.apply_filter();
+            self.draw()?;
         }
         Ok(())
     }
 
-    /// Private helper to render framebuffer to canvas
-    fn render_framebuffer(&self, framebuffer: &Framebuffer) -> Result<(), JsValue> {
-        // Convert 2-bit grayscale palette to RGBA
-        // Game Boy colors: 0 = lightest (white), 3 = darkest (black)
-        const COLORS: [[u8; 3]; 4] = [
-            [0x9B, 0xBC, 0x0F], // Lightest (greenish white)
-            [0x8B, 0xAC, 0x0F], // Light
-            [0x30, 0x62, 0x30], // Dark
-            [0x0F, 0x38, 0x0F], // Darkest (greenish black)
-        ];
-
-        // Create scaled RGBA buffer
-        let scaled_width = SCREEN_WIDTH * self.scale as usize;
-        let scaled_height = SCREEN_HEIGHT * self.scale as usize;
-        let mut rgba_data = vec![0u8; scaled_width * scaled_height * 4];
-
-        // Scale up during conversion
-        for y in 0..scaled_height {
-            for x in 0..scaled_width {
-                let src_y = y / self.scale as usize;
-                let src_x = x / self.scale as usize;
-                let pixel_value = framebuffer[src_y][src_x] as usize;
-                let color = COLORS[pixel_value];
-                let idx = (y * scaled_width + x) * 4;
-
-                rgba_data[idx] = color[0]; // R
-                rgba_data[idx + 1] = color[1]; // G
-                rgba_data[idx + 2] = color[2]; // B
-                rgba_data[idx + 3] = 255; // A (fully opaque)
-            }
+    /// Select an upscaling filter by name (none, scale2x, scale3x, hq2x, lcd)
+    pub fn set_filter(&mut self, name: &str) -> Result<(), JsValue> {
+        self.filter = Filter::from_name(name)
+            .ok_or_else(|| JsValue::from_str(&format!("Unknown filter '{}'", name)))?;
+        self.resize_frame(self.width, self.height)?;
+        self.draw()
+    }
+
+    /// Blend each frame with the previous one like the slow DMG LCD
+    pub fn set_ghosting(&mut self, enabled: bool) {
+        self.ghosting = enabled.then(Ghosting::default);
+    }
+
+    /// Change the integer scale of the canvas (1 to 32) and redraw
+    pub fn set_scale(&mut self, scale: u32) -> Result<(), JsValue> {
+        if !(1..=MAX_SCALE).contains(&scale) {
+            return Err(JsValue::from_str(&format!("Unsupported scale {}", scale)));
         }
+        self.scale = scale;
+        self.resize_canvas();
+        self.draw()
+    }
 
-        // Create ImageData and draw to canvas
-        let image_data = ImageData::new_with_u8_clamped_array_and_sh(
-            Clamped(&rgba_data),
-            scaled_width as u32,
-            scaled_height as u32,
-        )?;
+    pub fn scale(&self) -> u32 {
+        self.scale
+    }
+
+    /// Frame width in pixels, larger with an SGB border
+    pub fn frame_width(&self) -> usize {
+        self.width
+    }
+
+    /// Frame height in pixels, larger with an SGB border
+    pub fn frame_height(&self) -> usize {
+        self.height
+    }
 
-        self.ctx.put_image_data(&image_data, 0.0, 0.0)?;
+    /// Private helper to unpack, patch and load a ROM
+    fn load(&mut self, rom_data: &[u8], patch_data: Option<&[u8]>) -> Result<(), JsValue> {
+        let cartridge =
+            Cartridge::from_archive_patched(rom_data.to_vec(), patch_data).map_err(|e| {
+                let action = match patch_data {
+                    Some(_) => "load patched ROM",
+                    None => "load ROM",
+                };
+                JsValue::from_str(&format!("Failed to {}: {}", action, e))
+            })?;
+
+        let mut gameboy = GameBoy::with_cartridge(cartridge);
+        gameboy.ppu.set_palette(self.palette);
+        gameboy.ppu.set_color_correction(self.color_correction);
+        gameboy
+            .joypad
+            .set_opposing_directions(self.opposing_directions);
+
+        // Super Game Boy games are shown inside their border
+        let (width, height) = gameboy.screen_size();
+        self.resize_frame(width, height)?;
+        if let Some(ghosting) = &mut self.ghosting {
+            ghosting.clear();
+        }
+
+        self.gameboy = Some(gameboy);
+        self.running = false;
 
         Ok(())
     }
 
-    pub fn key_down(&mut self, button: u8) {
+    /// Private helper to size the frame buffer and canvases for frames of
+    /// the given size
+    fn resize_frame(&mut self, width: usize, height: usize) -> Result<(), JsValue> {
+        self.width = width;
+        self.height = height;
+        self.rgba.resize(width * height * 4, 0);
+        let frame_width = (width * self.filter.scale()) as u32;
+        let frame_height = (height * self.filter.scale()) as u32;
+        self.frame_canvas.set_width(frame_width);
+        self.frame_canvas.set_height(frame_height);
+        self.frame_pixels = Uint8ClampedArray::new_with_length(frame_width * frame_height * 4);
+        self.frame_image = ImageData::new_with_js_u8_clamped_array_and_sh(
+            &self.frame_pixels,
+            frame_width,
+            frame_height,
+        )?;
+        // Keep the filter output the size of the new frame
+        self.apply_filter();
+        self.resize_canvas();
+        Ok(())
+    }
+
+    /// Private helper to run the filter on the current frame
+    fn apply_filter(&mut self) {
+        if self.filter != Filter::None {
+            self.filter
+                .apply(&self.rgba, self.width, self.height, &mut self.filtered);
+        }
+    }
+
+    /// Private helper to size the visible canvas for the current scale
+    fn resize_canvas(&self) {
+        if let Some(canvas) = self.ctx.canvas() {
+            canvas.set_width(self.width as u32 * self.scale);
+            canvas.set_height(self.height as u32 * self.scale);
+            // Resizing resets the context state; keep the pixels crisp
+            self.ctx.set_image_smoothing_enabled(false);
+        }
+    }
+
+    /// Private helper to remember a palette and apply it to the running game
+    fn apply_palette(&mut self, palette: Palette) {
+        self.palette = palette;
         if let Some(ref mut gameboy) = self.gameboy {
-            let btn = match button {
-                0 => Button::Right,
-                1 => Button::Left,
-                2 => Button::Up,
-                3 => Button::Down,
-                4 => Button::A,
-                5 => Button::B,
-                6 => Button::Select,
-                7 => Button::Start,
-                _ => return,
-            };
-            gameboy.joypad.press(btn);
+            gameboy.ppu.set_palette(palette);
+        }
+    }
+
+    /// Private helper to draw the frame buffer to the canvas
+    ///
+    /// The (filtered) frame is put on the offscreen canvas and scaled up
+    /// to the canvas size by the browser.
+    fn draw(&self) -> Result<(), JsValue> {
+        let frame = match self.filter {
+            Filter::None => &self.rgba,
+            _ => &self.filtered,
+        };
+        self.frame_pixels.copy_from(frame);
+        self.frame_ctx.put_image_data(&self.frame_image, 0.0, 0.0)?;
+
+        self.ctx.draw_image_with_html_canvas_element_and_dw_and_dh(
+            &self.frame_canvas,
+            0.0,
+            0.0,
+            (self.width as u32 * self.scale) as f64,
+            (self.height as u32 * self.scale) as f64,
+        )
+    }
+
+    pub fn key_down(&mut self, button: u8) {
+        if let (Some(gameboy), Some(button)) = (&mut self.gameboy, Button::from_index(button)) {
+            gameboy.joypad.press(button);
         }
     }
 
     pub fn key_up(&mut self, button: u8) {
+        if let (Some(gameboy), Some(button)) = (&mut self.gameboy, Button::from_index(button)) {
+            gameboy.joypad.release(button);
+        }
+    }
+
+    /// Set all buttons at once (bit n = button index n)
+    pub fn set_buttons(&mut self, bits: u8) {
         if let Some(ref mut gameboy) = self.gameboy {
-            let btn = match button {
-                0 => Button::Right,
-                1 => Button::Left,
-                2 => Button::Up,
-                3 => Button::Down,
-                4 => Button::A,
-                5 => Button::B,
-                6 => Button::Select,
-                7 => Button::Start,
-                _ => return,
-            };
-            gameboy.joypad.release(btn);
+            gameboy.joypad.set_state(JoypadState::from_bits(bits));
         }
     }
+
+    /// Choose how Left+Right / Up+Down are reported (allow, last, neutral)
+    pub fn set_opposing_directions(&mut self, name: &str) -> Result<(), JsValue> {
+        let policy = OpposingDirections::from_name(name)
+            .ok_or_else(|| JsValue::from_str(&format!("Unknown direction policy '{}'", name)))?;
+        self.opposing_directions = policy;
+        if let Some(ref mut gameboy) = self.gameboy {
+            gameboy.joypad.set_opposing_directions(policy);
+        }
+        Ok(())
+    }
 }
 
 /// Log a message to the browser console